@@ -0,0 +1,196 @@
+//! Compares the queue of the `Throttle` worker with its previous
+//! implementation: a `Vec` which is scanned on every iteration of the worker,
+//! with per-chat counters rebuilt from the history.
+//!
+//! Both implementations are driven by the same simulation of the worker: a
+//! queue of `n` requests (two per chat) is processed during 10 seconds of
+//! simulated time, with the default limits.
+//!
+//! Run with `cargo bench --features __bench`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use teloxide_core::adaptors::throttle::{queue::ChatQueues, Priority};
+use vecrem::VecExt;
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Delay between iterations of the worker.
+const DELAY: Duration = Duration::from_millis(250);
+const ITERATIONS: u32 = 40;
+
+const MESSAGES_PER_SEC_CHAT: u32 = 1;
+const MESSAGES_PER_MIN_CHAT: u32 = 20;
+const MESSAGES_PER_SEC_OVERALL: u32 = 30;
+
+const PRIORITIES: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+fn requests(n: u64) -> Vec<(u64, Priority)> {
+    (0..n)
+        .map(|i| {
+            let priority = match i % 10 {
+                0 => Priority::High,
+                1..=3 => Priority::Low,
+                _ => Priority::Normal,
+            };
+
+            (i % (n / 2), priority)
+        })
+        .collect()
+}
+
+/// Returns the number of sent requests.
+fn vec_scan(requests: &[(u64, Priority)]) -> usize {
+    let start = Instant::now();
+
+    let mut queue = requests.to_vec();
+    let mut history: VecDeque<(u64, Instant)> = VecDeque::new();
+    let mut per_min: HashMap<u64, u32> = HashMap::new();
+    let mut sent = 0;
+
+    for i in 0..ITERATIONS {
+        let now = start + DELAY * i;
+
+        while history
+            .front()
+            .map_or(false, |&(_, time)| time + MINUTE < now)
+        {
+            let (chat, _) = history.pop_front().unwrap();
+            *per_min.get_mut(&chat).unwrap() -= 1;
+        }
+
+        let mut per_sec: HashMap<u64, u32> = HashMap::new();
+        for &(chat, _) in history
+            .iter()
+            .rev()
+            .take_while(|&&(_, time)| time + SECOND > now)
+        {
+            *per_sec.entry(chat).or_insert(0) += 1;
+        }
+
+        let used = per_sec.values().sum::<u32>();
+        let mut allowed = MESSAGES_PER_SEC_OVERALL.saturating_sub(used);
+        if allowed == 0 {
+            continue;
+        }
+
+        'priorities: for priority in PRIORITIES {
+            let mut queue_removing = queue.removing();
+
+            while let Some(entry) = queue_removing.next() {
+                let &(chat, request_priority) = entry.value();
+                if request_priority != priority {
+                    continue;
+                }
+
+                let sec = per_sec.entry(chat).or_insert(0);
+                let min = per_min.entry(chat).or_insert(0);
+
+                if *sec < MESSAGES_PER_SEC_CHAT && *min < MESSAGES_PER_MIN_CHAT {
+                    entry.remove();
+                    *sec += 1;
+                    *min += 1;
+                    history.push_back((chat, now));
+                    sent += 1;
+
+                    allowed -= 1;
+                    if allowed == 0 {
+                        break 'priorities;
+                    }
+                }
+            }
+        }
+    }
+
+    sent
+}
+
+/// Returns the number of sent requests.
+fn chat_queues(requests: &[(u64, Priority)]) -> usize {
+    let start = Instant::now();
+
+    let mut queue = ChatQueues::new();
+    for (i, &(chat, priority)) in requests.iter().enumerate() {
+        queue.push(chat, priority, i);
+    }
+
+    let mut history: VecDeque<(u64, Instant)> = VecDeque::new();
+    let mut sent = 0;
+
+    let ready_at = |_: &u64, sent: &VecDeque<Instant>| {
+        let nth_last = |limit: u32, period: Duration| {
+            let n = sent.len().checked_sub(limit as usize)?;
+            Some(sent[n] + period)
+        };
+
+        nth_last(MESSAGES_PER_SEC_CHAT, SECOND).max(nth_last(MESSAGES_PER_MIN_CHAT, MINUTE))
+    };
+
+    for i in 0..ITERATIONS {
+        let now = start + DELAY * i;
+
+        while history
+            .front()
+            .map_or(false, |&(_, time)| time + MINUTE < now)
+        {
+            let (chat, _) = history.pop_front().unwrap();
+            queue.forget_sent(chat);
+        }
+
+        let used = history
+            .iter()
+            .rev()
+            .take_while(|&&(_, time)| time + SECOND > now)
+            .count() as u32;
+        let mut allowed = MESSAGES_PER_SEC_OVERALL.saturating_sub(used);
+        if allowed == 0 {
+            continue;
+        }
+
+        queue.wake(now, ready_at);
+
+        'priorities: for priority in PRIORITIES {
+            while let Some((chat, _)) = queue.pop(priority, now, ready_at) {
+                queue.record_sent(chat, now);
+                history.push_back((chat, now));
+                sent += 1;
+
+                allowed -= 1;
+                if allowed == 0 {
+                    break 'priorities;
+                }
+            }
+        }
+    }
+
+    sent
+}
+
+fn throttle_queue(c: &mut Criterion) {
+    let mut group = c.benchmark_group("throttle_queue");
+    group.sample_size(10);
+
+    for n in [1_000, 10_000, 50_000] {
+        let requests = requests(n);
+
+        // Both implementations must send the same number of requests
+        assert_eq!(vec_scan(&requests), chat_queues(&requests));
+
+        group.bench_with_input(BenchmarkId::new("vec_scan", n), &requests, |b, r| {
+            b.iter(|| vec_scan(r))
+        });
+        group.bench_with_input(BenchmarkId::new("chat_queues", n), &requests, |b, r| {
+            b.iter(|| chat_queues(r))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, throttle_queue);
+criterion_main!(benches);
@@ -24,6 +24,19 @@ pub mod auto_send;
 #[cfg(feature = "cache_me")]
 pub mod cache_me;
 
+/// [`CacheFileIds`] bot adaptor which reuses `file_id`s of uploaded files.
+///
+/// [`CacheFileIds`]: cache_file_ids::CacheFileIds
+#[cfg(feature = "cache_file_ids")]
+pub mod cache_file_ids;
+
+/// [`AutoMigrate`] bot adaptor which retries requests to groups which were
+/// migrated to supergroups.
+///
+/// [`AutoMigrate`]: auto_migrate::AutoMigrate
+#[cfg(feature = "auto_migrate")]
+pub mod auto_migrate;
+
 /// [`Trace`] bot adaptor which traces requests.
 ///
 /// [`Trace`]: trace::Trace
@@ -45,11 +58,21 @@ pub mod erased;
 #[cfg(feature = "throttle")]
 pub mod throttle;
 
+/// [`Defaults`] bot adaptor which fills optional parameters with default
+/// values.
+///
+/// [`Defaults`]: defaults::Defaults
+pub mod defaults;
+
 mod parse_mode;
 
+#[cfg(feature = "auto_migrate")]
+pub use auto_migrate::AutoMigrate;
 #[cfg(feature = "auto_send")]
 #[allow(deprecated)]
 pub use auto_send::AutoSend;
+#[cfg(feature = "cache_file_ids")]
+pub use cache_file_ids::CacheFileIds;
 #[cfg(feature = "cache_me")]
 pub use cache_me::CacheMe;
 #[cfg(feature = "erased")]
@@ -59,4 +82,5 @@ pub use throttle::Throttle;
 #[cfg(feature = "trace_adaptor")]
 pub use trace::Trace;
 
+pub use defaults::Defaults;
 pub use parse_mode::DefaultParseMode;
@@ -0,0 +1,549 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::{Future, IntoFuture},
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+
+use futures::{
+    future::BoxFuture,
+    task::{Context, Poll},
+};
+use url::Url;
+
+use crate::{
+    errors::AsResponseParameters,
+    requests::{HasPayload, Output, Request, Requester},
+    types::*,
+};
+
+// Required to not trigger `clippy::type-complexity` lint
+type MigrateHook = dyn Fn(ChatId, ChatId) -> BoxFuture<'static, ()> + Send + Sync;
+type Migrations = Arc<RwLock<HashMap<ChatId, ChatId>>>;
+
+/// Automatic handling of group to supergroup migrations.
+///
+/// When a group is upgraded to a supergroup, requests to the group fail with
+/// [`RequestError::MigrateToChatId`]. This adaptor retries such requests with
+/// the id of the supergroup and remembers the migration, so that later
+/// requests to the group are sent to the supergroup directly.
+///
+/// All methods with a `chat_id` parameter are handled, except for
+/// [`forward_message`] and [`copy_message`]: their `from_chat_id` may be a
+/// migrated group too and the error doesn't tell which of the chats was
+/// migrated, so they are sent as-is.
+///
+/// Use [`AutoMigrate::on_migrate`] to be notified about migrations, e.g. to
+/// update ids stored in a database.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{requests::RequesterExt, Bot};
+///
+/// let bot = Bot::new("TOKEN")
+///     .auto_migrate()
+///     .on_migrate(|old, new| async move {
+///         /* replace `old` with `new` in the database */
+///         # let _ = (old, new);
+///     });
+/// ```
+///
+/// [`RequestError::MigrateToChatId`]: crate::RequestError::MigrateToChatId
+/// [`forward_message`]: crate::requests::Requester::forward_message
+/// [`copy_message`]: crate::requests::Requester::copy_message
+#[derive(Clone)]
+pub struct AutoMigrate<B> {
+    bot: B,
+    migrations: Migrations,
+    on_migrate: Option<Arc<MigrateHook>>,
+}
+
+impl<B> AutoMigrate<B> {
+    /// Creates new adaptor, which doesn't know about any migrations.
+    ///
+    /// Note: it's recommended to use [`RequesterExt::auto_migrate`] instead.
+    ///
+    /// [`RequesterExt::auto_migrate`]: crate::requests::RequesterExt::auto_migrate
+    pub fn new(bot: B) -> Self {
+        Self {
+            bot,
+            migrations: <_>::default(),
+            on_migrate: None,
+        }
+    }
+
+    /// Sets a function which is called with the old and the new id of a chat
+    /// when a migration is discovered.
+    ///
+    /// The request is retried after the returned future completes. The
+    /// function is not called for migrations added with
+    /// [`add_migration`](AutoMigrate::add_migration).
+    #[must_use]
+    pub fn on_migrate<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(ChatId, ChatId) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_migrate = Some(Arc::new(move |old, new| Box::pin(f(old, new))));
+        self
+    }
+
+    /// Allows to access inner bot
+    pub fn inner(&self) -> &B {
+        &self.bot
+    }
+
+    /// Unwraps inner bot
+    pub fn into_inner(self) -> B {
+        self.bot
+    }
+
+    /// Returns the id of the supergroup to which the group `chat` was
+    /// migrated, if the migration is known.
+    pub fn migrated_to(&self, chat: ChatId) -> Option<ChatId> {
+        self.migrations.read().unwrap().get(&chat).copied()
+    }
+
+    /// Remembers that the group `old` was migrated to the supergroup `new`,
+    /// e.g. if the migration is known from [`Message::migrate_to_chat_id`].
+    ///
+    /// Clones of this adaptor share the migrations.
+    pub fn add_migration(&self, old: ChatId, new: ChatId) {
+        self.migrations.write().unwrap().insert(old, new);
+    }
+}
+
+impl<B: fmt::Debug> fmt::Debug for AutoMigrate<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoMigrate")
+            .field("bot", &self.bot)
+            .field("migrations", &self.migrations)
+            .finish_non_exhaustive()
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        $this.inner().$m($($arg),*)
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        B::$T
+    };
+}
+
+macro_rules! fmig {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        MigratingRequest {
+            request: $this.inner().$m($($arg),*),
+            migrations: Arc::clone(&$this.migrations),
+            on_migrate: $this.on_migrate.clone(),
+            chat_id: |p| &mut p.chat_id,
+        }
+    };
+}
+
+macro_rules! ftymig {
+    ($T:ident) => {
+        MigratingRequest<B::$T>
+    };
+}
+
+impl<B> Requester for AutoMigrate<B>
+where
+    B: Requester,
+    B::Err: AsResponseParameters,
+
+    B::SendPhoto: Clone + Send + 'static,
+    B::SendAudio: Clone + Send + 'static,
+    B::SendDocument: Clone + Send + 'static,
+    B::SendVideo: Clone + Send + 'static,
+    B::SendAnimation: Clone + Send + 'static,
+    B::SendVoice: Clone + Send + 'static,
+    B::SendVideoNote: Clone + Send + 'static,
+    B::SendSticker: Clone + Send + 'static,
+    B::SendMessage: Clone + Send + 'static,
+    B::SendMediaGroup: Clone + Send + 'static,
+    B::SendLocation: Clone + Send + 'static,
+    B::EditMessageLiveLocation: Clone + Send + 'static,
+    B::StopMessageLiveLocation: Clone + Send + 'static,
+    B::SendVenue: Clone + Send + 'static,
+    B::SendContact: Clone + Send + 'static,
+    B::SendPoll: Clone + Send + 'static,
+    B::SendDice: Clone + Send + 'static,
+    B::SendChatAction: Clone + Send + 'static,
+    B::KickChatMember: Clone + Send + 'static,
+    B::BanChatMember: Clone + Send + 'static,
+    B::UnbanChatMember: Clone + Send + 'static,
+    B::RestrictChatMember: Clone + Send + 'static,
+    B::PromoteChatMember: Clone + Send + 'static,
+    B::SetChatAdministratorCustomTitle: Clone + Send + 'static,
+    B::BanChatSenderChat: Clone + Send + 'static,
+    B::UnbanChatSenderChat: Clone + Send + 'static,
+    B::SetChatPermissions: Clone + Send + 'static,
+    B::ExportChatInviteLink: Clone + Send + 'static,
+    B::CreateChatInviteLink: Clone + Send + 'static,
+    B::EditChatInviteLink: Clone + Send + 'static,
+    B::RevokeChatInviteLink: Clone + Send + 'static,
+    B::SetChatPhoto: Clone + Send + 'static,
+    B::DeleteChatPhoto: Clone + Send + 'static,
+    B::SetChatTitle: Clone + Send + 'static,
+    B::SetChatDescription: Clone + Send + 'static,
+    B::PinChatMessage: Clone + Send + 'static,
+    B::UnpinChatMessage: Clone + Send + 'static,
+    B::UnpinAllChatMessages: Clone + Send + 'static,
+    B::LeaveChat: Clone + Send + 'static,
+    B::GetChat: Clone + Send + 'static,
+    B::GetChatAdministrators: Clone + Send + 'static,
+    B::GetChatMembersCount: Clone + Send + 'static,
+    B::GetChatMemberCount: Clone + Send + 'static,
+    B::GetChatMember: Clone + Send + 'static,
+    B::SetChatStickerSet: Clone + Send + 'static,
+    B::DeleteChatStickerSet: Clone + Send + 'static,
+    B::EditMessageText: Clone + Send + 'static,
+    B::EditMessageCaption: Clone + Send + 'static,
+    B::EditMessageMedia: Clone + Send + 'static,
+    B::EditMessageReplyMarkup: Clone + Send + 'static,
+    B::StopPoll: Clone + Send + 'static,
+    B::DeleteMessage: Clone + Send + 'static,
+    B::SendInvoice: Clone + Send + 'static,
+    B::ApproveChatJoinRequest: Clone + Send + 'static,
+    B::DeclineChatJoinRequest: Clone + Send + 'static,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_sticker,
+        send_message,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        stop_message_live_location,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        kick_chat_member,
+        ban_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_members_count,
+        get_chat_member_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        edit_message_text,
+        edit_message_caption,
+        edit_message_media,
+        edit_message_reply_markup,
+        stop_poll,
+        delete_message,
+        send_invoice,
+        approve_chat_join_request,
+        decline_chat_join_request
+        => fmig, ftymig
+    }
+
+    requester_forward! {
+        get_me,
+        log_out,
+        close,
+        forward_message,
+        copy_message,
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        edit_message_live_location_inline,
+        stop_message_live_location_inline,
+        get_user_profile_photos,
+        get_file,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text_inline,
+        edit_message_caption_inline,
+        edit_message_media_inline,
+        edit_message_reply_markup_inline,
+        get_sticker_set,
+        get_custom_emoji_stickers,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        create_invoice_link,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => f, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    AutoMigrate<B>
+    { this => this.inner() }
+}
+
+/// Request returned by [`AutoMigrate`] methods which have a `chat_id`
+/// parameter.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct MigratingRequest<R: HasPayload> {
+    request: R,
+    migrations: Migrations,
+    on_migrate: Option<Arc<MigrateHook>>,
+    chat_id: fn(&mut R::Payload) -> &mut Recipient,
+}
+
+/// Future returned by [`MigratingRequest`]s.
+#[pin_project::pin_project]
+pub struct MigratingSend<R: Request>(#[pin] BoxFuture<'static, Result<Output<R>, R::Err>>);
+
+impl<R: HasPayload> HasPayload for MigratingRequest<R> {
+    type Payload = R::Payload;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        self.request.payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.request.payload_ref()
+    }
+}
+
+impl<R> Request for MigratingRequest<R>
+where
+    R: Request + Clone + Send + 'static,
+    R::Err: AsResponseParameters,
+{
+    type Err = R::Err;
+    type Send = MigratingSend<R>;
+    type SendRef = MigratingSend<R>;
+
+    fn send(self) -> Self::Send {
+        let fut = send(self.request, self.migrations, self.on_migrate, self.chat_id);
+
+        MigratingSend(Box::pin(fut))
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        // We may need to replace the chat id in the payload, so the request is cloned
+        let fut = send(
+            self.request.clone(),
+            Arc::clone(&self.migrations),
+            self.on_migrate.clone(),
+            self.chat_id,
+        );
+
+        MigratingSend(Box::pin(fut))
+    }
+}
+
+impl<R> IntoFuture for MigratingRequest<R>
+where
+    R: Request + Clone + Send + 'static,
+    R::Err: AsResponseParameters,
+{
+    type Output = Result<Output<Self>, <Self as Request>::Err>;
+    type IntoFuture = <Self as Request>::Send;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.send()
+    }
+}
+
+impl<R: Request> Future for MigratingSend<R> {
+    type Output = Result<Output<R>, R::Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().0.poll(cx)
+    }
+}
+
+/// Actual implementation of the `MigratingSend` future
+async fn send<R>(
+    mut request: R,
+    migrations: Migrations,
+    on_migrate: Option<Arc<MigrateHook>>,
+    chat_id: fn(&mut R::Payload) -> &mut Recipient,
+) -> Result<Output<R>, R::Err>
+where
+    R: Request + Send,
+    R::Err: AsResponseParameters,
+{
+    let old = match chat_id(request.payload_mut()) {
+        Recipient::Id(id) => Some(*id),
+        Recipient::ChannelUsername(_) => None,
+    };
+
+    // The chat may be already known to be migrated
+    let known = old.and_then(|old| migrations.read().unwrap().get(&old).copied());
+    if let Some(new) = known {
+        *chat_id(request.payload_mut()) = Recipient::Id(new);
+    }
+
+    let new = {
+        let res = request.send_ref().await;
+        match res.as_ref().err().and_then(<_>::migrate_to_chat_id) {
+            Some(new) => ChatId(new),
+            None => return res,
+        }
+    };
+
+    if let Some(old) = old {
+        let previous = migrations.write().unwrap().insert(old, new);
+
+        // Other requests to the same chat may have discovered the migration already
+        if previous != Some(new) {
+            log::info!("chat {} was migrated to {}", old, new);
+
+            if let Some(on_migrate) = &on_migrate {
+                on_migrate(old, new).await;
+            }
+        }
+    }
+
+    *chat_id(request.payload_mut()) = Recipient::Id(new);
+    request.send().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        adaptors::AutoMigrate,
+        mock::MockBot,
+        payloads::{ForwardMessage, SendMessage},
+        requests::{Request, Requester},
+        types::{ChatId, MessageId, Recipient},
+        RequestError,
+    };
+
+    const OLD: ChatId = ChatId(-1);
+    const NEW: ChatId = ChatId(-1001234567890);
+
+    fn message() -> Value {
+        json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": { "id": NEW.0, "title": "A", "type": "supergroup" },
+            "text": "text",
+        })
+    }
+
+    /// Returns a bot which records chats to which it sends messages and fails
+    /// requests to the `OLD` chat with `MigrateToChatId`.
+    fn bot() -> (MockBot, Arc<Mutex<Vec<Recipient>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let bot = MockBot::new({
+            let sent = Arc::clone(&sent);
+            move |method, payload| {
+                let chat_id = match method {
+                    "SendMessage" => &payload.downcast_ref::<SendMessage>().unwrap().chat_id,
+                    "ForwardMessage" => {
+                        &payload
+                            .downcast_ref::<ForwardMessage>()
+                            .unwrap()
+                            .from_chat_id
+                    }
+                    _ => unreachable!("unexpected method: {}", method),
+                };
+                sent.lock().unwrap().push(chat_id.clone());
+
+                match chat_id {
+                    Recipient::Id(OLD) => Err(RequestError::MigrateToChatId(NEW.0)),
+                    _ => Ok(message()),
+                }
+            }
+        });
+
+        (bot, sent)
+    }
+
+    #[tokio::test]
+    async fn migrates() {
+        let (bot, sent) = bot();
+        let migrated = Arc::new(Mutex::new(Vec::new()));
+        let bot = AutoMigrate::new(bot).on_migrate({
+            let migrated = Arc::clone(&migrated);
+            move |old, new| {
+                migrated.lock().unwrap().push((old, new));
+                async {}
+            }
+        });
+
+        bot.send_message(OLD, "text").await.unwrap();
+        assert_eq!(bot.migrated_to(OLD), Some(NEW));
+
+        // The migration is remembered
+        bot.send_message(OLD, "text").send_ref().await.unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), [OLD, NEW, NEW].map(Recipient::Id));
+        assert_eq!(*migrated.lock().unwrap(), [(OLD, NEW)]);
+    }
+
+    #[tokio::test]
+    async fn forward_is_not_migrated() {
+        let (bot, sent) = bot();
+        let bot = AutoMigrate::new(bot);
+
+        // The group from which the message is forwarded was migrated, not the
+        // destination
+        let err = bot
+            .forward_message(ChatId(1), OLD, MessageId(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::MigrateToChatId(id) if id == NEW.0));
+
+        assert_eq!(bot.migrated_to(ChatId(1)), None);
+        assert_eq!(*sent.lock().unwrap(), [Recipient::Id(OLD)]);
+    }
+}
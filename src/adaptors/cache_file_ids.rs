@@ -0,0 +1,692 @@
+use std::{
+    collections::HashMap,
+    future::{Future, IntoFuture},
+    mem,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use futures::{
+    future::BoxFuture,
+    task::{Context, Poll},
+};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{
+    errors::{ApiError, AsApiError},
+    requests::{HasPayload, Output, Payload, Request, Requester},
+    types::*,
+};
+
+/// Cache of `file_id`s of uploaded files.
+///
+/// When the same local file (or the same in-memory data) is sent multiple
+/// times, every request re-uploads it. This adaptor remembers the `file_id`
+/// which Telegram returns in the [`Message`] after the first upload and
+/// replaces the file with [`InputFile::file_id`] in all later requests which
+/// send the same file.
+///
+/// Files are identified by a [`FileKey`]: path and modification time for
+/// [`InputFile::file`] and a SHA-256 digest of the contents for
+/// [`InputFile::memory`]. Other kinds of files (urls, file ids, readers and
+/// streams) are sent as-is.
+///
+/// If Telegram rejects a cached `file_id` (e.g. because the file was deleted),
+/// the entry is removed from the store and the file is uploaded again.
+///
+/// The mapping is kept in a [`FileIdStore`], by default [`InMemoryStore`].
+///
+/// The following methods are cached:
+/// - [`send_photo`]
+/// - [`send_audio`]
+/// - [`send_document`]
+/// - [`send_video`]
+/// - [`send_animation`]
+/// - [`send_voice`]
+/// - [`send_video_note`]
+/// - [`send_sticker`]
+///
+/// Note that thumbnails can't be resent by file id, so they are always
+/// uploaded.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{requests::RequesterExt, Bot};
+///
+/// let bot = Bot::new("TOKEN").cache_file_ids();
+///
+/// /* send the same file many times here */
+/// ```
+///
+/// [`send_photo`]: crate::requests::Requester::send_photo
+/// [`send_audio`]: crate::requests::Requester::send_audio
+/// [`send_document`]: crate::requests::Requester::send_document
+/// [`send_video`]: crate::requests::Requester::send_video
+/// [`send_animation`]: crate::requests::Requester::send_animation
+/// [`send_voice`]: crate::requests::Requester::send_voice
+/// [`send_video_note`]: crate::requests::Requester::send_video_note
+/// [`send_sticker`]: crate::requests::Requester::send_sticker
+#[derive(Debug)]
+pub struct CacheFileIds<B, St = InMemoryStore> {
+    bot: B,
+    store: Arc<St>,
+}
+
+impl<B> CacheFileIds<B> {
+    /// Creates new cache, which keeps `file_id`s in memory.
+    ///
+    /// Note: it's recommended to use [`RequesterExt::cache_file_ids`] instead.
+    ///
+    /// [`RequesterExt::cache_file_ids`]: crate::requests::RequesterExt::cache_file_ids
+    pub fn new(bot: B) -> Self {
+        Self::with_store(bot, InMemoryStore::default())
+    }
+}
+
+impl<B, St> CacheFileIds<B, St> {
+    /// Creates new cache, which keeps `file_id`s in the given store.
+    pub fn with_store(bot: B, store: St) -> Self {
+        Self {
+            bot,
+            store: Arc::new(store),
+        }
+    }
+
+    /// Allows to access inner bot
+    pub fn inner(&self) -> &B {
+        &self.bot
+    }
+
+    /// Unwraps inner bot
+    pub fn into_inner(self) -> B {
+        self.bot
+    }
+
+    /// Returns the store used by this cache.
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+}
+
+impl<B: Clone, St> Clone for CacheFileIds<B, St> {
+    fn clone(&self) -> Self {
+        Self {
+            bot: self.bot.clone(),
+            store: Arc::clone(&self.store),
+        }
+    }
+}
+
+/// Key which identifies the contents of a file sent by [`CacheFileIds`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FileKey {
+    /// File on the file system, identified by its canonical path and
+    /// modification time.
+    Path { path: PathBuf, modified: SystemTime },
+
+    /// In-memory data, identified by its length and the SHA-256 digest of its
+    /// contents.
+    Memory { len: usize, sha256: [u8; 32] },
+}
+
+impl FileKey {
+    /// Computes the key of the given file.
+    ///
+    /// Returns `None` if the file can't be identified (it's not a local file
+    /// or an in-memory data, or its metadata can't be read).
+    pub async fn of(file: &InputFile) -> Option<Self> {
+        if let Some(path) = file.path() {
+            // Different paths may point to the same file (`./a`, `a`, symlinks)
+            let path = tokio::fs::canonicalize(path).await.ok()?;
+            let modified = tokio::fs::metadata(&path).await.ok()?.modified().ok()?;
+
+            return Some(Self::Path { path, modified });
+        }
+
+        file.bytes().map(|data| Self::Memory {
+            len: data.len(),
+            sha256: Sha256::digest(data).into(),
+        })
+    }
+}
+
+/// Storage of `file_id`s used by [`CacheFileIds`].
+///
+/// Note that the methods of this trait are called from async code, so they
+/// shouldn't block for a long time.
+pub trait FileIdStore: Send + Sync {
+    /// Returns `file_id` of a file identified by `key`, if it's known.
+    fn get(&self, key: &FileKey) -> Option<String>;
+
+    /// Remembers `file_id` of a file identified by `key`.
+    fn insert(&self, key: FileKey, file_id: String);
+
+    /// Forgets `file_id` of a file identified by `key`.
+    ///
+    /// This is called when Telegram rejects the cached `file_id`.
+    fn remove(&self, key: &FileKey);
+}
+
+/// [`FileIdStore`] which keeps `file_id`s in a `HashMap`.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    map: Mutex<HashMap<FileKey, String>>,
+}
+
+impl FileIdStore for InMemoryStore {
+    fn get(&self, key: &FileKey) -> Option<String> {
+        self.map.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: FileKey, file_id: String) {
+        self.map.lock().unwrap().insert(key, file_id);
+    }
+
+    fn remove(&self, key: &FileKey) {
+        self.map.lock().unwrap().remove(key);
+    }
+}
+
+impl<S: FileIdStore + ?Sized> FileIdStore for Arc<S> {
+    fn get(&self, key: &FileKey) -> Option<String> {
+        S::get(self, key)
+    }
+
+    fn insert(&self, key: FileKey, file_id: String) {
+        S::insert(self, key, file_id)
+    }
+
+    fn remove(&self, key: &FileKey) {
+        S::remove(self, key)
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        $this.inner().$m($($arg),*)
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        B::$T
+    };
+}
+
+macro_rules! fcache {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        CachedFileRequest {
+            request: $this.inner().$m($($arg),*),
+            store: Arc::clone(&$this.store),
+            file: file_field!($m),
+            uploaded: uploaded_file!($m),
+        }
+    };
+}
+
+macro_rules! ftycache {
+    ($T:ident) => {
+        CachedFileRequest<B::$T, St>
+    };
+}
+
+macro_rules! file_field {
+    (send_photo) => {
+        |p| &mut p.photo
+    };
+    (send_audio) => {
+        |p| &mut p.audio
+    };
+    (send_document) => {
+        |p| &mut p.document
+    };
+    (send_video) => {
+        |p| &mut p.video
+    };
+    (send_animation) => {
+        |p| &mut p.animation
+    };
+    (send_voice) => {
+        |p| &mut p.voice
+    };
+    (send_video_note) => {
+        |p| &mut p.video_note
+    };
+    (send_sticker) => {
+        |p| &mut p.sticker
+    };
+}
+
+macro_rules! uploaded_file {
+    // Telegram returns all sizes of a photo, the largest one is the last
+    (send_photo) => {
+        |m| {
+            m.photo()
+                .and_then(|sizes| sizes.last())
+                .map(|size| &size.file)
+        }
+    };
+    (send_audio) => {
+        |m| m.audio().map(|audio| &audio.file)
+    };
+    (send_document) => {
+        |m| m.document().map(|document| &document.file)
+    };
+    (send_video) => {
+        |m| m.video().map(|video| &video.file)
+    };
+    (send_animation) => {
+        |m| m.animation().map(|animation| &animation.file)
+    };
+    (send_voice) => {
+        |m| m.voice().map(|voice| &voice.file)
+    };
+    (send_video_note) => {
+        |m| m.video_note().map(|video_note| &video_note.file)
+    };
+    (send_sticker) => {
+        |m| m.sticker().map(|sticker| &sticker.file)
+    };
+}
+
+impl<B, St> Requester for CacheFileIds<B, St>
+where
+    B: Requester,
+    B::Err: AsApiError,
+    St: FileIdStore + 'static,
+
+    B::SendPhoto: Clone + Send + 'static,
+    B::SendAudio: Clone + Send + 'static,
+    B::SendDocument: Clone + Send + 'static,
+    B::SendVideo: Clone + Send + 'static,
+    B::SendAnimation: Clone + Send + 'static,
+    B::SendVoice: Clone + Send + 'static,
+    B::SendVideoNote: Clone + Send + 'static,
+    B::SendSticker: Clone + Send + 'static,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_sticker
+        => fcache, ftycache
+    }
+
+    requester_forward! {
+        get_me,
+        log_out,
+        close,
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        forward_message,
+        copy_message,
+        send_message,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        kick_chat_member,
+        ban_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_members_count,
+        get_chat_member_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        get_sticker_set,
+        get_custom_emoji_stickers,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        create_invoice_link,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores,
+        approve_chat_join_request,
+        decline_chat_join_request
+        => f, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    [St]
+    CacheFileIds<B, St>
+    { this => this.inner() }
+}
+
+/// Request returned by [`CacheFileIds`] methods which upload files.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct CachedFileRequest<R: HasPayload, St> {
+    request: R,
+    store: Arc<St>,
+    file: fn(&mut R::Payload) -> &mut InputFile,
+    uploaded: fn(&Message) -> Option<&FileMeta>,
+}
+
+/// Future returned by [`CachedFileRequest`]s.
+#[pin_project::pin_project]
+pub struct CachedFileSend<R: Request>(#[pin] BoxFuture<'static, Result<Output<R>, R::Err>>);
+
+impl<R: HasPayload, St> HasPayload for CachedFileRequest<R, St> {
+    type Payload = R::Payload;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        self.request.payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.request.payload_ref()
+    }
+}
+
+impl<R, St> Request for CachedFileRequest<R, St>
+where
+    R: Request + Clone + Send + 'static,
+    R::Err: AsApiError,
+    R::Payload: Payload<Output = Message>,
+    St: FileIdStore + 'static,
+{
+    type Err = R::Err;
+    type Send = CachedFileSend<R>;
+    type SendRef = CachedFileSend<R>;
+
+    fn send(self) -> Self::Send {
+        let fut = send(self.request, self.store, self.file, self.uploaded);
+
+        CachedFileSend(Box::pin(fut))
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        // We need to replace the file in the payload, so the request is cloned
+        let fut = send(
+            self.request.clone(),
+            Arc::clone(&self.store),
+            self.file,
+            self.uploaded,
+        );
+
+        CachedFileSend(Box::pin(fut))
+    }
+}
+
+impl<R, St> IntoFuture for CachedFileRequest<R, St>
+where
+    R: Request + Clone + Send + 'static,
+    R::Err: AsApiError,
+    R::Payload: Payload<Output = Message>,
+    St: FileIdStore + 'static,
+{
+    type Output = Result<Output<Self>, <Self as Request>::Err>;
+    type IntoFuture = <Self as Request>::Send;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.send()
+    }
+}
+
+impl<R: Request> Future for CachedFileSend<R> {
+    type Output = Result<Output<R>, R::Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().0.poll(cx)
+    }
+}
+
+/// Actual implementation of the `CachedFileSend` future
+async fn send<R, St>(
+    mut request: R,
+    store: Arc<St>,
+    file: fn(&mut R::Payload) -> &mut InputFile,
+    uploaded: fn(&Message) -> Option<&FileMeta>,
+) -> Result<Message, R::Err>
+where
+    R: Request + Send,
+    R::Err: AsApiError,
+    R::Payload: Payload<Output = Message>,
+    St: FileIdStore,
+{
+    let key = match FileKey::of(file(request.payload_mut())).await {
+        Some(key) => key,
+        None => return request.send().await,
+    };
+
+    // The file was already uploaded, there is no need to upload it again
+    if let Some(file_id) = store.get(&key) {
+        let original = mem::replace(file(request.payload_mut()), InputFile::file_id(file_id));
+
+        match request.send_ref().await {
+            // The file is not available by this `file_id` anymore
+            Err(err) if is_file_id_rejected(&err) => {
+                log::warn!(
+                    "Cached `file_id` was rejected in `{}` request, uploading the file again",
+                    R::Payload::NAME
+                );
+
+                store.remove(&key);
+                *file(request.payload_mut()) = original;
+            }
+            res => return res,
+        }
+    }
+
+    let res = request.send().await;
+
+    if let Ok(message) = &res {
+        match uploaded(message) {
+            Some(meta) => store.insert(key, meta.id.clone()),
+            None => log::warn!(
+                "Couldn't find uploaded file in the response to `{}` request",
+                R::Payload::NAME
+            ),
+        }
+    }
+
+    res
+}
+
+/// Returns `true` if `err` means that Telegram doesn't accept the `file_id`.
+fn is_file_id_rejected(err: &impl AsApiError) -> bool {
+    match err.api_error() {
+        Some(ApiError::WrongFileId | ApiError::WrongFileIdOrUrl | ApiError::FileIdInvalid) => true,
+        Some(ApiError::Unknown(description)) => description.contains("file identifier"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        adaptors::cache_file_ids::{FileIdStore, FileKey, InMemoryStore},
+        mock::MockBot,
+        payloads::SendDocument,
+        prelude::*,
+        types::{ChatId, InputFile},
+        ApiError, RequestError,
+    };
+
+    fn document_message(file_id: &str) -> Value {
+        json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": { "id": 1, "first_name": "A", "type": "private" },
+            "document": { "file_id": file_id, "file_unique_id": "" }
+        })
+    }
+
+    /// Returns a bot which records files sent with `send_document`, and
+    /// responds with `handler(file)`.
+    fn bot<F>(handler: F) -> (MockBot, Arc<Mutex<Vec<Value>>>)
+    where
+        F: Fn(&Value) -> Result<Value, RequestError> + Send + Sync + 'static,
+    {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let bot = MockBot::new({
+            let sent = Arc::clone(&sent);
+            move |_, payload| {
+                let payload = payload.downcast_ref::<SendDocument>().unwrap();
+                let file = serde_json::to_value(&payload.document).unwrap();
+                let res = handler(&file);
+                sent.lock().unwrap().push(file);
+                res
+            }
+        });
+
+        (bot, sent)
+    }
+
+    #[tokio::test]
+    async fn file_key() {
+        let a = FileKey::of(&InputFile::memory(&b"Hello world!"[..])).await;
+        let b = FileKey::of(&InputFile::memory(&b"Hello world!"[..]).file_name("b")).await;
+        let c = FileKey::of(&InputFile::memory(&b"Hello world?"[..])).await;
+
+        assert!(a.is_some());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let logo = FileKey::of(&InputFile::file("./media/logo.png")).await;
+        assert!(matches!(logo, Some(FileKey::Path { .. })));
+        assert_eq!(
+            logo,
+            FileKey::of(&InputFile::file("./media/../media/logo.png")).await
+        );
+
+        assert_eq!(
+            FileKey::of(&InputFile::file("./media/missing.png")).await,
+            None
+        );
+        assert_eq!(FileKey::of(&InputFile::file_id("id")).await, None);
+    }
+
+    #[tokio::test]
+    async fn in_memory_store() {
+        let store = InMemoryStore::default();
+        let key = FileKey::of(&InputFile::memory(&b"data"[..])).await.unwrap();
+
+        assert_eq!(store.get(&key), None);
+        store.insert(key.clone(), "id".to_owned());
+        assert_eq!(store.get(&key).as_deref(), Some("id"));
+        store.remove(&key);
+        assert_eq!(store.get(&key), None);
+    }
+
+    #[tokio::test]
+    async fn reuses_file_id() {
+        let (bot, sent) = bot(|_| Ok(document_message("id")));
+        let bot = bot.cache_file_ids();
+
+        for _ in 0..2 {
+            bot.send_document(ChatId(1), InputFile::memory(&b"data"[..]))
+                .await
+                .unwrap();
+        }
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].as_str().unwrap().starts_with("attach://"));
+        assert_eq!(sent[1], "id");
+    }
+
+    #[tokio::test]
+    async fn reuploads_rejected_file_id() {
+        let uploads = Mutex::new(0);
+        let (bot, sent) = bot(move |file| match file.as_str().unwrap() {
+            "old" => Err(RequestError::Api(ApiError::WrongFileIdOrUrl)),
+            _ => {
+                let mut uploads = uploads.lock().unwrap();
+                *uploads += 1;
+
+                Ok(document_message(if *uploads == 1 { "old" } else { "new" }))
+            }
+        });
+        let bot = bot.cache_file_ids();
+
+        for _ in 0..3 {
+            bot.send_document(ChatId(1), InputFile::memory(&b"data"[..]))
+                .await
+                .unwrap();
+        }
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 4);
+        assert!(sent[0].as_str().unwrap().starts_with("attach://"));
+        assert_eq!(sent[1], "old");
+        assert!(sent[2].as_str().unwrap().starts_with("attach://"));
+        assert_eq!(sent[3], "new");
+    }
+}
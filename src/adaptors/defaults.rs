@@ -0,0 +1,661 @@
+use std::{collections::HashMap, future::IntoFuture, sync::Arc};
+
+use url::Url;
+
+use crate::{
+    requests::{HasPayload, Output, Request, Requester},
+    types::*,
+};
+
+/// Default values of optional parameters, see [`Defaults`].
+///
+/// Parameters which are `None` are not filled.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DefaultValues {
+    pub disable_notification: Option<bool>,
+    pub protect_content: Option<bool>,
+    pub disable_web_page_preview: Option<bool>,
+    pub allow_sending_without_reply: Option<bool>,
+}
+
+/// Adaptor which fills optional parameters of requests with default values.
+///
+/// Parameters are only filled if they were not set by the caller (i.e. they
+/// are `None` when the request is sent), so that they can be overridden for a
+/// particular request. Default values can be overridden for particular chats
+/// with [`Defaults::chat`].
+///
+/// The following parameters are filled in all methods which have them:
+/// - `disable_notification`
+/// - `protect_content`
+/// - `disable_web_page_preview`
+/// - `allow_sending_without_reply`
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     adaptors::defaults::DefaultValues, requests::RequesterExt, types::ChatId, Bot,
+/// };
+///
+/// let bot = Bot::new("TOKEN")
+///     .defaults(DefaultValues::default().disable_web_page_preview(true))
+///     // Notify admins about every message
+///     .chat(
+///         ChatId(-1001),
+///         DefaultValues::default().disable_notification(false),
+///     );
+/// # let _ = bot;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Defaults<B> {
+    bot: B,
+    values: Arc<Values>,
+}
+
+/// Default values of all chats and overrides for particular chats.
+#[derive(Clone, Debug, Default)]
+struct Values {
+    all: DefaultValues,
+    chats: HashMap<ChatId, DefaultValues>,
+}
+
+impl DefaultValues {
+    pub fn disable_notification(mut self, val: bool) -> Self {
+        self.disable_notification = Some(val);
+        self
+    }
+
+    pub fn protect_content(mut self, val: bool) -> Self {
+        self.protect_content = Some(val);
+        self
+    }
+
+    pub fn disable_web_page_preview(mut self, val: bool) -> Self {
+        self.disable_web_page_preview = Some(val);
+        self
+    }
+
+    pub fn allow_sending_without_reply(mut self, val: bool) -> Self {
+        self.allow_sending_without_reply = Some(val);
+        self
+    }
+
+    /// Returns values of `self`, using values of `other` for parameters which
+    /// are not set in `self`.
+    #[must_use]
+    pub fn or(self, other: &Self) -> Self {
+        Self {
+            disable_notification: self.disable_notification.or(other.disable_notification),
+            protect_content: self.protect_content.or(other.protect_content),
+            disable_web_page_preview: self
+                .disable_web_page_preview
+                .or(other.disable_web_page_preview),
+            allow_sending_without_reply: self
+                .allow_sending_without_reply
+                .or(other.allow_sending_without_reply),
+        }
+    }
+}
+
+impl<B> Defaults<B> {
+    /// Creates new [`Defaults`].
+    ///
+    /// Note: it's recommended to use [`RequesterExt::defaults`] instead.
+    ///
+    /// [`RequesterExt::defaults`]: crate::requests::RequesterExt::defaults
+    pub fn new(bot: B, values: DefaultValues) -> Self {
+        Self {
+            bot,
+            values: Arc::new(Values {
+                all: values,
+                chats: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Overrides default values for the `chat`.
+    ///
+    /// Parameters which are not set in `values` are taken from the default
+    /// values of all chats.
+    #[must_use]
+    pub fn chat(mut self, chat: ChatId, values: DefaultValues) -> Self {
+        Arc::make_mut(&mut self.values).chats.insert(chat, values);
+        self
+    }
+
+    /// Allows to access the inner bot.
+    pub fn inner(&self) -> &B {
+        &self.bot
+    }
+
+    /// Unwraps the inner bot.
+    pub fn into_inner(self) -> B {
+        self.bot
+    }
+
+    /// Returns default values used for the `chat`.
+    pub fn values(&self, chat: ChatId) -> DefaultValues {
+        self.values.get(Some(chat))
+    }
+}
+
+impl Values {
+    fn get(&self, chat: Option<ChatId>) -> DefaultValues {
+        match chat.and_then(|chat| self.chats.get(&chat)) {
+            Some(values) => values.clone().or(&self.all),
+            None => self.all.clone(),
+        }
+    }
+}
+
+/// Returns the id of the chat, if it's known without asking Telegram.
+fn chat_id(recipient: &Recipient) -> Option<ChatId> {
+    match recipient {
+        Recipient::Id(id) => Some(*id),
+        Recipient::ChannelUsername(_) => None,
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        $this.inner().$m($($arg),*)
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        B::$T
+    };
+}
+
+macro_rules! fdef {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        DefaultsRequest {
+            request: $this.inner().$m($($arg),*),
+            values: Arc::clone(&$this.values),
+            fill: fill!($m),
+        }
+    };
+}
+
+macro_rules! ftydef {
+    ($T:ident) => {
+        DefaultsRequest<B::$T>
+    };
+}
+
+macro_rules! fill {
+    (@fields |$p:ident| $chat:expr, [$($field:ident),+ $(,)?]) => {
+        |$p, values| {
+            let values = values.get($chat);
+            $(
+                if $p.$field.is_none() {
+                    $p.$field = values.$field;
+                }
+            )+
+        }
+    };
+    (send_photo) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_audio) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_document) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_video) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_animation) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_voice) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [disable_notification, allow_sending_without_reply])
+    };
+    (send_video_note) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_sticker) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (forward_message) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [disable_notification, protect_content])
+    };
+    (copy_message) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_message) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            disable_web_page_preview,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_media_group) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_location) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_venue) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_contact) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_poll) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_dice) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (pin_chat_message) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [disable_notification])
+    };
+    (edit_message_text) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [disable_web_page_preview])
+    };
+    (edit_message_text_inline) => {
+        fill!(@fields |p| None, [disable_web_page_preview])
+    };
+    (send_invoice) => {
+        fill!(@fields |p| chat_id(&p.chat_id), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+    (send_game) => {
+        fill!(@fields |p| Some(ChatId(p.chat_id.into())), [
+            disable_notification,
+            protect_content,
+            allow_sending_without_reply,
+        ])
+    };
+}
+
+impl<B> Requester for Defaults<B>
+where
+    B: Requester,
+
+    B::SendPhoto: Clone,
+    B::SendAudio: Clone,
+    B::SendDocument: Clone,
+    B::SendVideo: Clone,
+    B::SendAnimation: Clone,
+    B::SendVoice: Clone,
+    B::SendVideoNote: Clone,
+    B::SendSticker: Clone,
+    B::ForwardMessage: Clone,
+    B::CopyMessage: Clone,
+    B::SendMessage: Clone,
+    B::SendMediaGroup: Clone,
+    B::SendLocation: Clone,
+    B::SendVenue: Clone,
+    B::SendContact: Clone,
+    B::SendPoll: Clone,
+    B::SendDice: Clone,
+    B::PinChatMessage: Clone,
+    B::EditMessageText: Clone,
+    B::EditMessageTextInline: Clone,
+    B::SendInvoice: Clone,
+    B::SendGame: Clone,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_sticker,
+        forward_message,
+        copy_message,
+        send_message,
+        send_media_group,
+        send_location,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        pin_chat_message,
+        edit_message_text,
+        edit_message_text_inline,
+        send_invoice,
+        send_game
+        => fdef, ftydef
+    }
+
+    requester_forward! {
+        get_me,
+        log_out,
+        close,
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        kick_chat_member,
+        ban_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_members_count,
+        get_chat_member_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        get_sticker_set,
+        get_custom_emoji_stickers,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        create_invoice_link,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores,
+        approve_chat_join_request,
+        decline_chat_join_request
+        => f, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    Defaults<B>
+    { this => this.inner() }
+}
+
+/// Request returned by [`Defaults`] methods which have parameters with default
+/// values.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct DefaultsRequest<R: HasPayload> {
+    request: R,
+    values: Arc<Values>,
+    fill: fn(&mut R::Payload, &Values),
+}
+
+impl<R: HasPayload> HasPayload for DefaultsRequest<R> {
+    type Payload = R::Payload;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        self.request.payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.request.payload_ref()
+    }
+}
+
+impl<R> Request for DefaultsRequest<R>
+where
+    R: Request + Clone,
+{
+    type Err = R::Err;
+    type Send = R::Send;
+    type SendRef = R::Send;
+
+    fn send(mut self) -> Self::Send {
+        (self.fill)(self.request.payload_mut(), &self.values);
+        self.request.send()
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        // Parameters need to be filled in the payload, so the request is cloned
+        let mut request = self.request.clone();
+        (self.fill)(request.payload_mut(), &self.values);
+        request.send()
+    }
+}
+
+impl<R> IntoFuture for DefaultsRequest<R>
+where
+    R: Request + Clone,
+{
+    type Output = Result<Output<Self>, <Self as Request>::Err>;
+    type IntoFuture = <Self as Request>::Send;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use crate::{
+        adaptors::defaults::{DefaultValues, Defaults},
+        mock::MockBot,
+        payloads::{SendMessage, SendMessageSetters},
+        requests::Requester,
+        types::ChatId,
+    };
+
+    /// Returns a bot which records payloads of messages it sends.
+    fn bot() -> (MockBot, Arc<Mutex<Vec<SendMessage>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let bot = MockBot::new({
+            let sent = Arc::clone(&sent);
+            move |method, payload| {
+                assert_eq!(method, "SendMessage");
+                let payload = payload.downcast_ref::<SendMessage>().unwrap();
+                sent.lock().unwrap().push(payload.clone());
+
+                Ok(json!({
+                    "message_id": 1,
+                    "date": 0,
+                    "chat": { "id": 1, "first_name": "A", "type": "private" },
+                    "text": payload.text,
+                }))
+            }
+        });
+
+        (bot, sent)
+    }
+
+    #[tokio::test]
+    async fn fills_unset_parameters() {
+        let (bot, sent) = bot();
+        let bot = Defaults::new(
+            bot,
+            DefaultValues::default()
+                .disable_notification(true)
+                .protect_content(true)
+                .disable_web_page_preview(true)
+                .allow_sending_without_reply(true),
+        );
+
+        bot.send_message(ChatId(1), "text").await.unwrap();
+
+        // Parameters set by the caller are not overridden
+        bot.send_message(ChatId(1), "text")
+            .disable_notification(false)
+            .allow_sending_without_reply(false)
+            .await
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        let filled = &sent[0];
+        assert_eq!(filled.disable_notification, Some(true));
+        assert_eq!(filled.protect_content, Some(true));
+        assert_eq!(filled.disable_web_page_preview, Some(true));
+        assert_eq!(filled.allow_sending_without_reply, Some(true));
+
+        let set = &sent[1];
+        assert_eq!(set.disable_notification, Some(false));
+        assert_eq!(set.protect_content, Some(true));
+        assert_eq!(set.disable_web_page_preview, Some(true));
+        assert_eq!(set.allow_sending_without_reply, Some(false));
+    }
+
+    #[tokio::test]
+    async fn fills_chat_overrides() {
+        let (bot, sent) = bot();
+        let bot = Defaults::new(
+            bot,
+            DefaultValues::default()
+                .disable_notification(true)
+                .protect_content(true),
+        )
+        .chat(
+            ChatId(1),
+            DefaultValues::default().disable_notification(false),
+        );
+
+        bot.send_message(ChatId(1), "text").await.unwrap();
+        bot.send_message(ChatId(2), "text").await.unwrap();
+        // Overrides can't be applied to chats which are only known by their usernames
+        bot.send_message("@channel".to_owned(), "text")
+            .await
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        let values = |i: usize| {
+            let p = &sent[i];
+            (
+                p.disable_notification,
+                p.protect_content,
+                p.disable_web_page_preview,
+            )
+        };
+        assert_eq!(values(0), (Some(false), Some(true), None));
+        assert_eq!(values(1), (Some(true), Some(true), None));
+        assert_eq!(values(2), (Some(true), Some(true), None));
+    }
+
+    #[test]
+    fn chat_overrides() {
+        let bot = Defaults::new(
+            (),
+            DefaultValues::default()
+                .disable_notification(true)
+                .protect_content(true),
+        )
+        .chat(
+            ChatId(1),
+            DefaultValues::default().disable_notification(false),
+        );
+
+        let chat = bot.values(ChatId(1));
+        assert_eq!(chat.disable_notification, Some(false));
+        assert_eq!(chat.protect_content, Some(true));
+        assert_eq!(chat.disable_web_page_preview, None);
+
+        let other = bot.values(ChatId(2));
+        assert_eq!(other.disable_notification, Some(true));
+    }
+}
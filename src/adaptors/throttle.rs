@@ -1,3 +1,14 @@
+/// `ThrottleBackend` trait and its implementations
+mod backend;
+/// Queue of requests waiting to be sent, grouped by chats
+#[cfg(not(feature = "__bench"))]
+mod queue;
+/// Queue of requests waiting to be sent, grouped by chats
+///
+/// Only public for benchmarks, not a part of the public API.
+#[cfg(feature = "__bench")]
+#[doc(hidden)]
+pub mod queue;
 /// `ThrottlingRequest` and `ThrottlingSend` structures
 mod request;
 /// Lock that allows requests to wait until they are allowed to be sent
@@ -6,14 +17,27 @@ mod request_lock;
 mod requester_impl;
 /// `Settings` and `Limits` structures
 mod settings;
+/// `ShutdownReport` and related structures
+mod shutdown;
+/// `ThrottleStats` structure
+mod stats;
+/// `ThrottleStore` trait and its implementations
+mod store;
 /// "Worker" that checks the limits
 mod worker;
 
 use std::{
     future::Future,
     hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime},
 };
 
+use serde::{Deserialize, Serialize};
+
 use tokio::sync::{
     mpsc,
     oneshot::{self},
@@ -22,12 +46,16 @@ use tokio::sync::{
 use crate::{errors::AsResponseParameters, requests::Requester, types::*};
 
 use self::{
-    request_lock::{channel, RequestLock},
-    worker::{worker, FreezeUntil, InfoMessage},
+    request_lock::channel,
+    worker::{worker, FreezeUntil, InfoMessage, QueuedRequest},
 };
 
-pub use request::{ThrottlingRequest, ThrottlingSend};
-pub use settings::{Limits, Settings};
+pub use backend::{LoopbackBackend, SentRequest, ThrottleBackend};
+pub use request::{Priority, ThrottlingRequest, ThrottlingSend};
+pub use settings::{ClassLimits, LimitClass, Limits, Settings};
+pub use shutdown::{DroppedRequest, ShutdownError, ShutdownReport};
+pub use stats::ThrottleStats;
+pub use store::{FileStore, InMemoryStore, ThrottleState, ThrottleStore};
 
 /// Automatic request limits respecting mechanism.
 ///
@@ -38,14 +66,26 @@ pub use settings::{Limits, Settings};
 ///
 /// This bot wrapper automatically checks for limits, suspending requests until
 /// they could be sent without exceeding limits (request order in chats is not
-/// changed).
+/// changed, unless requests have different [priorities]).
+///
+/// Besides sending messages, edits, chat actions, answers to callback queries
+/// and administrative methods are throttled too. Every [`LimitClass`] is
+/// limited separately, with its own [`Limits`].
 ///
 /// It's recommended to use this wrapper before other wrappers (i.e.:
 /// `SomeWrapper<Throttle<Bot>>` not `Throttle<SomeWrapper<Bot>>`) because if
 /// done otherwise inner wrappers may cause `Throttle` to miscalculate limits
 /// usage.
 ///
+/// By default the history of sent requests is only kept in memory, so after a
+/// restart the bot may exceed the limits. Use [`Settings::store`] to persist
+/// it (e.g. in a [`FileStore`]).
+///
+/// Before stopping the bot, call [`Throttle::shutdown`], so that queued
+/// requests are not lost silently.
+///
 /// [limits]: https://core.telegram.org/bots/faq#my-bot-is-hitting-limits-how-do-i-avoid-this
+/// [priorities]: ThrottlingRequest::priority
 ///
 /// ## Examples
 ///
@@ -62,20 +102,34 @@ pub use settings::{Limits, Settings};
 ///
 /// Telegram have limits on sending messages to _the same chat_. To check them
 /// we store `chat_id`s of several last requests. _However_ there is no good way
-/// to tell if given `ChatId::Id(x)` corresponds to the same chat as
-/// `ChatId::ChannelUsername(u)`.
+/// to tell if given `Recipient::Id(x)` corresponds to the same chat as
+/// `Recipient::ChannelUsername(u)` without asking Telegram.
+///
+/// So the worker resolves usernames to ids with [`GetChat`] and caches them
+/// for [`Settings::channel_username_ttl`]. Requests sent by a username are
+/// held until it's resolved. If a username can't be resolved, requests sent by
+/// it are counted separately from requests sent by the id, which may give
+/// incorrect results, and the username is not resolved again for a minute.
 ///
-/// Our current approach is to just give up and check `chat_id_a == chat_id_b`.
-/// This may give incorrect results.
+/// [`GetChat`]: crate::payloads::GetChat
 ///
-/// As such, we encourage not to use `ChatId::ChannelUsername(u)` with this bot
-/// wrapper.
+/// ## Note about multiple processes
+///
+/// Every [`Throttle`] only knows about requests sent through it, so if the
+/// same bot token is used by several processes, together they may exceed the
+/// limits. To prevent this, make them share a [`ThrottleBackend`] (see
+/// [`Settings::backend`]).
 #[derive(Clone, Debug)]
 pub struct Throttle<B> {
     bot: B,
-    // `RequestLock` allows to unlock requests (allowing them to be sent).
-    queue: mpsc::Sender<(ChatIdHash, RequestLock)>,
+    // `QueuedRequest::lock` allows to unlock requests (allowing them to be sent).
+    queue: mpsc::Sender<QueuedRequest>,
     info_tx: mpsc::Sender<InfoMessage>,
+    shutdown: Arc<AtomicBool>,
+    // The number of requests which were sent, but weren't yet allowed to be
+    // executed by the worker (including requests which wait for space in the
+    // channel).
+    waiting: Arc<AtomicUsize>,
 }
 
 impl<B> Throttle<B> {
@@ -112,6 +166,8 @@ impl<B> Throttle<B> {
             bot,
             queue: tx,
             info_tx,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            waiting: Arc::new(AtomicUsize::new(0)),
         };
 
         (this, worker)
@@ -126,7 +182,6 @@ impl<B> Throttle<B> {
     where
         B: Requester + Clone + Send + Sync + 'static,
         B::Err: AsResponseParameters,
-        B::GetChat: Send,
     {
         let (this, worker) = Self::new(bot, limits);
 
@@ -140,7 +195,6 @@ impl<B> Throttle<B> {
     where
         B: Requester + Clone + Send + Sync + 'static,
         B::Err: AsResponseParameters,
-        B::GetChat: Send,
     {
         let (this, worker) = Self::with_settings(bot, settings);
 
@@ -185,23 +239,113 @@ impl<B> Throttle<B> {
 
         rx.await.ok();
     }
+
+    /// Returns a snapshot of the state of the worker: the queue of pending
+    /// requests, active freezes, etc.
+    ///
+    /// This can be used to monitor the backlog or to estimate when a request
+    /// to a chat would be sent (see [`ThrottleStats::estimated_delay`]).
+    pub async fn stats(&self) -> ThrottleStats {
+        const WORKER_DIED: &str = "worker died before last `Throttle` instance";
+
+        let (tx, rx) = oneshot::channel();
+
+        self.info_tx
+            .send(InfoMessage::GetStats { response: tx })
+            .await
+            .expect(WORKER_DIED);
+
+        let mut stats = rx.await.expect(WORKER_DIED);
+
+        // The worker doesn't know about requests which are still waiting for
+        // space in the channel
+        stats.queue_len = stats.queue_len.max(self.waiting.load(Ordering::Relaxed));
+        stats
+    }
+
+    /// Gracefully shuts down the worker.
+    ///
+    /// After this method is called, new requests (including retries of
+    /// requests which got a `RetryAfter` error) fail with [`ShutdownError`].
+    /// Already queued requests are still sent, respecting the limits, until the
+    /// `deadline`. Requests which were not sent before the `deadline` fail with
+    /// [`ShutdownError`] too and are listed in the returned report.
+    ///
+    /// The shutdown affects all clones of this [`Throttle`]. The worker future
+    /// completes once all of them are dropped.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use teloxide_core::{adaptors::throttle::Limits, requests::RequesterExt, Bot};
+    ///
+    /// # async {
+    /// let bot = Bot::new("TOKEN").throttle(Limits::default());
+    ///
+    /// /* send many requests here */
+    ///
+    /// let report = bot.shutdown(Instant::now() + Duration::from_secs(10)).await;
+    /// for dropped in report.dropped() {
+    ///     eprintln!("a request to {:?} was not sent", dropped.chat());
+    /// }
+    /// # };
+    /// ```
+    pub async fn shutdown(&self, deadline: Instant) -> ShutdownReport {
+        const WORKER_DIED: &str = "worker died before last `Throttle` instance";
+
+        self.shutdown.store(true, Ordering::Relaxed);
+
+        let (tx, rx) = oneshot::channel();
+
+        self.info_tx
+            .send(InfoMessage::Shutdown {
+                deadline,
+                response: tx,
+            })
+            .await
+            .expect(WORKER_DIED);
+
+        rx.await.expect(WORKER_DIED)
+    }
 }
 
 /// An ID used in the worker.
 ///
 /// It is used instead of `ChatId` to make copying cheap even in case of
 /// usernames. (It is just a hashed username.)
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize
+)]
 enum ChatIdHash {
     Id(ChatId),
     ChannelUsernameHash(u64),
+    /// Requests which are not bound to a chat, e.g. `answer_callback_query`.
+    NoChat,
 }
 
 impl ChatIdHash {
+    /// Returns `false` for hashes of usernames, which differ between builds, so
+    /// they can't be saved or shared with other processes.
+    fn is_stable(&self) -> bool {
+        !matches!(self, Self::ChannelUsernameHash(_))
+    }
+
     fn is_channel(&self) -> bool {
         match self {
             &Self::Id(id) => id.is_channel_or_supergroup(),
             Self::ChannelUsernameHash(_) => true,
+            Self::NoChat => false,
         }
     }
 }
@@ -221,3 +365,207 @@ impl From<&Recipient> for ChatIdHash {
         }
     }
 }
+
+/// Converts an [`Instant`] to a [`SystemTime`].
+///
+/// The result is approximate, since the clocks may drift apart.
+fn to_system_time(time: Instant) -> SystemTime {
+    let now = Instant::now();
+    let system_now = SystemTime::now();
+
+    match now.checked_duration_since(time) {
+        Some(ago) => system_now - ago,
+        None => system_now + time.duration_since(now),
+    }
+}
+
+/// Converts a [`SystemTime`] to an [`Instant`], returns `None` if the time
+/// can't be represented as an [`Instant`].
+///
+/// The result is approximate, since the clocks may drift apart.
+fn to_instant(time: SystemTime) -> Option<Instant> {
+    let now = Instant::now();
+
+    match SystemTime::now().duration_since(time) {
+        Ok(ago) => now.checked_sub(ago),
+        Err(err) => now.checked_add(err.duration()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        adaptors::throttle::{Limits, Throttle},
+        mock::MockBot,
+        payloads::{GetChat, SendMessage},
+        requests::{Request, Requester},
+        types::{ChatId, Recipient},
+        ApiError, RequestError,
+    };
+
+    fn text_message(text: &str) -> Value {
+        json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": { "id": 1, "first_name": "A", "type": "private" },
+            "text": text,
+        })
+    }
+
+    /// Returns a bot which only answers `send_message`.
+    fn bot() -> MockBot {
+        MockBot::new(|method, payload| {
+            assert_eq!(method, "SendMessage");
+            let payload = payload.downcast_ref::<SendMessage>().unwrap();
+            Ok(text_message(&payload.text))
+        })
+    }
+
+    #[tokio::test]
+    async fn resolves_usernames() {
+        let resolved = Arc::new(Mutex::new(HashMap::<String, u32>::new()));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+
+        let bot = MockBot::new({
+            let resolved = Arc::clone(&resolved);
+            let sent = Arc::clone(&sent);
+
+            move |method, payload| match method {
+                "GetChat" => {
+                    let username = match &payload.downcast_ref::<GetChat>().unwrap().chat_id {
+                        Recipient::ChannelUsername(username) => username.clone(),
+                        Recipient::Id(_) => unreachable!(),
+                    };
+                    *resolved
+                        .lock()
+                        .unwrap()
+                        .entry(username.clone())
+                        .or_default() += 1;
+
+                    match &*username {
+                        "@channel" => {
+                            Ok(json!({ "id": -1001234567890i64, "type": "channel", "title": "A" }))
+                        }
+                        _ => Err(RequestError::Api(ApiError::ChatNotFound)),
+                    }
+                }
+                "SendMessage" => {
+                    let payload = payload.downcast_ref::<SendMessage>().unwrap();
+                    sent.lock().unwrap().push(payload.chat_id.clone());
+
+                    Ok(text_message(&payload.text))
+                }
+                _ => unreachable!("unexpected method: {}", method),
+            }
+        });
+        let bot = Throttle::new_spawn(bot, Limits::default());
+
+        let send = |chat: &str| bot.send_message(chat.to_owned(), "text");
+        let (a, b, c, d) = tokio::join!(
+            send("@channel"),
+            send("@channel"),
+            send("@missing"),
+            send("@missing"),
+        );
+        for res in [a, b, c, d] {
+            res.unwrap();
+        }
+
+        // Usernames are resolved once for all requests, failures are cached too
+        let resolved = resolved.lock().unwrap().clone();
+        assert_eq!(
+            resolved,
+            [("@channel".to_owned(), 1), ("@missing".to_owned(), 1)].into()
+        );
+        assert_eq!(sent.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn stats_count_waiting_requests() {
+        // The channel to the worker and its queue can only hold one request
+        let limits = Limits {
+            messages_per_sec_overall: 1,
+            ..Limits::default()
+        };
+        let bot = Throttle::new_spawn(bot(), limits);
+
+        for _ in 0..4 {
+            tokio::spawn(bot.send_message(ChatId(1), "text").send());
+        }
+        // Let the worker read the next request after the first one is sent
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // The first message is sent, one is queued by the worker, one is in the
+        // channel and one waits for space in the channel
+        let stats = bot.stats().await;
+        assert_eq!(stats.queue_len(), 3);
+        assert_eq!(stats.pending(ChatId(1)), 1);
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_queue() {
+        let bot = Throttle::new_spawn(bot(), Limits::default());
+
+        // Only one message per second can be sent to a chat, so these are queued
+        let send = || bot.send_message(ChatId(1), "text");
+        let shutdown = async {
+            // Let the worker receive the requests
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            bot.shutdown(Instant::now() + Duration::from_secs(10)).await
+        };
+        let (a, b, report) = tokio::join!(send(), send(), shutdown);
+
+        a.unwrap();
+        b.unwrap();
+        assert!(report.is_complete());
+
+        // New requests are rejected
+        let err = bot.send_message(ChatId(1), "text").await.unwrap_err();
+        assert!(matches!(err, RequestError::ThrottleShutdown), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn shutdown_deadline() {
+        let bot = Throttle::new_spawn(bot(), Limits::default());
+
+        let send = || bot.send_message(ChatId(1), "text");
+        let shutdown = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            bot.shutdown(Instant::now()).await
+        };
+        let (a, b, report) = tokio::join!(send(), send(), shutdown);
+
+        a.unwrap();
+        let err = b.unwrap_err();
+        assert!(matches!(err, RequestError::ThrottleShutdown), "{:?}", err);
+
+        assert_eq!(report.dropped().len(), 1);
+        assert_eq!(report.dropped()[0].chat(), Some(ChatId(1)));
+    }
+
+    #[tokio::test]
+    async fn dead_worker() {
+        let (bot, worker) = Throttle::new(bot(), Limits::default());
+        let worker = tokio::spawn(worker);
+
+        let send = || bot.send_message(ChatId(1), "text");
+        let kill = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            worker.abort();
+        };
+        let (a, b, ()) = tokio::join!(send(), send(), kill);
+
+        // The request which was waiting in the queue of the dead worker is sent
+        // without throttling instead of failing with `ShutdownError`
+        a.unwrap();
+        b.unwrap();
+    }
+}
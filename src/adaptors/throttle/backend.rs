@@ -0,0 +1,268 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use futures::future::{ready, BoxFuture};
+use serde::{Deserialize, Serialize};
+
+use crate::adaptors::throttle::{ChatIdHash, LimitClass, Limits};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const SECOND: Duration = Duration::from_secs(1);
+
+/// Shared storage of requests sent by [`Throttle`]s.
+///
+/// By default every [`Throttle`] only knows about the requests it has sent
+/// itself. If the same bot token is used by several processes, they can
+/// coordinate through a backend: every worker records requests it sends to the
+/// backend and checks the limits against the requests sent by all of them.
+///
+/// Before sending a request, the worker acquires it from the backend with
+/// [`try_acquire`], which must check the limits and record the request
+/// atomically, so that workers sending requests at the same moment can't
+/// exceed the limits together. A backend for a key-value store (e.g. Redis)
+/// could store serialized [`SentRequest`]s in a sorted set, using
+/// [`SentRequest::time`] as the score, and check them with
+/// [`SentRequest::fits`] in a transaction.
+///
+/// If the backend fails, the worker logs the error and only checks the limits
+/// against the requests it has sent itself. The same is done for requests sent
+/// to channels by a username which the worker couldn't resolve to an id, since
+/// usernames are only tracked by their hash which isn't stable across builds.
+///
+/// See also [`Settings::backend`].
+///
+/// [`try_acquire`]: ThrottleBackend::try_acquire
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+/// [`Settings::backend`]: crate::adaptors::throttle::Settings::backend
+pub trait ThrottleBackend: Send + Sync {
+    /// Records the `request` if it [fits] the `limits` together with the
+    /// requests recorded before, returns `false` otherwise.
+    ///
+    /// The check and the record must be atomic.
+    ///
+    /// [fits]: SentRequest::fits
+    fn try_acquire<'a>(
+        &'a self,
+        request: SentRequest,
+        limits: &'a Limits,
+    ) -> BoxFuture<'a, io::Result<bool>>;
+}
+
+/// A request sent by a [`Throttle`], as stored in a [`ThrottleBackend`].
+///
+/// The chat to which the request was sent is opaque, but the request can be
+/// (de)serialized with `serde`.
+///
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SentRequest {
+    pub(super) class: LimitClass,
+    pub(super) chat: ChatIdHash,
+    pub(super) time: SystemTime,
+}
+
+/// In-process [`ThrottleBackend`].
+///
+/// Clones of this backend share the history, so all [`Throttle`]s using them
+/// respect the limits together. This is mostly useful for tests.
+///
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackBackend {
+    history: Arc<Mutex<VecDeque<SentRequest>>>,
+}
+
+impl SentRequest {
+    /// Returns the time at which the request was sent.
+    #[must_use]
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Returns the class of the method which was called.
+    #[must_use]
+    pub fn class(&self) -> LimitClass {
+        self.class
+    }
+
+    /// Returns `true` if this request can be sent without exceeding the
+    /// `limits`, given the requests sent in the minute before it (`history`).
+    ///
+    /// Requests in the `history` which were sent more than a minute before
+    /// this one are ignored.
+    #[must_use]
+    pub fn fits<'a, I>(&self, history: I, limits: &Limits) -> bool
+    where
+        I: IntoIterator<Item = &'a SentRequest>,
+    {
+        let (class_limits, per_min_channel) = limits.class(self.class);
+        let per_min_chat = if self.chat.is_channel() {
+            per_min_channel
+        } else {
+            class_limits.per_min_chat
+        };
+
+        let (mut sec_all, mut sec_overall, mut sec_chat, mut min_chat) = (0, 0, 0, 0);
+        for r in history {
+            // Requests sent "after" this one (e.g. because clocks of workers differ) are
+            // counted as sent at the same time
+            let ago = self.time.duration_since(r.time).unwrap_or_default();
+            if ago >= MINUTE {
+                continue;
+            }
+
+            // Requests of all classes share the overall limit
+            if ago < SECOND {
+                sec_all += 1;
+            }
+            if r.class != self.class {
+                continue;
+            }
+
+            let same_chat = r.chat == self.chat && self.chat != ChatIdHash::NoChat;
+            if ago < SECOND {
+                sec_overall += 1;
+                sec_chat += u32::from(same_chat);
+            }
+            min_chat += u32::from(same_chat);
+        }
+
+        // Only the overall limits apply to requests which are not bound to a chat
+        sec_all < limits.requests_per_sec_overall
+            && sec_overall < class_limits.per_sec_overall
+            && (self.chat == ChatIdHash::NoChat
+                || (sec_chat < class_limits.per_sec_chat.max(1) && min_chat < per_min_chat.max(1)))
+    }
+}
+
+impl LoopbackBackend {
+    /// Creates a backend with an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ThrottleBackend for LoopbackBackend {
+    fn try_acquire<'a>(
+        &'a self,
+        request: SentRequest,
+        limits: &'a Limits,
+    ) -> BoxFuture<'a, io::Result<bool>> {
+        let mut history = self.history.lock().unwrap();
+
+        // Forget about requests that don't affect the limits anymore
+        if let Some(min_back) = SystemTime::now().checked_sub(MINUTE) {
+            while history.front().map_or(false, |r| r.time < min_back) {
+                history.pop_front();
+            }
+        }
+
+        let acquired = request.fits(&*history, limits);
+        if acquired {
+            history.push_back(request);
+        }
+
+        Box::pin(ready(Ok(acquired)))
+    }
+}
+
+impl<B: ThrottleBackend + ?Sized> ThrottleBackend for Arc<B> {
+    fn try_acquire<'a>(
+        &'a self,
+        request: SentRequest,
+        limits: &'a Limits,
+    ) -> BoxFuture<'a, io::Result<bool>> {
+        (**self).try_acquire(request, limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{LoopbackBackend, SentRequest, ThrottleBackend};
+    use crate::{
+        adaptors::throttle::{ChatIdHash, LimitClass, Limits},
+        types::ChatId,
+    };
+
+    #[tokio::test]
+    async fn loopback() {
+        let a = LoopbackBackend::new();
+        let b = a.clone();
+
+        let limits = Limits {
+            messages_per_sec_overall: 2,
+            ..Limits::default()
+        };
+        let now = SystemTime::now();
+        let request = |id, time| SentRequest {
+            class: LimitClass::Send,
+            chat: ChatIdHash::Id(ChatId(id)),
+            time,
+        };
+
+        // Only one message per second can be sent to a chat, no matter by which worker
+        assert!(a.try_acquire(request(1, now), &limits).await.unwrap());
+        assert!(!b.try_acquire(request(1, now), &limits).await.unwrap());
+        assert!(b.try_acquire(request(2, now), &limits).await.unwrap());
+
+        // The overall limit is exceeded
+        assert!(!a.try_acquire(request(3, now), &limits).await.unwrap());
+
+        let later = now + Duration::from_secs(1);
+        assert!(b.try_acquire(request(1, later), &limits).await.unwrap());
+    }
+
+    #[test]
+    fn fits() {
+        let limits = Limits::default();
+        let now = SystemTime::now();
+        let request = |class, chat, ago| SentRequest {
+            class,
+            chat,
+            time: now - Duration::from_secs(ago),
+        };
+        let chat = ChatIdHash::Id(ChatId(1));
+        let channel = ChatIdHash::Id(ChatId(-1001234567890));
+
+        let new = request(LimitClass::Send, chat, 0);
+        assert!(new.fits(&[], &limits));
+        assert!(!new.fits(&[request(LimitClass::Send, chat, 0)], &limits));
+        // Requests of other classes and old requests don't count
+        assert!(new.fits(
+            &[
+                request(LimitClass::Edit, chat, 0),
+                request(LimitClass::Send, chat, 61),
+            ],
+            &limits
+        ));
+
+        // Channels have a per minute limit
+        let history: Vec<_> = (1..=limits.messages_per_min_channel)
+            .map(|i| request(LimitClass::Send, channel, u64::from(i)))
+            .collect();
+        assert!(!request(LimitClass::Send, channel, 0).fits(&history, &limits));
+        assert!(request(LimitClass::Send, chat, 0).fits(&history, &limits));
+
+        // Requests without a chat are only limited overall
+        let no_chat = request(LimitClass::ChatAction, ChatIdHash::NoChat, 0);
+        assert!(no_chat.fits(&[no_chat], &limits));
+
+        // All classes share the overall limit
+        let limits = Limits {
+            requests_per_sec_overall: 2,
+            ..limits
+        };
+        let history = [
+            request(LimitClass::Send, chat, 0),
+            request(LimitClass::Edit, chat, 0),
+        ];
+        assert!(!no_chat.fits(&history, &limits));
+        assert!(no_chat.fits(&history[..1], &limits));
+    }
+}
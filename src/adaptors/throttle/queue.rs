@@ -0,0 +1,391 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry, BTreeMap, BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+    time::Instant,
+};
+
+use super::Priority;
+
+/// Number of priorities, i.e. variants of [`Priority`].
+const LANES: usize = 3;
+
+/// Queue of requests waiting to be sent, grouped by chats.
+///
+/// Chats which can be sent a request right now are kept in ready sets (one per
+/// priority, ordered by the age of the oldest request to the chat), chats which
+/// hit their limits are kept in a timer heap until they can be sent a request
+/// again. This way the worker doesn't need to scan the whole queue to find
+/// requests which can be sent: pushing and popping a request is `O(log n)`.
+///
+/// When a chat can be sent the next request is decided by the `ready_at`
+/// function passed to [`pop`] and [`wake`], which gets the times at which the
+/// last requests were sent to the chat (see [`record_sent`]) and returns `None`
+/// if the chat doesn't hit any limits. If this time may
+/// become earlier for some chats (e.g. the limits are raised),
+/// [`reschedule`] must be called.
+///
+/// [`pop`]: ChatQueues::pop
+/// [`wake`]: ChatQueues::wake
+/// [`record_sent`]: ChatQueues::record_sent
+/// [`reschedule`]: ChatQueues::reschedule
+pub struct ChatQueues<K, T> {
+    chats: HashMap<K, Chat<T>>,
+
+    /// Ready chats by the sequence number of their oldest request with the
+    /// priority.
+    ready: [BTreeMap<u64, K>; LANES],
+
+    /// Chats which hit their limits by the time when they may be ready again.
+    waiting: BinaryHeap<Reverse<(Instant, K)>>,
+
+    next_seq: u64,
+    len: usize,
+
+    /// Sequence number of the request returned by the last call to `pop`.
+    popped_seq: u64,
+}
+
+struct Chat<T> {
+    /// Requests by their priority, alongside with their sequence numbers.
+    lanes: [VecDeque<(u64, T)>; LANES],
+
+    /// Times at which requests were sent to the chat, oldest first.
+    sent: VecDeque<Instant>,
+
+    /// `true` if the chat is in the `waiting` heap instead of the `ready` sets.
+    is_waiting: bool,
+}
+
+impl<K, T> ChatQueues<K, T>
+where
+    K: Copy + Eq + Hash + Ord,
+{
+    // `ChatQueues` is only public for benchmarks
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            chats: HashMap::new(),
+            ready: <_>::default(),
+            waiting: BinaryHeap::new(),
+            next_seq: 0,
+            len: 0,
+            popped_seq: 0,
+        }
+    }
+
+    /// Returns the number of queued requests.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds a request to the end of the queue of the `chat`.
+    pub fn push(&mut self, chat: K, priority: Priority, request: T) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.len += 1;
+
+        let lane = priority as usize;
+        let state = self.chats.entry(chat).or_default();
+
+        if state.lanes[lane].is_empty() && !state.is_waiting {
+            self.ready[lane].insert(seq, chat);
+        }
+
+        state.lanes[lane].push_back((seq, request));
+    }
+
+    /// Removes the oldest request with the `priority` to a chat which can be
+    /// sent a request at `now`.
+    pub fn pop<F>(&mut self, priority: Priority, now: Instant, ready_at: F) -> Option<(K, T)>
+    where
+        F: Fn(&K, &VecDeque<Instant>) -> Option<Instant>,
+    {
+        let lane = priority as usize;
+
+        loop {
+            let (&seq, &chat) = self.ready[lane].iter().next()?;
+            let state = self
+                .chats
+                .get_mut(&chat)
+                .expect("ready chats are in the map");
+
+            // The chat was ready when it was added to the ready sets, but it could hit the
+            // limits since then
+            if let Some(at) = ready_at(&chat, &state.sent).filter(|&at| at > now) {
+                remove_ready(&mut self.ready, chat, state);
+                state.is_waiting = true;
+                self.waiting.push(Reverse((at, chat)));
+                continue;
+            }
+
+            self.ready[lane].remove(&seq);
+            let (_, request) = state.lanes[lane]
+                .pop_front()
+                .expect("ready lanes are not empty");
+
+            if let Some(&(next, _)) = state.lanes[lane].front() {
+                self.ready[lane].insert(next, chat);
+            }
+
+            if state.is_empty() {
+                self.chats.remove(&chat);
+            }
+
+            self.len -= 1;
+            self.popped_seq = seq;
+            return Some((chat, request));
+        }
+    }
+
+    /// Returns the request which was just removed by [`pop`] to the front of
+    /// the queue of the `chat`, the chat is not ready until `at`.
+    ///
+    /// [`pop`]: ChatQueues::pop
+    pub fn requeue(&mut self, chat: K, priority: Priority, request: T, at: Instant) {
+        self.len += 1;
+
+        let state = self.chats.entry(chat).or_default();
+        if !state.is_waiting {
+            remove_ready(&mut self.ready, chat, state);
+            state.is_waiting = true;
+            self.waiting.push(Reverse((at, chat)));
+        }
+
+        state.lanes[priority as usize].push_front((self.popped_seq, request));
+    }
+
+    /// Moves chats which can be sent a request at `now` from the timer heap to
+    /// the ready sets.
+    pub fn wake<F>(&mut self, now: Instant, ready_at: F)
+    where
+        F: Fn(&K, &VecDeque<Instant>) -> Option<Instant>,
+    {
+        while let Some(&Reverse((at, chat))) = self.waiting.peek() {
+            if at > now {
+                break;
+            }
+
+            self.waiting.pop();
+
+            let state = match self.chats.get_mut(&chat) {
+                Some(state) if state.is_waiting => state,
+                _ => continue,
+            };
+
+            match ready_at(&chat, &state.sent).filter(|&at| at > now) {
+                Some(at) => self.waiting.push(Reverse((at, chat))),
+                None => {
+                    state.is_waiting = false;
+                    insert_ready(&mut self.ready, chat, state);
+                }
+            }
+        }
+    }
+
+    /// Moves all chats from the timer heap to the ready sets, so that the time
+    /// at which they can be sent a request is recomputed.
+    pub fn reschedule(&mut self) {
+        for Reverse((_, chat)) in self.waiting.drain() {
+            if let Some(state) = self.chats.get_mut(&chat) {
+                if state.is_waiting {
+                    state.is_waiting = false;
+                    insert_ready(&mut self.ready, chat, state);
+                }
+            }
+        }
+    }
+
+    /// Records that a request was sent to the `chat` at `time`.
+    ///
+    /// Requests must be recorded in the order they were sent.
+    pub fn record_sent(&mut self, chat: K, time: Instant) {
+        self.chats.entry(chat).or_default().sent.push_back(time);
+    }
+
+    /// Forgets the oldest request sent to the `chat`.
+    pub fn forget_sent(&mut self, chat: K) {
+        if let Entry::Occupied(mut entry) = self.chats.entry(chat) {
+            entry.get_mut().sent.pop_front();
+
+            if entry.get().is_empty() {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Removes all queued requests and returns them in no particular order.
+    pub fn drain(&mut self) -> Vec<(K, T)> {
+        self.ready = <_>::default();
+        self.waiting.clear();
+        self.len = 0;
+
+        let mut requests = Vec::new();
+        self.chats.retain(|&chat, state| {
+            state.is_waiting = false;
+
+            let lanes = state.lanes.iter_mut().flat_map(|lane| lane.drain(..));
+            requests.extend(lanes.map(|(_, request)| (chat, request)));
+
+            !state.sent.is_empty()
+        });
+
+        requests
+    }
+
+    /// Returns chats alongside with the number of requests queued for them and
+    /// the number of requests sent to them.
+    pub fn chats(&self) -> impl Iterator<Item = (K, usize, usize)> + '_ {
+        self.chats.iter().map(|(&chat, state)| {
+            let queued = state.lanes.iter().map(VecDeque::len).sum();
+            (chat, queued, state.sent.len())
+        })
+    }
+}
+
+impl<T> Chat<T> {
+    fn is_empty(&self) -> bool {
+        self.sent.is_empty() && self.lanes.iter().all(VecDeque::is_empty)
+    }
+}
+
+impl<T> Default for Chat<T> {
+    fn default() -> Self {
+        Self {
+            lanes: <_>::default(),
+            sent: VecDeque::new(),
+            is_waiting: false,
+        }
+    }
+}
+
+fn insert_ready<K: Copy + Ord, T>(ready: &mut [BTreeMap<u64, K>; LANES], chat: K, state: &Chat<T>) {
+    for (ready, requests) in ready.iter_mut().zip(&state.lanes) {
+        if let Some(&(seq, _)) = requests.front() {
+            ready.insert(seq, chat);
+        }
+    }
+}
+
+fn remove_ready<K: Copy + Ord, T>(ready: &mut [BTreeMap<u64, K>; LANES], chat: K, state: &Chat<T>) {
+    for (ready, requests) in ready.iter_mut().zip(&state.lanes) {
+        if let Some(&(seq, _)) = requests.front() {
+            let removed = ready.remove(&seq);
+            debug_assert!(removed == Some(chat));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    };
+
+    use super::ChatQueues;
+    use crate::adaptors::throttle::Priority;
+
+    #[test]
+    fn limits() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+
+        // Every chat can be sent one request per second
+        let ready_at = |_: &u32, sent: &VecDeque<Instant>| sent.back().map(|&last| last + secs(1));
+
+        let mut queue = ChatQueues::new();
+        queue.push(1, Priority::Normal, "a");
+        queue.push(1, Priority::Normal, "b");
+        queue.push(2, Priority::Low, "c");
+        queue.push(3, Priority::Normal, "d");
+        queue.push(2, Priority::High, "e");
+        assert_eq!(queue.len(), 5);
+
+        let mut pop = |priority, now| {
+            let (chat, request) = queue.pop(priority, now, ready_at)?;
+            queue.record_sent(chat, now);
+            Some(request)
+        };
+
+        assert_eq!(pop(Priority::High, start), Some("e"));
+        assert_eq!(pop(Priority::High, start), None);
+        assert_eq!(pop(Priority::Normal, start), Some("a"));
+        // Chat 1 needs to wait a second before "b" can be sent
+        assert_eq!(pop(Priority::Normal, start), Some("d"));
+        assert_eq!(pop(Priority::Normal, start), None);
+        // Chat 2 needs to wait too
+        assert_eq!(pop(Priority::Low, start), None);
+
+        let now = start + secs(1);
+        queue.wake(now, ready_at);
+        assert_eq!(queue.pop(Priority::Normal, now, ready_at), Some((1, "b")));
+        assert_eq!(queue.pop(Priority::Low, now, ready_at), Some((2, "c")));
+        assert!(queue.is_empty());
+
+        // Chats are forgotten when they have neither queued nor sent requests
+        let chats = |queue: &ChatQueues<u32, &str>| {
+            let mut chats: Vec<_> = queue.chats().collect();
+            chats.sort_unstable();
+            chats
+        };
+        assert_eq!(chats(&queue), [(1, 0, 1), (2, 0, 1), (3, 0, 1)]);
+
+        queue.forget_sent(1);
+        queue.forget_sent(2);
+        assert_eq!(chats(&queue), [(3, 0, 1)]);
+    }
+
+    #[test]
+    fn requeue() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(1);
+        let ready_at = |_: &u32, _: &VecDeque<Instant>| None;
+
+        let mut queue = ChatQueues::new();
+        queue.push(1, Priority::Normal, "a");
+        queue.push(1, Priority::Normal, "b");
+        queue.push(2, Priority::Normal, "c");
+
+        let (chat, request) = queue.pop(Priority::Normal, start, ready_at).unwrap();
+        assert_eq!((chat, request), (1, "a"));
+        queue.requeue(chat, Priority::Normal, request, later);
+        assert_eq!(queue.len(), 3);
+
+        // Chat 1 waits until `later`
+        assert_eq!(queue.pop(Priority::Normal, start, ready_at), Some((2, "c")));
+        assert_eq!(queue.pop(Priority::Normal, start, ready_at), None);
+
+        // ...and then its requests are sent in the original order
+        queue.wake(later, ready_at);
+        assert_eq!(queue.pop(Priority::Normal, later, ready_at), Some((1, "a")));
+        assert_eq!(queue.pop(Priority::Normal, later, ready_at), Some((1, "b")));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drain() {
+        let now = Instant::now();
+        let ready_at = |_: &u32, _: &VecDeque<Instant>| None;
+
+        let mut queue = ChatQueues::new();
+        queue.push(1, Priority::Normal, "a");
+        queue.push(1, Priority::Low, "b");
+        queue.push(2, Priority::High, "c");
+        queue.record_sent(2, now);
+
+        let mut drained = queue.drain();
+        drained.sort_unstable();
+        assert_eq!(drained, [(1, "a"), (1, "b"), (2, "c")]);
+
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(Priority::High, now, ready_at), None);
+
+        // Sent requests are still counted
+        assert_eq!(queue.chats().collect::<Vec<_>>(), [(2, 0, 1)]);
+    }
+}
@@ -1,7 +1,10 @@
 use std::{
     future::{Future, IntoFuture},
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
     time::Instant,
 };
 
@@ -12,17 +15,46 @@ use futures::{
 use tokio::sync::mpsc;
 
 use crate::{
-    adaptors::throttle::{channel, ChatIdHash, FreezeUntil, RequestLock},
+    adaptors::throttle::{
+        channel, ChatIdHash, FreezeUntil, LimitClass, QueuedRequest, ShutdownError,
+    },
     errors::AsResponseParameters,
     requests::{HasPayload, Output, Request},
+    types::Recipient,
 };
 
 /// Request returned by [`Throttling`](crate::adaptors::Throttle) methods.
 #[must_use = "Requests are lazy and do nothing unless sent"]
 pub struct ThrottlingRequest<R: HasPayload> {
     pub(super) request: Arc<R>,
-    pub(super) chat_id: fn(&R::Payload) -> ChatIdHash,
-    pub(super) worker: mpsc::Sender<(ChatIdHash, RequestLock)>,
+    /// Returns `None` if the request is not bound to a chat.
+    pub(super) chat_id: fn(&R::Payload) -> Option<&Recipient>,
+    pub(super) class: LimitClass,
+    pub(super) priority: Priority,
+    pub(super) worker: mpsc::Sender<QueuedRequest>,
+    pub(super) shutdown: Arc<AtomicBool>,
+    pub(super) waiting: Arc<AtomicUsize>,
+}
+
+/// Priority of a [`ThrottlingRequest`].
+///
+/// Requests with higher priority are sent before requests with lower priority
+/// (while still respecting the limits). Requests with [`Priority::Low`] can
+/// only use a part of the overall limit, see
+/// [`Settings::low_priority_share`].
+///
+/// [`Settings::low_priority_share`]: crate::adaptors::throttle::Settings::low_priority_share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Priority for requests which are not urgent, e.g. broadcasts.
+    Low,
+
+    /// Default priority.
+    #[default]
+    Normal,
+
+    /// Priority for urgent requests, e.g. replies to users.
+    High,
 }
 
 /// Future returned by [`ThrottlingRequest`]s.
@@ -35,6 +67,14 @@ enum ShareableRequest<R> {
     Owned(Option<R>),
 }
 
+impl<R: HasPayload> ThrottlingRequest<R> {
+    /// Sets the priority of this request.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
 impl<R: HasPayload + Clone> HasPayload for ThrottlingRequest<R> {
     type Payload = R::Payload;
 
@@ -52,7 +92,7 @@ impl<R: HasPayload + Clone> HasPayload for ThrottlingRequest<R> {
 impl<R> Request for ThrottlingRequest<R>
 where
     R: Request + Clone + Send + Sync + 'static, // TODO: rem static
-    R::Err: AsResponseParameters + Send,
+    R::Err: AsResponseParameters + From<ShutdownError> + Send,
     Output<R>: Send,
 {
     type Err = R::Err;
@@ -60,20 +100,36 @@ where
     type SendRef = ThrottlingSend<R>;
 
     fn send(self) -> Self::Send {
-        let chat = (self.chat_id)(self.payload_ref());
+        let chat = (self.chat_id)(self.payload_ref()).cloned();
         let request = match Arc::try_unwrap(self.request) {
             Ok(owned) => ShareableRequest::Owned(Some(owned)),
             Err(shared) => ShareableRequest::Shared(shared),
         };
-        let fut = send(request, chat, self.worker);
+        let fut = send(
+            request,
+            chat,
+            self.class,
+            self.priority,
+            self.worker,
+            self.shutdown,
+            self.waiting,
+        );
 
         ThrottlingSend(Box::pin(fut))
     }
 
     fn send_ref(&self) -> Self::SendRef {
-        let chat = (self.chat_id)(self.payload_ref());
+        let chat = (self.chat_id)(self.payload_ref()).cloned();
         let request = ShareableRequest::Shared(Arc::clone(&self.request));
-        let fut = send(request, chat, self.worker.clone());
+        let fut = send(
+            request,
+            chat,
+            self.class,
+            self.priority,
+            self.worker.clone(),
+            Arc::clone(&self.shutdown),
+            Arc::clone(&self.waiting),
+        );
 
         ThrottlingSend(Box::pin(fut))
     }
@@ -82,7 +138,7 @@ where
 impl<R> IntoFuture for ThrottlingRequest<R>
 where
     R: Request + Clone + Send + Sync + 'static,
-    R::Err: AsResponseParameters + Send,
+    R::Err: AsResponseParameters + From<ShutdownError> + Send,
     Output<R>: Send,
 {
     type Output = Result<Output<Self>, <Self as Request>::Err>;
@@ -154,26 +210,58 @@ where
 /// Actual implementation of the `ThrottlingSend` future
 async fn send<R>(
     mut request: ShareableRequest<R>,
-    chat: ChatIdHash,
-    worker: mpsc::Sender<(ChatIdHash, RequestLock)>,
+    recipient: Option<Recipient>,
+    class: LimitClass,
+    priority: Priority,
+    worker: mpsc::Sender<QueuedRequest>,
+    shutdown: Arc<AtomicBool>,
+    waiting: Arc<AtomicUsize>,
 ) -> Result<Output<R>, R::Err>
 where
     R: Request + Send + Sync + 'static,
-    R::Err: AsResponseParameters + Send,
+    R::Err: AsResponseParameters + From<ShutdownError> + Send,
     Output<R>: Send,
 {
+    let mut chat = recipient
+        .as_ref()
+        .map_or(ChatIdHash::NoChat, ChatIdHash::from);
+    let mut username = match recipient {
+        Some(Recipient::ChannelUsername(username)) => Some(username),
+        Some(Recipient::Id(_)) | None => None,
+    };
+
     // We use option in `ShareableRequest` to `take` when sending by value.
     //
     // All unwraps down below will succeed because we always return immediately
     // after taking.
 
     loop {
+        // Don't accept new requests (and retries) after `Throttle::shutdown`
+        if shutdown.load(Ordering::Relaxed) {
+            return Err(ShutdownError.into());
+        }
+
         let (lock, wait) = channel();
 
-        // The worker is unlikely to drop queue before sending all requests,
-        // but just in case it has dropped the queue, we want to just send the
-        // request.
-        if worker.send((chat, lock)).await.is_err() {
+        // Counts the request in `Throttle::stats` until the worker allows to send it
+        let guard = WaitingGuard::new(&waiting);
+
+        // The worker is unlikely to drop queue before sending all requests
+        // (unless it's shut down), but just in case it has dropped the queue,
+        // we want to just send the request.
+        let queued = QueuedRequest {
+            chat,
+            username: username.clone(),
+            class,
+            priority,
+            lock,
+        };
+
+        if worker.send(queued).await.is_err() {
+            if shutdown.load(Ordering::Relaxed) {
+                return Err(ShutdownError.into());
+            }
+
             log::error!("Worker dropped the queue before sending all requests");
 
             let res = match &mut request {
@@ -184,7 +272,29 @@ where
             return res;
         };
 
-        let (retry, freeze) = wait.await;
+        let (resolved, retry, freeze) = match wait.await {
+            Some(unlocked) => unlocked,
+            // The request wasn't sent before the deadline of the shutdown
+            None if shutdown.load(Ordering::Relaxed) => return Err(ShutdownError.into()),
+            // The worker has panicked or its future was dropped, there is no one to wait for
+            None => {
+                log::error!("Worker dropped a request without sending it");
+
+                let res = match &mut request {
+                    ShareableRequest::Shared(shared) => shared.send_ref().await,
+                    ShareableRequest::Owned(owned) => owned.take().unwrap().await,
+                };
+
+                return res;
+            }
+        };
+        drop(guard);
+
+        // Freezes and retries use the id to which the worker has resolved the username
+        if let ChatIdHash::Id(_) = resolved {
+            chat = resolved;
+            username = None;
+        }
 
         let res = match (retry, &mut request) {
             // Retries are turned on, use `send_ref` even if we have owned access
@@ -221,3 +331,19 @@ where
         };
     }
 }
+
+/// Decrements the number of waiting requests when dropped.
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl<'a> WaitingGuard<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Self(waiting)
+    }
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
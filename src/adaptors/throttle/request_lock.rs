@@ -9,7 +9,12 @@ use tokio::sync::{
     oneshot::{self, Receiver, Sender},
 };
 
-use crate::adaptors::throttle::FreezeUntil;
+use crate::adaptors::throttle::{ChatIdHash, FreezeUntil};
+
+/// Sent to a request when it's unlocked: the chat to which it is sent (with
+/// the username of a channel resolved to its id, if possible), whether it
+/// should be retried and where to report `RetryAfter` errors.
+type Unlocked = (ChatIdHash, bool, mpsc::Sender<FreezeUntil>);
 
 pub(super) fn channel() -> (RequestLock, RequestWaiter) {
     let (tx, rx) = oneshot::channel();
@@ -19,27 +24,30 @@ pub(super) fn channel() -> (RequestLock, RequestWaiter) {
 }
 
 #[must_use]
-pub(super) struct RequestLock(Sender<(bool, mpsc::Sender<FreezeUntil>)>);
+pub(super) struct RequestLock(Sender<Unlocked>);
 
 #[must_use]
 #[pin_project::pin_project]
-pub(super) struct RequestWaiter(#[pin] Receiver<(bool, mpsc::Sender<FreezeUntil>)>);
+pub(super) struct RequestWaiter(#[pin] Receiver<Unlocked>);
 
 impl RequestLock {
-    pub(super) fn unlock(self, retry: bool, freeze: mpsc::Sender<FreezeUntil>) -> Result<(), ()> {
-        self.0.send((retry, freeze)).map_err(drop)
+    pub(super) fn unlock(
+        self,
+        chat: ChatIdHash,
+        retry: bool,
+        freeze: mpsc::Sender<FreezeUntil>,
+    ) -> Result<(), ()> {
+        self.0.send((chat, retry, freeze)).map_err(drop)
     }
 }
 
+/// Resolves to `None` if the lock is dropped without unlocking (i.e. the
+/// worker is shut down).
 impl Future for RequestWaiter {
-    type Output = (bool, mpsc::Sender<FreezeUntil>);
+    type Output = Option<Unlocked>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        match this.0.poll(cx) {
-            Poll::Ready(Ok(ret)) => Poll::Ready(ret),
-            Poll::Ready(Err(_)) => panic!("`RequestLock` is dropped by the throttle worker"),
-            Poll::Pending => Poll::Pending,
-        }
+        this.0.poll(cx).map(Result::ok)
     }
 }
@@ -3,22 +3,60 @@ use std::sync::Arc;
 use url::Url;
 
 use crate::{
-    adaptors::{throttle::ThrottlingRequest, Throttle},
+    adaptors::{
+        throttle::{LimitClass, Priority, ShutdownError, ThrottlingRequest},
+        Throttle,
+    },
     errors::AsResponseParameters,
     requests::{HasPayload, Requester},
     types::*,
 };
 
-macro_rules! f {
-    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+macro_rules! throttling_request {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*), $class:ident, $chat_id:expr) => {
         ThrottlingRequest {
             request: Arc::new($this.inner().$m($($arg),*)),
-            chat_id: |p| (&p.payload_ref().chat_id).into(),
+            chat_id: $chat_id,
+            class: LimitClass::$class,
+            priority: Priority::default(),
             worker: $this.queue.clone(),
+            shutdown: Arc::clone(&$this.shutdown),
+            waiting: Arc::clone(&$this.waiting),
         }
     };
 }
 
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        throttling_request!($m $this ($($arg: $T),*), Send, |p| Some(&p.payload_ref().chat_id))
+    };
+}
+
+macro_rules! fedit {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        throttling_request!($m $this ($($arg: $T),*), Edit, |p| Some(&p.payload_ref().chat_id))
+    };
+}
+
+macro_rules! faction {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        throttling_request!($m $this ($($arg: $T),*), ChatAction, |p| Some(&p.payload_ref().chat_id))
+    };
+}
+
+// Callback queries are not bound to chats
+macro_rules! fcallback {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        throttling_request!($m $this ($($arg: $T),*), ChatAction, |_| None)
+    };
+}
+
+macro_rules! fadmin {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        throttling_request!($m $this ($($arg: $T),*), Admin, |p| Some(&p.payload_ref().chat_id))
+    };
+}
+
 macro_rules! fty {
     ($T:ident) => {
         ThrottlingRequest<B::$T>
@@ -39,7 +77,7 @@ macro_rules! ftyid {
 
 impl<B: Requester> Requester for Throttle<B>
 where
-    B::Err: AsResponseParameters,
+    B::Err: AsResponseParameters + From<ShutdownError>,
 
     B::SendMessage: Clone + Send + Sync + 'static,
     B::ForwardMessage: Clone + Send + Sync + 'static,
@@ -59,6 +97,40 @@ where
     B::SendDice: Clone + Send + Sync + 'static,
     B::SendSticker: Clone + Send + Sync + 'static,
     B::SendInvoice: Clone + Send + Sync + 'static,
+    B::EditMessageText: Clone + Send + Sync + 'static,
+    B::EditMessageCaption: Clone + Send + Sync + 'static,
+    B::EditMessageMedia: Clone + Send + Sync + 'static,
+    B::EditMessageReplyMarkup: Clone + Send + Sync + 'static,
+    B::EditMessageLiveLocation: Clone + Send + Sync + 'static,
+    B::StopMessageLiveLocation: Clone + Send + Sync + 'static,
+    B::StopPoll: Clone + Send + Sync + 'static,
+    B::DeleteMessage: Clone + Send + Sync + 'static,
+    B::SendChatAction: Clone + Send + Sync + 'static,
+    B::AnswerCallbackQuery: Clone + Send + Sync + 'static,
+    B::KickChatMember: Clone + Send + Sync + 'static,
+    B::BanChatMember: Clone + Send + Sync + 'static,
+    B::UnbanChatMember: Clone + Send + Sync + 'static,
+    B::RestrictChatMember: Clone + Send + Sync + 'static,
+    B::PromoteChatMember: Clone + Send + Sync + 'static,
+    B::SetChatAdministratorCustomTitle: Clone + Send + Sync + 'static,
+    B::BanChatSenderChat: Clone + Send + Sync + 'static,
+    B::UnbanChatSenderChat: Clone + Send + Sync + 'static,
+    B::SetChatPermissions: Clone + Send + Sync + 'static,
+    B::ExportChatInviteLink: Clone + Send + Sync + 'static,
+    B::CreateChatInviteLink: Clone + Send + Sync + 'static,
+    B::EditChatInviteLink: Clone + Send + Sync + 'static,
+    B::RevokeChatInviteLink: Clone + Send + Sync + 'static,
+    B::SetChatPhoto: Clone + Send + Sync + 'static,
+    B::DeleteChatPhoto: Clone + Send + Sync + 'static,
+    B::SetChatTitle: Clone + Send + Sync + 'static,
+    B::SetChatDescription: Clone + Send + Sync + 'static,
+    B::PinChatMessage: Clone + Send + Sync + 'static,
+    B::UnpinChatMessage: Clone + Send + Sync + 'static,
+    B::UnpinAllChatMessages: Clone + Send + Sync + 'static,
+    B::SetChatStickerSet: Clone + Send + Sync + 'static,
+    B::DeleteChatStickerSet: Clone + Send + Sync + 'static,
+    B::ApproveChatJoinRequest: Clone + Send + Sync + 'static,
+    B::DeclineChatJoinRequest: Clone + Send + Sync + 'static,
 {
     type Err = B::Err;
 
@@ -85,20 +157,28 @@ where
     }
 
     requester_forward! {
-        get_me,
-        log_out,
-        close,
-        get_updates,
-        set_webhook,
-        delete_webhook,
-        get_webhook_info,
+        edit_message_text,
+        edit_message_caption,
+        edit_message_media,
+        edit_message_reply_markup,
         edit_message_live_location,
-        edit_message_live_location_inline,
         stop_message_live_location,
-        stop_message_live_location_inline,
-        send_chat_action,
-        get_user_profile_photos,
-        get_file,
+        stop_poll,
+        delete_message
+        => fedit, fty
+    }
+
+    requester_forward! {
+        send_chat_action
+        => faction, fty
+    }
+
+    requester_forward! {
+        answer_callback_query
+        => fcallback, fty
+    }
+
+    requester_forward! {
         kick_chat_member,
         ban_chat_member,
         unban_chat_member,
@@ -119,15 +199,31 @@ where
         pin_chat_message,
         unpin_chat_message,
         unpin_all_chat_messages,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        approve_chat_join_request,
+        decline_chat_join_request
+        => fadmin, fty
+    }
+
+    requester_forward! {
+        get_me,
+        log_out,
+        close,
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        edit_message_live_location_inline,
+        stop_message_live_location_inline,
+        get_user_profile_photos,
+        get_file,
         leave_chat,
         get_chat,
         get_chat_administrators,
         get_chat_members_count,
         get_chat_member_count,
         get_chat_member,
-        set_chat_sticker_set,
-        delete_chat_sticker_set,
-        answer_callback_query,
         set_my_commands,
         get_my_commands,
         set_chat_menu_button,
@@ -137,16 +233,10 @@ where
         delete_my_commands,
         answer_inline_query,
         answer_web_app_query,
-        edit_message_text,
         edit_message_text_inline,
-        edit_message_caption,
         edit_message_caption_inline,
-        edit_message_media,
         edit_message_media_inline,
-        edit_message_reply_markup,
         edit_message_reply_markup_inline,
-        stop_poll,
-        delete_message,
         get_sticker_set,
         get_custom_emoji_stickers,
         upload_sticker_file,
@@ -162,8 +252,6 @@ where
         send_game,
         set_game_score,
         set_game_score_inline,
-        approve_chat_join_request,
-        decline_chat_join_request,
         get_game_high_scores
         => fid, ftyid
     }
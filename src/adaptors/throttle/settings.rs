@@ -1,6 +1,9 @@
-use std::pin::Pin;
+use std::{pin::Pin, time::Duration};
 
 use futures::{future::ready, Future};
+use serde::{Deserialize, Serialize};
+
+use crate::adaptors::throttle::{ThrottleBackend, ThrottleStore};
 
 // Required to not trigger `clippy::type-complexity` lint
 type BoxedFnMut<I, O> = Box<dyn FnMut(I) -> O + Send>;
@@ -28,17 +31,29 @@ pub struct Settings {
     pub on_queue_full: BoxedFnMut<usize, BoxedFuture>,
     pub retry: bool,
     pub check_slow_mode: bool,
+    pub low_priority_share: u8,
+    pub channel_username_ttl: Duration,
+    pub queue_capacity: Option<usize>,
+    pub store: Option<Box<dyn ThrottleStore>>,
+    pub backend: Option<Box<dyn ThrottleBackend>>,
 }
 
 /// Telegram request limits.
 ///
 /// This struct is used in [`Throttle`].
 ///
+/// Limits of sending messages are set by `messages_*` fields, limits of other
+/// [`LimitClass`]es are set by their own fields. Telegram doesn't document
+/// limits other than limits of sending messages, so defaults of other classes
+/// are chosen to be close to them. Requests of all classes together are
+/// limited by [`requests_per_sec_overall`].
+///
 /// Note that you may ask telegram [@BotSupport] to increase limits for your
 /// particular bot if it has a lot of users (but they may or may not do that).
 ///
 /// [@BotSupport]: https://t.me/botsupport
 /// [`Throttle`]: crate::adaptors::throttle::Throttle
+/// [`requests_per_sec_overall`]: Limits::requests_per_sec_overall
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Limits {
     /// Allowed messages in one chat per second.
@@ -52,6 +67,91 @@ pub struct Limits {
 
     /// Allowed messages per second.
     pub messages_per_sec_overall: u32,
+
+    /// Limits of [`LimitClass::Edit`] methods.
+    pub edits: ClassLimits,
+
+    /// Limits of [`LimitClass::ChatAction`] methods.
+    pub chat_actions: ClassLimits,
+
+    /// Limits of [`LimitClass::Admin`] methods.
+    pub admin: ClassLimits,
+
+    /// Allowed requests of all [`LimitClass`]es together per second.
+    ///
+    /// Overall limits of classes only split this budget, so that e.g. edits
+    /// can't slow down sending messages too much.
+    pub requests_per_sec_overall: u32,
+}
+
+/// Limits of a [`LimitClass`] other than [`LimitClass::Send`].
+///
+/// Every class is limited separately, e.g. edits don't use limits of sending
+/// messages, but all classes share [`Limits::requests_per_sec_overall`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ClassLimits {
+    /// Allowed requests to one chat per second.
+    pub per_sec_chat: u32,
+
+    /// Allowed requests to one chat (or channel) per minute.
+    pub per_min_chat: u32,
+
+    /// Allowed requests of this class per second.
+    pub per_sec_overall: u32,
+}
+
+/// Group of methods which are limited together.
+///
+/// Methods which are not listed here are not throttled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LimitClass {
+    /// Sending messages: `send_*`, `forward_message` and `copy_message`.
+    ///
+    /// Limited by `messages_*` fields of [`Limits`].
+    Send,
+
+    /// Editing and deleting messages: `edit_message_*` (except for inline
+    /// messages), `stop_message_live_location`, `stop_poll` and
+    /// `delete_message`.
+    Edit,
+
+    /// `send_chat_action` and `answer_callback_query`.
+    ///
+    /// Answers to callback queries are not bound to chats, so only
+    /// [`ClassLimits::per_sec_overall`] applies to them.
+    ChatAction,
+
+    /// Managing chats: banning and restricting members, changing chat
+    /// settings, managing invite links, pinning messages, etc.
+    Admin,
+}
+
+impl LimitClass {
+    /// All classes.
+    pub(super) const ALL: [Self; 4] = [Self::Send, Self::Edit, Self::ChatAction, Self::Admin];
+}
+
+impl Limits {
+    /// Returns limits of the `class`, alongside with the number of allowed
+    /// requests to one channel per minute.
+    pub(super) fn class(&self, class: LimitClass) -> (ClassLimits, u32) {
+        let limits = match class {
+            LimitClass::Send => {
+                let limits = ClassLimits {
+                    per_sec_chat: self.messages_per_sec_chat,
+                    per_min_chat: self.messages_per_min_chat,
+                    per_sec_overall: self.messages_per_sec_overall,
+                };
+
+                return (limits, self.messages_per_min_channel);
+            }
+            LimitClass::Edit => self.edits,
+            LimitClass::ChatAction => self.chat_actions,
+            LimitClass::Admin => self.admin,
+        };
+
+        (limits, limits.per_min_chat)
+    }
 }
 
 impl Settings {
@@ -78,6 +178,67 @@ impl Settings {
         self.check_slow_mode = true;
         self
     }
+
+    /// Sets the share of [`Limits::messages_per_sec_overall`] (in percents)
+    /// which can be used by requests with [`Priority::Low`], so that they
+    /// don't delay other requests too much. Defaults to `50`.
+    ///
+    /// Values greater than `100` are treated as `100`. Unless the share is `0`,
+    /// low priority requests are always allowed to use at least one request
+    /// per second. With `0` low priority requests are never sent, they stay in
+    /// the queue until the shutdown.
+    ///
+    /// [`Priority::Low`]: crate::adaptors::throttle::Priority::Low
+    pub fn low_priority_share(mut self, percent: u8) -> Self {
+        self.low_priority_share = percent;
+        self
+    }
+
+    /// Sets for how long ids of channels resolved from their usernames are
+    /// cached. Defaults to 1 hour.
+    ///
+    /// See also "Note about send-by-@channelusername" in [`Throttle`] docs.
+    ///
+    /// [`Throttle`]: crate::adaptors::throttle::Throttle
+    pub fn channel_username_ttl(mut self, ttl: Duration) -> Self {
+        self.channel_username_ttl = ttl;
+        self
+    }
+
+    /// Sets the maximum number of requests the worker keeps in its queue.
+    /// Other requests wait until there is a room for them and
+    /// `on_queue_full` is called when the queue is full.
+    ///
+    /// Defaults to [`Limits::messages_per_sec_overall`]. Bigger queues allow
+    /// the worker to send requests to other chats while some chats hit their
+    /// limits (e.g. when broadcasting), at the cost of memory.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Sets the store in which the worker persists the history of sent
+    /// requests and active freezes, so that they survive restarts.
+    pub fn store<S>(mut self, store: S) -> Self
+    where
+        S: ThrottleStore + 'static,
+    {
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    /// Sets the backend through which the worker shares the history of sent
+    /// requests with other [`Throttle`]s (possibly in other processes), so
+    /// that they respect the limits together.
+    ///
+    /// [`Throttle`]: crate::adaptors::throttle::Throttle
+    pub fn backend<B>(mut self, backend: B) -> Self
+    where
+        B: ThrottleBackend + 'static,
+    {
+        self.backend = Some(Box::new(backend));
+        self
+    }
 }
 
 impl Default for Settings {
@@ -90,6 +251,11 @@ impl Default for Settings {
             }),
             retry: true,
             check_slow_mode: false,
+            low_priority_share: 50,
+            channel_username_ttl: Duration::from_secs(60 * 60),
+            queue_capacity: None,
+            store: None,
+            backend: None,
         }
     }
 }
@@ -105,6 +271,22 @@ impl Default for Limits {
             messages_per_sec_overall: 30,
             messages_per_min_chat: 20,
             messages_per_min_channel: 10,
+            edits: ClassLimits {
+                per_sec_chat: 1,
+                per_min_chat: 20,
+                per_sec_overall: 30,
+            },
+            chat_actions: ClassLimits {
+                per_sec_chat: 1,
+                per_min_chat: 60,
+                per_sec_overall: 30,
+            },
+            admin: ClassLimits {
+                per_sec_chat: 1,
+                per_min_chat: 20,
+                per_sec_overall: 30,
+            },
+            requests_per_sec_overall: 30,
         }
     }
 }
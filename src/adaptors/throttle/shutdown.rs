@@ -0,0 +1,94 @@
+use std::fmt;
+
+use crate::{
+    adaptors::throttle::{ChatIdHash, LimitClass, Priority},
+    types::ChatId,
+    RequestError,
+};
+
+/// Error returned by requests which were not sent because the [`Throttle`]
+/// was shut down.
+///
+/// See [`Throttle::shutdown`].
+///
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+/// [`Throttle::shutdown`]: crate::adaptors::throttle::Throttle::shutdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShutdownError;
+
+/// Report of a [`Throttle::shutdown`].
+///
+/// [`Throttle::shutdown`]: crate::adaptors::throttle::Throttle::shutdown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub(super) dropped: Vec<DroppedRequest>,
+}
+
+/// A request which was not sent before the deadline of a
+/// [`Throttle::shutdown`].
+///
+/// [`Throttle::shutdown`]: crate::adaptors::throttle::Throttle::shutdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DroppedRequest {
+    pub(super) chat: ChatIdHash,
+    pub(super) class: LimitClass,
+    pub(super) priority: Priority,
+}
+
+impl ShutdownReport {
+    /// Returns requests which were not sent before the deadline, in no
+    /// particular order.
+    ///
+    /// All of them have failed with [`ShutdownError`].
+    #[must_use]
+    pub fn dropped(&self) -> &[DroppedRequest] {
+        &self.dropped
+    }
+
+    /// Returns `true` if all queued requests were sent before the deadline.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.dropped.is_empty()
+    }
+}
+
+impl DroppedRequest {
+    /// Returns the chat to which the request was addressed.
+    ///
+    /// Returns `None` for channels whose `@channelusername` couldn't be
+    /// resolved to an id and for requests which are not addressed to a chat
+    /// (answers to callback queries).
+    #[must_use]
+    pub fn chat(&self) -> Option<ChatId> {
+        match self.chat {
+            ChatIdHash::Id(id) => Some(id),
+            ChatIdHash::ChannelUsernameHash(_) | ChatIdHash::NoChat => None,
+        }
+    }
+
+    /// Returns the class of the method which was called.
+    #[must_use]
+    pub fn class(&self) -> LimitClass {
+        self.class
+    }
+
+    /// Returns the priority of the request.
+    #[must_use]
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+}
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the request was dropped because `Throttle` was shut down")
+    }
+}
+
+impl std::error::Error for ShutdownError {}
+
+impl From<ShutdownError> for RequestError {
+    fn from(ShutdownError: ShutdownError) -> Self {
+        RequestError::ThrottleShutdown
+    }
+}
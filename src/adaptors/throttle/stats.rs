@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    adaptors::throttle::{ChatIdHash, Limits},
+    types::ChatId,
+};
+
+/// A snapshot of the state of the [`Throttle`] worker.
+///
+/// Returned by [`Throttle::stats`].
+///
+/// Pending requests of all [`LimitClass`]es are counted, including requests
+/// which are held by the worker while their `@channelusername` is resolved.
+/// Only the number of sent messages ([`LimitClass::Send`]) is tracked.
+///
+/// Chats are identified by their ids. Requests sent by `@channelusername` are
+/// counted under the id of the channel once the worker has resolved the
+/// username (see "Note about send-by-@channelusername" in [`Throttle`] docs),
+/// until then they are only yielded by [`pending_by_chat`].
+///
+/// [`LimitClass`]: crate::adaptors::throttle::LimitClass
+/// [`LimitClass::Send`]: crate::adaptors::throttle::LimitClass::Send
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+/// [`Throttle::stats`]: crate::adaptors::throttle::Throttle::stats
+/// [`pending_by_chat`]: ThrottleStats::pending_by_chat
+#[derive(Debug, Clone)]
+pub struct ThrottleStats {
+    pub(super) taken_at: Instant,
+    pub(super) limits: Limits,
+    pub(super) queue_len: usize,
+    pub(super) pending: HashMap<ChatIdHash, usize>,
+    // Only requests of `LimitClass::Send`, used to estimate delays
+    pub(super) pending_messages: HashMap<ChatIdHash, usize>,
+    pub(super) sent_per_min: HashMap<ChatIdHash, u32>,
+    pub(super) frozen_until: Option<Instant>,
+    pub(super) slow_mode_until: HashMap<ChatIdHash, Instant>,
+}
+
+impl ThrottleStats {
+    /// Returns the time at which this snapshot was taken.
+    #[must_use]
+    pub fn taken_at(&self) -> Instant {
+        self.taken_at
+    }
+
+    /// Returns [`Limits`] used by the worker.
+    #[must_use]
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Returns the number of requests which wait to be sent.
+    ///
+    /// This includes requests which wait for space in the channel to the
+    /// worker (its capacity is [`Limits::messages_per_sec_overall`]), so this
+    /// may be bigger than the sum of [`pending_by_chat`].
+    ///
+    /// Requests which got a `RetryAfter` error are counted again only after
+    /// the freeze ends and they are resent to the worker.
+    ///
+    /// [`pending_by_chat`]: ThrottleStats::pending_by_chat
+    #[must_use]
+    pub fn queue_len(&self) -> usize {
+        self.queue_len
+    }
+
+    /// Returns the number of requests to the `chat` received by the worker,
+    /// which wait to be sent.
+    ///
+    /// Requests which still wait for space in the channel to the worker are
+    /// not counted, since the worker doesn't know their chats yet.
+    #[must_use]
+    pub fn pending(&self, chat: ChatId) -> usize {
+        self.pending
+            .get(&ChatIdHash::Id(chat))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of requests received by the worker, which wait to be
+    /// sent, for every chat which has such requests.
+    ///
+    /// Requests which are not bound to a chat and requests to channels whose
+    /// `@channelusername` wasn't resolved to an id (yet) are yielded as
+    /// `None`, possibly multiple times.
+    pub fn pending_by_chat(&self) -> impl Iterator<Item = (Option<ChatId>, usize)> + '_ {
+        self.pending.iter().map(|(chat, &pending)| match chat {
+            ChatIdHash::Id(id) => (Some(*id), pending),
+            ChatIdHash::ChannelUsernameHash(_) | ChatIdHash::NoChat => (None, pending),
+        })
+    }
+
+    /// Returns the time until which the bot is frozen because of a
+    /// `RetryAfter` error, if it is.
+    #[must_use]
+    pub fn frozen_until(&self) -> Option<Instant> {
+        self.frozen_until
+    }
+
+    /// Returns the time until which no requests can be sent to the `chat`
+    /// because of its slow mode.
+    ///
+    /// Slow mode is only tracked if it's enabled with
+    /// [`Settings::check_slow_mode`].
+    ///
+    /// [`Settings::check_slow_mode`]: crate::adaptors::throttle::Settings::check_slow_mode
+    #[must_use]
+    pub fn slow_mode_until(&self, chat: ChatId) -> Option<Instant> {
+        self.slow_mode_until.get(&ChatIdHash::Id(chat)).copied()
+    }
+
+    /// Returns an estimated delay (from [`taken_at`]) before a new message to
+    /// the `chat` would be sent.
+    ///
+    /// The estimation is rough: it assumes that the new request has normal
+    /// priority, that no other requests are sent, that all requests in
+    /// [`queue_len`] share the overall limit and that no `RetryAfter` errors
+    /// happen.
+    ///
+    /// [`taken_at`]: ThrottleStats::taken_at
+    /// [`queue_len`]: ThrottleStats::queue_len
+    #[must_use]
+    pub fn estimated_delay(&self, chat: ChatId) -> Duration {
+        const SECOND: Duration = Duration::from_secs(1);
+        const MINUTE: Duration = Duration::from_secs(60);
+
+        let chat = ChatIdHash::Id(chat);
+        let limits = &self.limits;
+
+        let pending = self.pending_messages.get(&chat).copied().unwrap_or(0) as u32;
+        let sent_per_min = self.sent_per_min.get(&chat).copied().unwrap_or(0);
+        let messages_per_min_limit = if chat.is_channel() {
+            limits.messages_per_min_channel
+        } else {
+            limits.messages_per_min_chat
+        };
+
+        // All pending requests need to be sent before the new one
+        let chat_per_sec = SECOND * pending / limits.messages_per_sec_chat.max(1);
+        let chat_per_min = MINUTE * ((sent_per_min + pending) / messages_per_min_limit.max(1));
+        let overall = SECOND * self.queue_len as u32 / limits.requests_per_sec_overall.max(1);
+
+        let blocked = [self.frozen_until, self.slow_mode_until.get(&chat).copied()]
+            .into_iter()
+            .flatten()
+            .max()
+            .map_or(Duration::ZERO, |until| {
+                until.saturating_duration_since(self.taken_at)
+            });
+
+        chat_per_sec.max(chat_per_min).max(overall).max(blocked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use super::ThrottleStats;
+    use crate::{
+        adaptors::throttle::{ChatIdHash, Limits},
+        types::ChatId,
+    };
+
+    #[test]
+    fn estimated_delay() {
+        let now = Instant::now();
+        let secs = Duration::from_secs;
+
+        let mut stats = ThrottleStats {
+            taken_at: now,
+            limits: Limits::default(),
+            queue_len: 0,
+            pending: HashMap::new(),
+            pending_messages: HashMap::new(),
+            sent_per_min: HashMap::new(),
+            frozen_until: None,
+            slow_mode_until: HashMap::new(),
+        };
+        assert_eq!(stats.estimated_delay(ChatId(1)), Duration::ZERO);
+
+        stats.queue_len = 60;
+        stats.pending.insert(ChatIdHash::Id(ChatId(1)), 7);
+        stats.pending_messages.insert(ChatIdHash::Id(ChatId(1)), 5);
+        assert_eq!(stats.pending(ChatId(1)), 7);
+        assert_eq!(stats.pending(ChatId(2)), 0);
+        assert_eq!(stats.estimated_delay(ChatId(1)), secs(5));
+        assert_eq!(stats.estimated_delay(ChatId(2)), secs(2));
+
+        // 20 messages per minute are already used
+        stats.sent_per_min.insert(ChatIdHash::Id(ChatId(1)), 15);
+        assert_eq!(stats.estimated_delay(ChatId(1)), secs(60));
+
+        stats.frozen_until = Some(now + secs(100));
+        assert_eq!(stats.estimated_delay(ChatId(2)), secs(100));
+    }
+}
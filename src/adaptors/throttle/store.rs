@@ -0,0 +1,266 @@
+use std::{
+    collections::VecDeque,
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures::future::{ready, BoxFuture};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::adaptors::throttle::{to_instant, to_system_time, ChatIdHash, LimitClass};
+
+/// Storage for the state of the [`Throttle`] worker.
+///
+/// The worker saves its state to the store periodically and loads it on
+/// startup, so that the limits are respected even after a restart of the bot.
+///
+/// Requests sent to channels by a username which the worker couldn't resolve
+/// to an id are not saved, since usernames are only tracked by their hash which
+/// isn't stable across builds.
+///
+/// See also [`Settings::store`].
+///
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+/// [`Settings::store`]: crate::adaptors::throttle::Settings::store
+pub trait ThrottleStore: Send {
+    /// Loads the state saved by the last call to [`save`], returns `Ok(None)`
+    /// if there is no saved state.
+    ///
+    /// [`save`]: ThrottleStore::save
+    fn load(&mut self) -> BoxFuture<'_, io::Result<Option<ThrottleState>>>;
+
+    /// Saves the state, overwriting the previously saved one.
+    fn save<'a>(&'a mut self, state: &'a ThrottleState) -> BoxFuture<'a, io::Result<()>>;
+}
+
+/// State of the [`Throttle`] worker that is saved to a [`ThrottleStore`].
+///
+/// The contents are opaque, but the state can be (de)serialized with `serde`.
+///
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThrottleState {
+    /// Requests sent in the last minute, oldest first.
+    history: Vec<(LimitClass, ChatIdHash, SystemTime)>,
+
+    /// The time until which the bot is frozen because of a `RetryAfter` error.
+    freeze_until: Option<SystemTime>,
+}
+
+/// [`ThrottleStore`] which saves the state into a file as JSON.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+/// [`ThrottleStore`] which keeps the state in memory.
+///
+/// Clones of this store share the state, so it can be used to preserve the
+/// state when a [`Throttle`] is recreated in the same process.
+///
+/// [`Throttle`]: crate::adaptors::throttle::Throttle
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    state: Arc<Mutex<Option<ThrottleState>>>,
+}
+
+impl FileStore {
+    /// Creates a store which saves the state into the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ThrottleStore for FileStore {
+    fn load(&mut self) -> BoxFuture<'_, io::Result<Option<ThrottleState>>> {
+        Box::pin(async move {
+            let bytes = match fs::read(&self.path).await {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(err) => return Err(err),
+            };
+
+            serde_json::from_slice(&bytes).map(Some).map_err(Into::into)
+        })
+    }
+
+    fn save<'a>(&'a mut self, state: &'a ThrottleState) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            // Write to a temporary file first and then rename it, so that the state is
+            // not corrupted if the bot is killed in the middle of writing
+            let mut tmp = self.path.clone().into_os_string();
+            tmp.push(".tmp");
+
+            fs::write(&tmp, serde_json::to_vec(state)?).await?;
+            fs::rename(&tmp, &self.path).await
+        })
+    }
+}
+
+impl InMemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ThrottleStore for InMemoryStore {
+    fn load(&mut self) -> BoxFuture<'_, io::Result<Option<ThrottleState>>> {
+        Box::pin(ready(Ok(self.state.lock().unwrap().clone())))
+    }
+
+    fn save<'a>(&'a mut self, state: &'a ThrottleState) -> BoxFuture<'a, io::Result<()>> {
+        *self.state.lock().unwrap() = Some(state.clone());
+        Box::pin(ready(Ok(())))
+    }
+}
+
+impl<S: ThrottleStore + ?Sized> ThrottleStore for Box<S> {
+    fn load(&mut self) -> BoxFuture<'_, io::Result<Option<ThrottleState>>> {
+        (**self).load()
+    }
+
+    fn save<'a>(&'a mut self, state: &'a ThrottleState) -> BoxFuture<'a, io::Result<()>> {
+        (**self).save(state)
+    }
+}
+
+/// Loads the history and the freeze from the `store`, dropping records that
+/// are older than `max_age`.
+pub(super) async fn load_state(
+    store: &mut dyn ThrottleStore,
+    max_age: Duration,
+) -> (VecDeque<(LimitClass, ChatIdHash, Instant)>, Option<Instant>) {
+    let state = match store.load().await {
+        Ok(state) => state.unwrap_or_default(),
+        Err(err) => {
+            log::warn!("couldn't load `Throttle` state: {}", err);
+            return <_>::default();
+        }
+    };
+
+    let now = Instant::now();
+
+    // Times in the future are clamped to "now", times too far in the past are
+    // dropped
+    let history = state
+        .history
+        .into_iter()
+        .filter(|(_, chat, _)| chat.is_stable())
+        .filter_map(|(class, chat, time)| Some((class, chat, to_instant(time)?.min(now))))
+        .filter(|&(_, _, time)| now.duration_since(time) <= max_age)
+        .collect();
+
+    let freeze_until = state
+        .freeze_until
+        .and_then(to_instant)
+        .filter(|&until| until > now);
+
+    (history, freeze_until)
+}
+
+/// Saves the history and the freeze to the `store`.
+pub(super) async fn save_state(
+    store: &mut dyn ThrottleStore,
+    history: &VecDeque<(LimitClass, ChatIdHash, Instant)>,
+    freeze_until: Option<Instant>,
+) {
+    let state = ThrottleState {
+        history: history
+            .iter()
+            .filter(|(_, chat, _)| chat.is_stable())
+            .map(|&(class, chat, time)| (class, chat, to_system_time(time)))
+            .collect(),
+        freeze_until: freeze_until.map(to_system_time),
+    };
+
+    if let Err(err) = store.save(&state).await {
+        log::warn!("couldn't save `Throttle` state: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    };
+
+    use super::{load_state, save_state, FileStore, InMemoryStore, ThrottleStore};
+    use crate::{
+        adaptors::throttle::{ChatIdHash, LimitClass},
+        types::ChatId,
+    };
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn round_trip() {
+        let mut store = InMemoryStore::new();
+        assert_eq!(
+            load_state(&mut store, MINUTE).await,
+            (VecDeque::new(), None)
+        );
+
+        let now = Instant::now();
+        let history: VecDeque<_> = [
+            // Too old, is dropped on load
+            (
+                LimitClass::Send,
+                ChatIdHash::Id(ChatId(1)),
+                now - 2 * MINUTE,
+            ),
+            (
+                LimitClass::Edit,
+                ChatIdHash::Id(ChatId(2)),
+                now - Duration::from_secs(10),
+            ),
+            (LimitClass::Send, ChatIdHash::ChannelUsernameHash(17), now),
+            (LimitClass::ChatAction, ChatIdHash::NoChat, now),
+        ]
+        .into();
+
+        save_state(&mut store, &history, Some(now + MINUTE)).await;
+
+        let (loaded, freeze_until) = load_state(&mut store.clone(), MINUTE).await;
+        let chats: Vec<_> = loaded
+            .iter()
+            .map(|&(class, chat, _)| (class, chat))
+            .collect();
+        assert_eq!(
+            chats,
+            [
+                (LimitClass::Edit, ChatIdHash::Id(ChatId(2))),
+                // Hashes of usernames are not saved
+                (LimitClass::ChatAction, ChatIdHash::NoChat),
+            ]
+        );
+        assert!(freeze_until.unwrap() > Instant::now());
+
+        // Expired freezes are not loaded
+        save_state(&mut store, &history, Some(now - MINUTE)).await;
+        assert_eq!(load_state(&mut store, MINUTE).await.1, None);
+    }
+
+    #[tokio::test]
+    async fn file_store() {
+        let path = std::env::temp_dir().join(format!(
+            "teloxide-core-throttle-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut store = FileStore::new(&path);
+
+        assert_eq!(store.load().await.unwrap(), None);
+
+        let history = [(LimitClass::Send, ChatIdHash::Id(ChatId(1)), Instant::now())].into();
+        save_state(&mut store, &history, None).await;
+
+        let state = store.load().await.unwrap().unwrap();
+        assert_eq!(FileStore::new(&path).load().await.unwrap(), Some(state));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,15 +1,23 @@
 use std::{
-    collections::{hash_map::Entry, HashMap, VecDeque},
-    time::{Duration, Instant},
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant, SystemTime},
 };
 
+use futures::{stream::FuturesUnordered, Future, StreamExt};
 use tokio::sync::{mpsc, mpsc::error::TryRecvError, oneshot::Sender};
-use vecrem::VecExt;
 
 use crate::{
-    adaptors::throttle::{request_lock::RequestLock, ChatIdHash, Limits, Settings},
+    adaptors::throttle::{
+        queue::ChatQueues,
+        request::Priority,
+        request_lock::RequestLock,
+        store::{load_state, save_state},
+        ChatIdHash, DroppedRequest, LimitClass, Limits, SentRequest, Settings, ShutdownReport,
+        ThrottleStats, ThrottleStore,
+    },
     errors::AsResponseParameters,
-    requests::Requester,
+    requests::{Request, Requester},
+    types::{ChatId, Recipient},
 };
 
 const MINUTE: Duration = Duration::from_secs(60);
@@ -24,21 +32,50 @@ const DELAY: Duration = Duration::from_millis(250);
 /// Minimal time between calls to queue_full function
 const QUEUE_FULL_DELAY: Duration = Duration::from_secs(4);
 
+/// Minimal time between saves of the state to the `ThrottleStore`
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// For how long a username which couldn't be resolved is not resolved again
+const FAILED_RESOLVE_TTL: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub(super) enum InfoMessage {
-    GetLimits { response: Sender<Limits> },
-    SetLimits { new: Limits, response: Sender<()> },
+    GetLimits {
+        response: Sender<Limits>,
+    },
+    SetLimits {
+        new: Limits,
+        response: Sender<()>,
+    },
+    GetStats {
+        response: Sender<ThrottleStats>,
+    },
+    Shutdown {
+        deadline: Instant,
+        response: Sender<ShutdownReport>,
+    },
+}
+
+/// State of a shutdown requested with `Throttle::shutdown`.
+struct Shutdown {
+    deadline: Instant,
+    responses: Vec<Sender<ShutdownReport>>,
 }
 
-type RequestsSent = u32;
+/// A request waiting in the queue of the worker.
+pub(super) struct QueuedRequest {
+    pub(super) chat: ChatIdHash,
+    /// Username of the channel, if the request is sent by `@channelusername`
+    /// and the username is not yet resolved to an id.
+    pub(super) username: Option<String>,
+    pub(super) class: LimitClass,
+    pub(super) priority: Priority,
+    pub(super) lock: RequestLock,
+}
 
-// I wish there was special data structure for history which removed the
-// need in 2 hashmaps
-// (waffle)
-#[derive(Default)]
-struct RequestsSentToChats {
-    per_min: HashMap<ChatIdHash, RequestsSent>,
-    per_sec: HashMap<ChatIdHash, RequestsSent>,
+/// Queues of requests of every `LimitClass`, indexed by the class.
+struct Queues {
+    classes: [ChatQueues<ChatIdHash, QueuedRequest>; LimitClass::ALL.len()],
 }
 
 pub(super) struct FreezeUntil {
@@ -62,7 +99,11 @@ pub(super) struct FreezeUntil {
 // never exceeded.
 //
 // The worker stores a history of requests sent in the last minute (and to which
-// chats they were sent) and a queue of pending updates.
+// chats they were sent) and a queue of pending updates. The queue is split by
+// `LimitClass`es, which are limited independently of each other, and by chats:
+// chats which didn't exceed their limits are kept in "ready" sets and other
+// chats are kept in a timer heap until they can be sent a request again (see
+// `ChatQueues`), so that the worker doesn't scan the whole queue.
 //
 // The worker does the following algorithm loop:
 //
@@ -76,45 +117,117 @@ pub(super) struct FreezeUntil {
 //
 // 4. Clear the history from records whose time < (current time - minute).
 //
-// 5. Count all requests which were sent last second, `allowed =
-// limit.messages_per_sec_overall - count`.
+// Steps 5-8 are done for every class separately:
+//
+// 5. Count all requests of the class which were sent last second, `allowed =
+// min(per_sec_overall - count, allowed_overall)`, where `allowed_overall` is
+// `requests_per_sec_overall` minus the number of requests of all classes sent
+// last second (and unlocked by the previous classes in this iteration).
+//
+// 6. If `allowed == 0` skip the class.
+//
+// 7. Move chats whose time has come from the timer heap to the ready sets.
+//
+// 8. While `allowed >= 0` take the oldest request to a ready chat, if the chat
+// has exceeded the limits, move it to the timer heap, otherwise decrease
+// `allowed` (and `allowed_overall`), notify the request that it can be now
+// executed and add record to the history. Requests are taken in the order of
+// their priority, low priority requests can only use `low_priority_share`
+// percent of the overall limit.
+//
+// Requests which are not bound to a chat (`ChatIdHash::NoChat`) are only
+// limited by the overall limit of their class.
 //
-// 6. If `allowed == 0` wait a bit and `continue` to the next iteration.
+// Before step 3 usernames of channels of the new requests are resolved to ids
+// (with `GetChat`), so that the same chat is always counted by its id. Requests
+// sent by a username which is not cached are held until it's resolved, the
+// resolution is polled alongside with the incoming channel, so it doesn't block
+// the worker. If the username can't be resolved, the requests are counted by
+// its hash.
 //
-// 7. Count how many requests were sent to which chats (i.e.: create
-// `Map<ChatId, Count>`). (Note: the same map, but for last minute also exists,
-// but it's updated, instead of recreation.)
+// If a request got a `RetryAfter` error, the worker is frozen (doesn't unlock
+// any requests) until the time specified by telegram. `InfoMessage`s are
+// answered even while the worker is frozen or waits for requests.
 //
-// 8. While `allowed >= 0` search for requests which chat haven't exceed the
-// limits (i.e.: map[chat] < limit), if one is found, decrease `allowed`, notify
-// the request that it can be now executed, increase counts, add record to the
-// history.
+// If a `ThrottleStore` is set, the history (and active freezes) are loaded from
+// it before the loop and are saved to it at most once per `SAVE_DELAY` when
+// they change.
+//
+// If a `ThrottleBackend` is set, requests are acquired from the backend in step
+// 8 before they are unlocked. If the backend refuses a request (because other
+// workers have used the limits), the request is returned to the queue and the
+// class is skipped until the next iteration.
+//
+// After `Throttle::shutdown` is called, the incoming channel is closed and the
+// loop ends when the queue is empty or the shutdown deadline has come. Requests
+// which are left in the queue are dropped (which makes them fail with
+// `ShutdownError`) and reported. After that the worker only answers
+// `InfoMessage`s until all `Throttle`s are dropped.
 pub(super) async fn worker<B>(
     Settings {
         mut limits,
         mut on_queue_full,
         retry,
         check_slow_mode,
+        low_priority_share,
+        channel_username_ttl,
+        queue_capacity,
+        mut store,
+        backend,
     }: Settings,
-    mut rx: mpsc::Receiver<(ChatIdHash, RequestLock)>,
+    mut rx: mpsc::Receiver<QueuedRequest>,
     mut info_rx: mpsc::Receiver<InfoMessage>,
     bot: B,
 ) where
     B: Requester,
     B::Err: AsResponseParameters,
 {
-    // FIXME(waffle): Make an research about data structures for this queue.
-    //                Currently this is O(n) removing (n = number of elements
-    //                stayed), amortized O(1) push (vec+vecrem).
-    let mut queue: Vec<(ChatIdHash, RequestLock)> =
-        Vec::with_capacity(limits.messages_per_sec_overall as usize);
+    let mut queues = Queues::new();
+
+    // Don't grow queue bigger than the capacity to limit DOS possibility
+    let queue_capacity = queue_capacity.unwrap_or(limits.messages_per_sec_overall as usize);
+
+    // Requests read from the incoming channel, but not yet added to the queue
+    let mut received: Vec<QueuedRequest> = Vec::new();
+
+    let mut history: VecDeque<(LimitClass, ChatIdHash, Instant)> = VecDeque::new();
+
+    // The time until which the bot is frozen because of a `RetryAfter` error
+    let mut frozen_until: Option<Instant> = None;
+
+    if let Some(store) = store.as_deref_mut() {
+        let (loaded, freeze_until) = load_state(store, MINUTE).await;
+
+        for &(class, chat, time) in &loaded {
+            queues.get_mut(class).record_sent(chat, time);
+        }
+        history = loaded;
+
+        if freeze_until.is_some() {
+            log::warn!("the bot was frozen before the restart, waiting until the freeze ends");
+            frozen_until = freeze_until;
+        }
+    }
+
+    let mut last_save = Instant::now();
+    let mut unsaved = false;
 
-    let mut history: VecDeque<(ChatIdHash, Instant)> = VecDeque::new();
-    let mut requests_sent = RequestsSentToChats::default();
+    // Times at which low priority requests of every class were sent in the last
+    // second
+    let mut low_priority_sent: [VecDeque<Instant>; LimitClass::ALL.len()] = <_>::default();
+    let low_priority_share = u32::from(low_priority_share.min(100));
 
     let mut slow_mode: Option<HashMap<ChatIdHash, (Duration, Instant)>> =
         check_slow_mode.then(HashMap::new);
 
+    // Channel ids resolved from usernames (`None` if resolving failed) and the
+    // times until which they are cached
+    let mut channel_ids: HashMap<String, (Option<ChatId>, Instant)> = HashMap::new();
+
+    // Requests sent by usernames which are being resolved
+    let mut unresolved: HashMap<String, Vec<QueuedRequest>> = HashMap::new();
+    let mut resolving = FuturesUnordered::new();
+
     let mut rx_is_closed = false;
 
     let mut last_queue_full = Instant::now()
@@ -123,36 +236,163 @@ pub(super) async fn worker<B>(
 
     let (freeze_tx, mut freeze_rx) = mpsc::channel::<FreezeUntil>(1);
 
-    while !rx_is_closed || !queue.is_empty() {
-        // FIXME(waffle):
-        // 1. If the `queue` is empty, `read_from_rx` call down below will 'block'
-        //    execution until a request is sent. While the execution is 'blocked' no
-        //    `InfoMessage`s could be answered.
-        //
-        // 2. If limits are decreased, ideally we want to shrink queue.
-        //
-        // *blocked in asynchronous way
-        answer_info(&mut info_rx, &mut limits);
+    let mut shutdown: Option<Shutdown> = None;
+
+    while !rx_is_closed || !queues.is_empty() || !unresolved.is_empty() {
+        // FIXME(waffle): If limits are decreased, ideally we want to shrink queue.
+        while let Ok(req) = info_rx.try_recv() {
+            answer_info(
+                req,
+                &mut limits,
+                &mut queues,
+                &unresolved,
+                slow_mode.as_ref(),
+                frozen_until,
+                &mut shutdown,
+                &mut rx,
+            );
+        }
+
+        if shutdown
+            .as_ref()
+            .map_or(false, |s| s.deadline <= Instant::now())
+        {
+            break;
+        }
+
+        let block = queues.is_empty();
+        let held = unresolved.values().map(Vec::len).sum::<usize>();
+        let limit = queue_capacity.saturating_sub(queues.len() + held);
 
         loop {
             tokio::select! {
                 freeze_until = freeze_rx.recv() => {
-                    freeze(
+                    let until = freeze(
                         &mut freeze_rx,
                         slow_mode.as_mut(),
                         &bot,
                         freeze_until
                     )
                     .await;
+
+                    frozen_until = frozen_until.max(until);
+
+                    // Slow mode delays may have changed
+                    queues.reschedule();
+                },
+                // Answer `InfoMessage`s even if the queue is empty and we are waiting for requests
+                Some(req) = info_rx.recv() => {
+                    answer_info(
+                        req,
+                        &mut limits,
+                        &mut queues,
+                        &unresolved,
+                        slow_mode.as_ref(),
+                        frozen_until,
+                        &mut shutdown,
+                        &mut rx,
+                    );
                 },
-                () = read_from_rx(&mut rx, &mut queue, &mut rx_is_closed) => break,
+                Some(resolved) = resolving.next(), if !resolving.is_empty() => {
+                    let (username, id): (String, Option<ChatId>) = resolved;
+                    let ttl = if id.is_some() { channel_username_ttl } else { FAILED_RESOLVE_TTL };
+                    channel_ids.retain(|_, &mut (_, until)| until > Instant::now());
+                    channel_ids.insert(username.clone(), (id, Instant::now() + ttl));
+
+                    for mut req in unresolved.remove(&username).unwrap_or_default() {
+                        if let Some(id) = id {
+                            req.chat = ChatIdHash::Id(id);
+                        }
+                        queues.push(req);
+                    }
+
+                    break;
+                },
+                () = read_from_rx(&mut rx, &mut received, block, limit, &mut rx_is_closed) => break,
+            }
+        }
+
+        for mut req in received.drain(..) {
+            let username = match req.username.take() {
+                Some(username) => username,
+                None => {
+                    queues.push(req);
+                    continue;
+                }
+            };
+
+            match channel_ids.get(&username) {
+                Some(&(id, until)) if until > Instant::now() => {
+                    if let Some(id) = id {
+                        req.chat = ChatIdHash::Id(id);
+                    }
+                    queues.push(req);
+                }
+                _ => {
+                    let held = unresolved.entry(username.clone()).or_default();
+                    if held.is_empty() {
+                        resolving.push(resolve_username(&bot, username));
+                    }
+                    held.push(req);
+                }
+            }
+        }
+
+        if let Some(until) = frozen_until {
+            // Save the freeze, so that it's not lost if the bot is restarted
+            if let Some(store) = store.as_deref_mut() {
+                save_state(store, &history, Some(until)).await;
+            }
+
+            log::warn!(
+                "freezing the bot for approximately {:?} due to `RetryAfter` error from telegram",
+                until.saturating_duration_since(Instant::now())
+            );
+
+            let sleep = tokio::time::sleep_until(until.into());
+            tokio::pin!(sleep);
+
+            loop {
+                // Don't wait for the end of the freeze after the shutdown deadline
+                if let Some(Shutdown { deadline, .. }) = shutdown {
+                    if deadline < until {
+                        sleep.as_mut().reset(deadline.into());
+                    }
+                }
+
+                tokio::select! {
+                    () = &mut sleep => break,
+                    Some(req) = info_rx.recv() => {
+                        answer_info(
+                            req,
+                            &mut limits,
+                            &mut queues,
+                            &unresolved,
+                            slow_mode.as_ref(),
+                            frozen_until,
+                            &mut shutdown,
+                            &mut rx,
+                        );
+                    },
+                }
+            }
+
+            if Instant::now() < until {
+                // The shutdown deadline has come before the end of the freeze
+                continue;
             }
+
+            frozen_until = None;
+            if let Some(store) = store.as_deref_mut() {
+                save_state(store, &history, None).await;
+            }
+
+            log::warn!("unfreezing the bot");
         }
-        //debug_assert_eq!(queue.capacity(), limits.messages_per_sec_overall as usize);
 
-        if queue.len() == queue.capacity() && last_queue_full.elapsed() > QUEUE_FULL_DELAY {
+        if queues.len() >= queue_capacity && last_queue_full.elapsed() > QUEUE_FULL_DELAY {
             last_queue_full = Instant::now();
-            tokio::spawn(on_queue_full(queue.len()));
+            tokio::spawn(on_queue_full(queues.len()));
         }
 
         // _Maybe_ we need to use `spawn_blocking` here, because there is
@@ -192,121 +432,440 @@ pub(super) async fn worker<B>(
         let min_back = now - MINUTE;
         let sec_back = now - SECOND;
 
-        // make history and requests_sent up-to-date
-        while let Some((_, time)) = history.front() {
+        // make history and the queues up-to-date
+        while let Some(&(class, chat, time)) = history.front() {
             // history is sorted, we found first up-to-date thing
-            if time >= &min_back {
+            if time >= min_back {
                 break;
             }
 
-            if let Some((chat, _)) = history.pop_front() {
-                let entry = requests_sent.per_min.entry(chat).and_modify(|count| {
-                    *count -= 1;
-                });
+            history.pop_front();
+            queues.get_mut(class).forget_sent(chat);
+        }
+
+        // Requests of all classes share the overall limit
+        let used_overall = history
+            .iter()
+            .rev()
+            .take_while(|(_, _, time)| time > &sec_back)
+            .count() as u32;
+        let mut allowed_overall = limits.requests_per_sec_overall.saturating_sub(used_overall);
+
+        for class in LimitClass::ALL {
+            let (class_limits, _) = limits.class(class);
+            let queue = queues.get_mut(class);
+            let low_priority_sent = &mut low_priority_sent[class as usize];
+
+            // as truncates which is ok since in case of truncation it would always be >=
+            // limits.overall_s
+            let used = history
+                .iter()
+                .rev()
+                .take_while(|(_, _, time)| time > &sec_back)
+                .filter(|&&(c, _, _)| c == class)
+                .count() as u32;
+            let mut allowed = class_limits
+                .per_sec_overall
+                .saturating_sub(used)
+                .min(allowed_overall);
+
+            if allowed == 0 {
+                continue;
+            }
+
+            // Low priority requests can only use a share of the overall limit
+            while low_priority_sent
+                .front()
+                .map_or(false, |time| time <= &sec_back)
+            {
+                low_priority_sent.pop_front();
+            }
+            let low_priority_limit =
+                low_priority_limit(class_limits.per_sec_overall, low_priority_share);
+            let mut low_priority_allowed =
+                low_priority_limit.saturating_sub(low_priority_sent.len() as u32);
+
+            queue.wake(now, |chat, sent| {
+                ready_at(class, chat, sent, &limits, slow_mode.as_ref())
+            });
+
+            // Requests with higher priority are unlocked first
+            'priorities: for priority in [Priority::High, Priority::Normal, Priority::Low] {
+                if priority == Priority::Low && low_priority_allowed == 0 {
+                    break;
+                }
+
+                while let Some((chat, req)) = queue.pop(priority, now, |chat, sent| {
+                    ready_at(class, chat, sent, &limits, slow_mode.as_ref())
+                }) {
+                    // Hashes of usernames can't be shared with other processes
+                    if let Some(backend) = backend.as_ref().filter(|_| chat.is_stable()) {
+                        let time = SystemTime::now();
+                        match backend
+                            .try_acquire(SentRequest { class, chat, time }, &limits)
+                            .await
+                        {
+                            Ok(true) => {}
+                            // Other workers have used the limits
+                            Ok(false) => {
+                                queue.requeue(chat, priority, req, now);
+                                break 'priorities;
+                            }
+                            Err(err) => {
+                                log::warn!(
+                                    "couldn't acquire a request from `Throttle` backend: {}",
+                                    err
+                                )
+                            }
+                        }
+                    }
+
+                    // Only count request as sent if the request wasn't dropped before unlocked
+                    if req.lock.unlock(chat, retry, freeze_tx.clone()).is_ok() {
+                        let sent_at = Instant::now();
+
+                        queue.record_sent(chat, sent_at);
+                        history.push_back((class, chat, sent_at));
+                        unsaved = true;
+
+                        if class == LimitClass::Send {
+                            if let Some((_, last)) =
+                                slow_mode.as_mut().and_then(|sm| sm.get_mut(&chat))
+                            {
+                                *last = sent_at;
+                            }
+                        }
+
+                        if priority == Priority::Low {
+                            low_priority_sent.push_back(sent_at);
+                            low_priority_allowed -= 1;
+                        }
 
-                if let Entry::Occupied(entry) = entry {
-                    if *entry.get() == 0 {
-                        entry.remove_entry();
+                        // We have "sent" one request, so now we can send one less.
+                        allowed -= 1;
+                        allowed_overall -= 1;
+                        if allowed == 0 || (priority == Priority::Low && low_priority_allowed == 0)
+                        {
+                            break 'priorities;
+                        }
                     }
                 }
             }
         }
 
-        // as truncates which is ok since in case of truncation it would always be >=
-        // limits.overall_s
-        let used = history
-            .iter()
-            .take_while(|(_, time)| time > &sec_back)
-            .count() as u32;
-        let mut allowed = limits.messages_per_sec_overall.saturating_sub(used);
+        save_if_needed(&mut store, &history, &mut unsaved, &mut last_save).await;
+        tokio::time::sleep(DELAY).await;
+    }
 
-        if allowed == 0 {
-            requests_sent.per_sec.clear();
-            tokio::time::sleep(DELAY).await;
-            continue;
+    // Requests which weren't sent before the shutdown deadline
+    let mut dropped = Vec::new();
+    if shutdown.is_some() {
+        rx.close();
+        while let Ok(req) = rx.try_recv() {
+            dropped.push(req);
         }
+        dropped.extend(queues.drain());
+        dropped.extend(unresolved.drain().flat_map(|(_, held)| held));
+    }
+
+    if let Some(store) = store.as_deref_mut() {
+        if unsaved {
+            save_state(store, &history, frozen_until).await;
+        }
+    }
 
-        for (chat, _) in history.iter().take_while(|(_, time)| time > &sec_back) {
-            *requests_sent.per_sec.entry(*chat).or_insert(0) += 1;
+    if let Some(Shutdown { responses, .. }) = shutdown.take() {
+        if !dropped.is_empty() {
+            log::warn!(
+                "{} requests were not sent before the shutdown deadline",
+                dropped.len()
+            );
         }
 
-        let mut queue_removing = queue.removing();
+        let report = ShutdownReport {
+            dropped: dropped
+                .iter()
+                .map(|req| DroppedRequest {
+                    chat: req.chat,
+                    class: req.class,
+                    priority: req.priority,
+                })
+                .collect(),
+        };
 
-        while let Some(entry) = queue_removing.next() {
-            let chat = &entry.value().0;
+        // Dropping the locks fails the requests
+        drop(dropped);
 
-            let slow_mode = slow_mode.as_mut().and_then(|sm| sm.get_mut(chat));
+        for response in responses {
+            response.send(report.clone()).ok();
+        }
 
-            if let Some(&mut (delay, last)) = slow_mode {
-                if last + delay > Instant::now() {
-                    continue;
+        while let Some(req) = info_rx.recv().await {
+            answer_info(
+                req,
+                &mut limits,
+                &mut queues,
+                &unresolved,
+                slow_mode.as_ref(),
+                frozen_until,
+                &mut shutdown,
+                &mut rx,
+            );
+
+            // The worker is already shut down, there is nothing to wait for
+            if let Some(Shutdown { responses, .. }) = shutdown.take() {
+                for response in responses {
+                    response.send(ShutdownReport::default()).ok();
+                }
+            }
+        }
+    }
+}
+
+async fn save_if_needed(
+    store: &mut Option<Box<dyn ThrottleStore>>,
+    history: &VecDeque<(LimitClass, ChatIdHash, Instant)>,
+    unsaved: &mut bool,
+    last_save: &mut Instant,
+) {
+    if let Some(store) = store.as_deref_mut() {
+        if *unsaved && last_save.elapsed() >= SAVE_DELAY {
+            save_state(store, history, None).await;
+            *unsaved = false;
+            *last_save = Instant::now();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn answer_info(
+    req: InfoMessage,
+    limits: &mut Limits,
+    queues: &mut Queues,
+    unresolved: &HashMap<String, Vec<QueuedRequest>>,
+    slow_mode: Option<&HashMap<ChatIdHash, (Duration, Instant)>>,
+    frozen_until: Option<Instant>,
+    shutdown: &mut Option<Shutdown>,
+    rx: &mut mpsc::Receiver<QueuedRequest>,
+) {
+    // Errors are ignored with .ok(). Error means that the response channel
+    // is closed and the response isn't needed.
+    match req {
+        InfoMessage::GetLimits { response } => response.send(*limits).ok(),
+        InfoMessage::SetLimits { new, response } => {
+            *limits = new;
+
+            // Chats may be able to send requests earlier with the new limits
+            queues.reschedule();
+            response.send(()).ok()
+        }
+        InfoMessage::GetStats { response } => {
+            let now = Instant::now();
+
+            let mut pending = HashMap::new();
+            let mut pending_messages = HashMap::new();
+            let mut sent_per_min = HashMap::new();
+            for class in LimitClass::ALL {
+                for (chat, queued, sent) in queues.get(class).chats() {
+                    if queued != 0 {
+                        *pending.entry(chat).or_default() += queued;
+
+                        if class == LimitClass::Send {
+                            pending_messages.insert(chat, queued);
+                        }
+                    }
+
+                    // Only messages are limited per minute
+                    if sent != 0 && class == LimitClass::Send {
+                        sent_per_min.insert(chat, sent as u32);
+                    }
                 }
             }
 
-            let requests_sent_per_sec_count = requests_sent.per_sec.get(chat).copied().unwrap_or(0);
-            let requests_sent_per_min_count = requests_sent.per_min.get(chat).copied().unwrap_or(0);
+            // Requests held until their usernames are resolved are counted by
+            // the hashes of the usernames
+            let mut held = 0;
+            for req in unresolved.values().flatten() {
+                *pending.entry(req.chat).or_default() += 1;
+                if req.class == LimitClass::Send {
+                    *pending_messages.entry(req.chat).or_default() += 1;
+                }
+                held += 1;
+            }
 
-            let messages_per_min_limit = if chat.is_channel() {
-                limits.messages_per_min_channel
-            } else {
-                limits.messages_per_min_chat
+            let slow_mode_until = slow_mode
+                .into_iter()
+                .flatten()
+                .map(|(&chat, &(delay, last))| (chat, last + delay))
+                .filter(|&(_, until)| until > now)
+                .collect();
+
+            let stats = ThrottleStats {
+                taken_at: now,
+                limits: *limits,
+                queue_len: queues.len() + held,
+                pending,
+                pending_messages,
+                sent_per_min,
+                frozen_until: frozen_until.filter(|&until| until > now),
+                slow_mode_until,
             };
 
-            let limits_not_exceeded = requests_sent_per_sec_count < limits.messages_per_sec_chat
-                && requests_sent_per_min_count < messages_per_min_limit;
+            response.send(stats).ok()
+        }
+        InfoMessage::Shutdown { deadline, response } => {
+            // Don't accept new requests, requests which are already in the channel are
+            // still received
+            rx.close();
+
+            match shutdown {
+                Some(shutdown) => {
+                    shutdown.deadline = shutdown.deadline.min(deadline);
+                    shutdown.responses.push(response);
+                }
+                None => {
+                    *shutdown = Some(Shutdown {
+                        deadline,
+                        responses: vec![response],
+                    })
+                }
+            }
+
+            // The response is sent when the shutdown is complete
+            None
+        }
+    };
+}
+
+/// Returns how many low priority requests can be sent per second, `share` is
+/// in percents.
+fn low_priority_limit(per_sec_overall: u32, share: u32) -> u32 {
+    match per_sec_overall * share / 100 {
+        // A non-zero share allows at least one request per second
+        0 if share != 0 => 1,
+        limit => limit,
+    }
+}
 
-            if limits_not_exceeded {
-                // Unlock the associated request.
+/// Returns the time at which a request of the `class` can be sent to the
+/// `chat` given the times at which the last requests of the class were `sent`
+/// to it, or `None` if the chat didn't exceed the limits.
+fn ready_at(
+    class: LimitClass,
+    chat: &ChatIdHash,
+    sent: &VecDeque<Instant>,
+    limits: &Limits,
+    slow_mode: Option<&HashMap<ChatIdHash, (Duration, Instant)>>,
+) -> Option<Instant> {
+    // Only the overall limit applies to requests which are not bound to a chat
+    if *chat == ChatIdHash::NoChat {
+        return None;
+    }
 
-                let chat = *chat;
-                let (_, lock) = entry.remove();
+    let (class_limits, per_min_channel) = limits.class(class);
+    let per_min_limit = if chat.is_channel() {
+        per_min_channel
+    } else {
+        class_limits.per_min_chat
+    };
+
+    // If `limit` requests were sent during the last `period`, the next request can
+    // be sent only when the oldest of them is out of the period
+    let nth_last = |limit: u32, period: Duration| {
+        let n = sent.len().checked_sub(limit.max(1) as usize)?;
+        Some(sent[n] + period)
+    };
+
+    // Slow mode only limits sending messages
+    let slow_mode = slow_mode
+        .filter(|_| class == LimitClass::Send)
+        .and_then(|sm| sm.get(chat))
+        .map(|&(delay, last)| last + delay);
+
+    [
+        nth_last(class_limits.per_sec_chat, SECOND),
+        nth_last(per_min_limit, MINUTE),
+        slow_mode,
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+}
 
-                // Only count request as sent if the request wasn't dropped before unlocked
-                if lock.unlock(retry, freeze_tx.clone()).is_ok() {
-                    *requests_sent.per_sec.entry(chat).or_insert(0) += 1;
-                    *requests_sent.per_min.entry(chat).or_insert(0) += 1;
-                    history.push_back((chat, Instant::now()));
+impl Queues {
+    fn new() -> Self {
+        Self {
+            classes: LimitClass::ALL.map(|_| ChatQueues::new()),
+        }
+    }
 
-                    if let Some((_, last)) = slow_mode {
-                        *last = Instant::now();
-                    }
+    fn get(&self, class: LimitClass) -> &ChatQueues<ChatIdHash, QueuedRequest> {
+        &self.classes[class as usize]
+    }
 
-                    // We have "sent" one request, so now we can send one less.
-                    allowed -= 1;
-                    if allowed == 0 {
-                        break;
-                    }
-                }
-            }
+    fn get_mut(&mut self, class: LimitClass) -> &mut ChatQueues<ChatIdHash, QueuedRequest> {
+        &mut self.classes[class as usize]
+    }
+
+    /// Returns the number of queued requests of all classes.
+    fn len(&self) -> usize {
+        self.classes.iter().map(ChatQueues::len).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.classes.iter().all(ChatQueues::is_empty)
+    }
+
+    fn push(&mut self, req: QueuedRequest) {
+        self.get_mut(req.class).push(req.chat, req.priority, req);
+    }
+
+    /// See `ChatQueues::reschedule`.
+    fn reschedule(&mut self) {
+        for queue in &mut self.classes {
+            queue.reschedule();
         }
+    }
 
-        // It's easier to just recompute last second stats, instead of keeping
-        // track of it alongside with minute stats, so we just throw this away.
-        requests_sent.per_sec.clear();
-        tokio::time::sleep(DELAY).await;
+    /// Removes all queued requests and returns them in no particular order.
+    fn drain(&mut self) -> Vec<QueuedRequest> {
+        self.classes
+            .iter_mut()
+            .flat_map(|queue| queue.drain())
+            .map(|(_, req)| req)
+            .collect()
     }
 }
 
-fn answer_info(rx: &mut mpsc::Receiver<InfoMessage>, limits: &mut Limits) {
-    while let Ok(req) = rx.try_recv() {
-        // Errors are ignored with .ok(). Error means that the response channel
-        // is closed and the response isn't needed.
-        match req {
-            InfoMessage::GetLimits { response } => response.send(*limits).ok(),
-            InfoMessage::SetLimits { new, response } => {
-                *limits = new;
-                response.send(()).ok()
-            }
-        };
+/// Resolves the username of a channel to its id with `GetChat`, returns `None`
+/// if the username can't be resolved.
+fn resolve_username<B>(
+    bot: &B,
+    username: String,
+) -> impl Future<Output = (String, Option<ChatId>)> + Send
+where
+    B: Requester,
+{
+    let request = bot
+        .get_chat(Recipient::ChannelUsername(username.clone()))
+        .send();
+
+    async move {
+        let id = request.await.ok().map(|chat| chat.id);
+        (username, id)
     }
 }
 
+/// Handles `FreezeUntil` notifications, returns the time until which the bot
+/// should be frozen (if it should).
 async fn freeze(
     rx: &mut mpsc::Receiver<FreezeUntil>,
     mut slow_mode: Option<&mut HashMap<ChatIdHash, (Duration, Instant)>>,
     bot: &impl Requester,
     mut imm: Option<FreezeUntil>,
-) {
+) -> Option<Instant> {
+    let mut frozen_until = None;
+
     while let Some(freeze_until) = imm.take().or_else(|| rx.try_recv().ok()) {
         let FreezeUntil { until, after, chat } = freeze_until;
 
@@ -342,35 +901,38 @@ async fn freeze(
             .and_then(|m| m.get(&chat).map(|(delay, _)| delay <= &after))
             .unwrap_or(false);
 
-        // Do not sleep if slow mode is enabled since the freeze is most likely caused
+        // Do not freeze if slow mode is enabled since the freeze is most likely caused
         // by the said slow mode and not by the global limits.
         if !slow_mode_enabled_and_likely_the_cause {
-            log::warn!(
-                "freezing the bot for approximately {:?} due to `RetryAfter` error from telegram",
-                after
-            );
-
-            tokio::time::sleep_until(until.into()).await;
-
-            log::warn!("unfreezing the bot");
+            frozen_until = frozen_until.max(Some(until));
         }
     }
+
+    frozen_until
 }
 
-async fn read_from_rx<T>(rx: &mut mpsc::Receiver<T>, queue: &mut Vec<T>, rx_is_closed: &mut bool) {
-    if queue.is_empty() {
+/// Reads requests from `rx` into `received`, until there are `limit` of them.
+///
+/// If `block` is `true`, waits for at least one request.
+async fn read_from_rx<T>(
+    rx: &mut mpsc::Receiver<T>,
+    received: &mut Vec<T>,
+    block: bool,
+    limit: usize,
+    rx_is_closed: &mut bool,
+) {
+    if block {
         log::debug!("blocking on queue");
 
         match rx.recv().await {
-            Some(req) => queue.push(req),
+            Some(req) => received.push(req),
             None => *rx_is_closed = true,
         }
     }
 
-    // Don't grow queue bigger than the capacity to limit DOS possibility
-    while queue.len() < queue.capacity() {
+    while received.len() < limit {
         match rx.try_recv() {
-            Ok(req) => queue.push(req),
+            Ok(req) => received.push(req),
             Err(TryRecvError::Disconnected) => {
                 *rx_is_closed = true;
                 break;
@@ -383,6 +945,51 @@ async fn read_from_rx<T>(rx: &mut mpsc::Receiver<T>, queue: &mut Vec<T>, rx_is_c
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::{HashMap, VecDeque},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        adaptors::throttle::{ChatIdHash, LimitClass, Limits},
+        types::ChatId,
+    };
+
+    #[test]
+    fn ready_at_classes() {
+        let now = Instant::now();
+        let limits = Limits::default();
+        let chat = ChatIdHash::Id(ChatId(1));
+        let sent: VecDeque<_> = [now].into();
+
+        // Slow mode only limits sending messages
+        let slow_mode: HashMap<_, _> = [(chat, (Duration::from_secs(10), now))].into();
+        let ready_at =
+            |class, chat| super::ready_at(class, &chat, &sent, &limits, Some(&slow_mode));
+
+        assert_eq!(
+            ready_at(LimitClass::Send, chat),
+            Some(now + Duration::from_secs(10))
+        );
+        assert_eq!(
+            ready_at(LimitClass::Edit, chat),
+            Some(now + Duration::from_secs(1))
+        );
+
+        // Requests which are not bound to a chat are only limited overall
+        assert_eq!(ready_at(LimitClass::ChatAction, ChatIdHash::NoChat), None);
+    }
+
+    #[test]
+    fn low_priority_limit() {
+        use super::low_priority_limit;
+
+        assert_eq!(low_priority_limit(30, 50), 15);
+        assert_eq!(low_priority_limit(30, 100), 30);
+        assert_eq!(low_priority_limit(30, 1), 1);
+        assert_eq!(low_priority_limit(30, 0), 0);
+    }
+
     #[tokio::test]
     async fn issue_535() {
         let (tx, mut rx) = tokio::sync::mpsc::channel(1);
@@ -391,6 +998,6 @@ mod tests {
         drop(tx);
 
         // Previously this caused an infinite loop
-        super::read_from_rx::<()>(&mut rx, &mut Vec::new(), &mut false).await;
+        super::read_from_rx::<()>(&mut rx, &mut Vec::new(), true, 1, &mut false).await;
     }
 }
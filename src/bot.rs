@@ -1,5 +1,6 @@
 use std::{future::Future, sync::Arc};
 
+use futures::future::Either;
 use reqwest::Client;
 use serde::{de::DeserializeOwned, Serialize};
 
@@ -57,6 +58,7 @@ pub struct Bot {
     token: Arc<str>,
     api_url: Arc<reqwest::Url>,
     client: Client,
+    validate_files: bool,
 }
 
 /// Constructors
@@ -102,6 +104,7 @@ impl Bot {
             token,
             api_url,
             client,
+            validate_files: false,
         }
     }
 
@@ -182,6 +185,33 @@ impl Bot {
         self.api_url = Arc::new(url);
         self
     }
+
+    /// Enables or disables validation of uploaded files (disabled by default).
+    ///
+    /// When enabled, files uploaded via `multipart/form-data` are checked
+    /// against the requirements documented by Telegram before sending a
+    /// request, e.g. photos must be at most 10 MB, voice messages must be
+    /// OGG files encoded with OPUS and thumbnails must be JPEG images less
+    /// than 200 kB in size. If a file doesn't satisfy the requirements, the
+    /// request fails with [`RequestError::InvalidFile`] before anything is
+    /// uploaded.
+    ///
+    /// The type of the files is detected by their first bytes and is set as
+    /// the content type of the multipart part.
+    ///
+    /// Note that only files created with [`InputFile::file`] and
+    /// [`InputFile::memory`] can be fully checked, for
+    /// [`InputFile::stream_with_length`] only the size is checked and other
+    /// files are not checked at all.
+    ///
+    /// [`RequestError::InvalidFile`]: crate::RequestError::InvalidFile
+    /// [`InputFile::file`]: crate::types::InputFile::file
+    /// [`InputFile::memory`]: crate::types::InputFile::memory
+    /// [`InputFile::stream_with_length`]: crate::types::InputFile::stream_with_length
+    pub fn validate_files(mut self, enabled: bool) -> Self {
+        self.validate_files = enabled;
+        self
+    }
 }
 
 /// Getters
@@ -245,16 +275,22 @@ impl Bot {
         P: MultipartPayload + Serialize,
         P::Output: DeserializeOwned,
     {
+        // Files that are already on Telegram servers (or are sent by URL) don't need
+        // to be uploaded, so there is no need to build `multipart/form-data`
+        if !needs_attach(&*payload) {
+            return Either::Left(self.execute_json(&*payload));
+        }
+
         let client = self.client.clone();
         let token = Arc::clone(&self.token);
         let api_url = Arc::clone(&self.api_url);
 
         let timeout_hint = payload.timeout_hint();
-        let params = serde_multipart::to_form(payload);
+        let params = serde_multipart::to_form(payload, self.validate_files);
 
         // async move to capture client&token&api_url&params
-        async move {
-            let params = params?.await;
+        Either::Right(async move {
+            let params = params.await?;
             net::request_multipart(
                 &client,
                 token.as_ref(),
@@ -264,7 +300,7 @@ impl Bot {
                 timeout_hint,
             )
             .await
-        }
+        })
     }
 
     pub(crate) fn execute_multipart_ref<P>(
@@ -275,16 +311,22 @@ impl Bot {
         P: MultipartPayload + Serialize,
         P::Output: DeserializeOwned,
     {
+        // Files that are already on Telegram servers (or are sent by URL) don't need
+        // to be uploaded, so there is no need to build `multipart/form-data`
+        if !needs_attach(payload) {
+            return Either::Left(self.execute_json(payload));
+        }
+
         let client = self.client.clone();
         let token = Arc::clone(&self.token);
         let api_url = self.api_url.clone();
 
         let timeout_hint = payload.timeout_hint();
-        let params = serde_multipart::to_form_ref(payload);
+        let params = serde_multipart::to_form_ref(payload, self.validate_files);
 
         // async move to capture client&token&api_url&params
-        async move {
-            let params = params?.await;
+        Either::Right(async move {
+            let params = params.await?;
             net::request_multipart(
                 &client,
                 token.as_ref(),
@@ -294,10 +336,117 @@ impl Bot {
                 timeout_hint,
             )
             .await
-        }
+        })
     }
 }
 
+/// Returns `true` if any file in the `payload` needs to be uploaded.
+fn needs_attach<P: ?Sized + MultipartPayload>(payload: &P) -> bool {
+    let mut needs_attach = false;
+    payload.for_each_file(&mut |_, file| needs_attach |= file.needs_attach());
+
+    needs_attach
+}
+
 fn get_env(env: &'static str) -> String {
     std::env::var(env).unwrap_or_else(|_| panic!("Cannot get the {} env variable", env))
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{
+        mock::MockServer,
+        payloads::SendMediaGroupSetters,
+        requests::{Request, Requester},
+        types::{ChatId, InputFile, InputMedia, InputMediaDocument, InputMediaPhoto, MessageId},
+        RequestError,
+    };
+
+    fn media(document: InputFile) -> [InputMedia; 2] {
+        let url = "https://example.com/photo.jpg".parse().unwrap();
+
+        [
+            InputMedia::Photo(InputMediaPhoto::new(InputFile::url(url))),
+            InputMedia::Document(InputMediaDocument::new(document)),
+        ]
+    }
+
+    #[tokio::test]
+    async fn multipart_without_uploads_is_sent_as_json() {
+        let mut server = MockServer::start(json!([])).await;
+        let bot = server.bot();
+
+        let request = bot
+            .send_media_group(ChatId(1), media(InputFile::file_id("id")))
+            .reply_to_message_id(MessageId(2));
+        request.send_ref().await.unwrap();
+        request.send().await.unwrap();
+
+        for _ in 0..2 {
+            let request = server.request().await;
+            assert!(
+                request.path.ends_with("/SendMediaGroup"),
+                "{}",
+                request.path
+            );
+            assert_eq!(request.header("content-type"), Some("application/json"));
+
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(
+                body,
+                json!({
+                    "chat_id": 1,
+                    "media": [
+                        { "type": "photo", "media": "https://example.com/photo.jpg" },
+                        { "type": "document", "media": "id" },
+                    ],
+                    "reply_to_message_id": 2,
+                })
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn multipart_with_uploads_is_sent_as_form() {
+        let mut server = MockServer::start(json!([])).await;
+        let bot = server.bot();
+
+        let document = InputFile::memory(&b"Hello world!"[..]);
+        let id = document.id().to_owned();
+        bot.send_media_group(ChatId(1), media(document))
+            .await
+            .unwrap();
+
+        let request = server.request().await;
+        assert!(request
+            .header("content-type")
+            .unwrap()
+            .starts_with("multipart/form-data"));
+        assert_eq!(request.part(&id).content, b"Hello world!");
+    }
+
+    #[tokio::test]
+    async fn stream_is_not_sent_twice() {
+        let mut server = MockServer::start(json!([])).await;
+        let bot = server.bot();
+
+        let stream = futures::stream::iter([Ok(bytes::Bytes::from_static(b"Hello world!"))]);
+        let request = bot.send_media_group(ChatId(1), media(InputFile::stream(stream)));
+
+        request.send_ref().await.unwrap();
+        server.request().await;
+
+        // This is what adaptors do when they retry a request
+        match request.send_ref().await {
+            Err(RequestError::Io(err)) => assert!(
+                err.to_string()
+                    .contains("the stream of this `InputFile` was already uploaded"),
+                "{}",
+                err
+            ),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::types::ResponseParameters;
 
 /// An error caused by sending a request to Telegram.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum RequestError {
     /// A Telegram API error.
     #[error("A Telegram's error: {0}")]
@@ -46,6 +47,63 @@ pub enum RequestError {
     /// Occurs when trying to send a file to Telegram.
     #[error("An I/O error: {0}")]
     Io(#[from] io::Error),
+
+    /// A file doesn't satisfy Telegram's requirements, found before uploading
+    /// it.
+    ///
+    /// This error is only returned when file validation is enabled, see
+    /// [`Bot::validate_files`].
+    ///
+    /// [`Bot::validate_files`]: crate::Bot::validate_files
+    #[error("An invalid file: {0}")]
+    InvalidFile(#[from] FileValidationError),
+
+    /// The request wasn't sent because the `Throttle` bot adaptor was shut
+    /// down.
+    ///
+    /// See [`Throttle::shutdown`].
+    ///
+    /// [`Throttle::shutdown`]: crate::adaptors::Throttle::shutdown
+    #[cfg(feature = "throttle")]
+    #[error("The request was dropped because `Throttle` was shut down")]
+    ThrottleShutdown,
+}
+
+/// An error caused by a file which doesn't satisfy Telegram's requirements.
+///
+/// See [`Bot::validate_files`].
+///
+/// [`Bot::validate_files`]: crate::Bot::validate_files
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum FileValidationError {
+    /// The file is bigger than allowed.
+    #[error("`{field}` of `{method}` is too big: {size} bytes (the limit is {limit} bytes)")]
+    TooBig {
+        /// Name of the method, e.g. `"SendPhoto"`.
+        method: &'static str,
+        /// Name of the field the file is in, e.g. `"photo"`.
+        field: &'static str,
+        /// Size of the file in bytes.
+        size: u64,
+        /// Maximum allowed size of the file in bytes.
+        limit: u64,
+    },
+
+    /// The file has a type not allowed in this field.
+    #[error(
+        "`{field}` of `{method}` has a wrong type: expected {expected}, found {}",
+        .found.unwrap_or("unknown type")
+    )]
+    WrongType {
+        /// Name of the method, e.g. `"SendVoice"`.
+        method: &'static str,
+        /// Name of the field the file is in, e.g. `"voice"`.
+        field: &'static str,
+        /// Description of the allowed types, e.g. `"OGG/Opus"`.
+        expected: &'static str,
+        /// Detected type of the file, if it could be detected.
+        found: Option<&'static str>,
+    },
 }
 
 /// An error caused by downloading a file.
@@ -89,6 +147,21 @@ impl AsResponseParameters for crate::RequestError {
     }
 }
 
+/// Errors which may contain an [`ApiError`].
+pub trait AsApiError {
+    /// Returns the API error, if this error was returned by Telegram.
+    fn api_error(&self) -> Option<&ApiError>;
+}
+
+impl AsApiError for crate::RequestError {
+    fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Api(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 /// A kind of an API error.
 #[derive(Debug, Error, Deserialize, PartialEq, Hash, Eq, Clone)]
 #[serde(field_identifier)]
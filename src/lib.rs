@@ -47,6 +47,8 @@
 //! - `erased` — enables [`ErasedRequester`] bot adaptor
 //! - `throttle` — enables [`Throttle`] bot adaptor
 //! - `cache_me` — enables [`CacheMe`] bot adaptor
+//! - `cache_file_ids` — enables [`CacheFileIds`] bot adaptor
+//! - `auto_migrate` — enables [`AutoMigrate`] bot adaptor
 //! - `full` — enables all features except `nightly` and tls-related
 //! - `nightly` — enables nightly-only features, currently:
 //!   - Removes some future boxing using `#![feature(type_alias_impl_trait)]`
@@ -58,6 +60,8 @@
 //! [`ErasedRequester`]: adaptors::ErasedRequester
 //! [`Throttle`]: adaptors::Throttle
 //! [`CacheMe`]: adaptors::CacheMe
+//! [`CacheFileIds`]: adaptors::CacheFileIds
+//! [`AutoMigrate`]: adaptors::AutoMigrate
 //! [`native-tls`]: https://docs.rs/native-tls
 //! [`rustls`]: https://docs.rs/rustls
 
@@ -124,3 +128,5 @@ mod serde_multipart;
 
 #[cfg(test)]
 mod codegen;
+#[cfg(test)]
+mod mock;
@@ -353,6 +353,15 @@ macro_rules! impl_payload {
     };
     (@[multipart = $($multipart_attr:ident),*] $Method:ident req { $($reqf:ident),* } opt { $($optf:ident),*} ) => {
         impl crate::requests::MultipartPayload for $Method {
+            fn text_fields(&self, into: &mut dyn FnMut(&'static str, String)) {
+                $(
+                    crate::serde_multipart::MultipartField::encode(&self.$reqf, stringify!($reqf), into);
+                )*
+                $(
+                    crate::serde_multipart::MultipartField::encode(&self.$optf, stringify!($optf), into);
+                )*
+            }
+
             fn copy_files(&self, into: &mut dyn FnMut(crate::types::InputFile)) {
                 $(
                     crate::types::InputFileLike::copy_into(&self.$multipart_attr, into);
@@ -364,14 +373,20 @@ macro_rules! impl_payload {
                     crate::types::InputFileLike::move_into(&mut self.$multipart_attr, into);
                 )*
             }
+
+            fn for_each_file(&self, f: &mut dyn FnMut(&'static str, &crate::types::InputFile)) {
+                $(
+                    crate::types::InputFileLike::for_each(&self.$multipart_attr, stringify!($multipart_attr), f);
+                )*
+            }
         }
     };
     (@[] $($ignored:tt)*) => {}
 }
 
 macro_rules! download_forward {
-    ($l:lifetime $T:ident $S:ty {$this:ident => $inner:expr}) => {
-        impl<$l, $T: $crate::net::Download<$l>> $crate::net::Download<$l> for $S {
+    ($l:lifetime $T:ident [$($G:ident),*] $S:ty {$this:ident => $inner:expr}) => {
+        impl<$l, $T: $crate::net::Download<$l> $(, $G)*> $crate::net::Download<$l> for $S {
             type Err = <$T as $crate::net::Download<$l>>::Err;
 
             type Fut = <$T as $crate::net::Download<$l>>::Fut;
@@ -397,6 +412,9 @@ macro_rules! download_forward {
             }
         }
     };
+    ($l:lifetime $T:ident $S:ty {$this:ident => $inner:expr}) => {
+        download_forward!($l $T [] $S { $this => $inner });
+    };
 }
 
 macro_rules! requester_forward {
@@ -0,0 +1,455 @@
+//! Mocks used in tests.
+
+use std::{
+    any::Any,
+    future::{ready, IntoFuture, Ready},
+    sync::Arc,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use url::Url;
+
+use crate::{
+    requests::{HasPayload, Output, Payload, Request, Requester},
+    types::*,
+    Bot, RequestError,
+};
+
+type Handler = dyn Fn(&'static str, &dyn Any) -> Result<Value, RequestError> + Send + Sync;
+
+/// [`Requester`] which passes all requests to a handler instead of sending
+/// them to Telegram.
+///
+/// The handler receives the name of the method and the payload (which can be
+/// downcasted to the concrete payload type) and returns the JSON of the result.
+#[derive(Clone)]
+pub(crate) struct MockBot {
+    // Used only to construct payloads
+    bot: Bot,
+    handler: Arc<Handler>,
+}
+
+impl MockBot {
+    pub(crate) fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&'static str, &dyn Any) -> Result<Value, RequestError> + Send + Sync + 'static,
+    {
+        Self {
+            bot: Bot::new("TOKEN"),
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+/// Request returned by [`MockBot`] methods.
+#[derive(Clone)]
+pub(crate) struct MockRequest<P> {
+    handler: Arc<Handler>,
+    payload: P,
+}
+
+impl<P: Payload> HasPayload for MockRequest<P> {
+    type Payload = P;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        &mut self.payload
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        &self.payload
+    }
+}
+
+impl<P> Request for MockRequest<P>
+where
+    P: Payload + Any,
+    P::Output: DeserializeOwned + Send,
+{
+    type Err = RequestError;
+    type Send = Ready<Result<P::Output, RequestError>>;
+    type SendRef = Ready<Result<P::Output, RequestError>>;
+
+    fn send(self) -> Self::Send {
+        self.send_ref()
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        let res = (self.handler)(P::NAME, &self.payload).and_then(|value| {
+            serde_json::from_value(value.clone()).map_err(|source| RequestError::InvalidJson {
+                source,
+                raw: value.to_string().into(),
+            })
+        });
+
+        ready(res)
+    }
+}
+
+impl<P> IntoFuture for MockRequest<P>
+where
+    P: Payload + Any,
+    P::Output: DeserializeOwned + Send,
+{
+    type Output = Result<Output<Self>, <Self as Request>::Err>;
+    type IntoFuture = <Self as Request>::Send;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.send()
+    }
+}
+
+macro_rules! fmock {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        MockRequest {
+            handler: Arc::clone(&$this.handler),
+            payload: $this.bot.$m($($arg),*).payload_ref().clone(),
+        }
+    };
+}
+
+macro_rules! ftymock {
+    ($T:ident) => {
+        MockRequest<crate::payloads::$T>
+    };
+}
+
+impl Requester for MockBot {
+    type Err = RequestError;
+
+    requester_forward! {
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        send_message,
+        forward_message,
+        copy_message,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        send_sticker,
+        get_sticker_set,
+        get_custom_emoji_stickers,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        create_invoice_link,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => fmock, ftymock
+    }
+}
+
+/// HTTP server which imitates the Bot API: it records all requests and
+/// responds to them with `{"ok": true, "result": <result>}`.
+pub(crate) struct MockServer {
+    url: Url,
+    requests: mpsc::UnboundedReceiver<RecordedRequest>,
+}
+
+impl MockServer {
+    pub(crate) async fn start(result: Value) -> Self {
+        Self::start_with(result, |_| {}).await
+    }
+
+    /// Like [`MockServer::start`], but calls `on_data` with all bytes of a
+    /// request received so far every time new bytes are received.
+    pub(crate) async fn start_with<F>(result: Value, on_data: F) -> Self
+    where
+        F: Fn(&[u8]) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let response = json!({ "ok": true, "result": result }).to_string();
+        let on_data: Arc<OnData> = Arc::new(on_data);
+        let (tx, requests) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((conn, _)) = listener.accept().await {
+                tokio::spawn(serve(
+                    conn,
+                    response.clone(),
+                    Arc::clone(&on_data),
+                    tx.clone(),
+                ));
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Returns the url of this server.
+    pub(crate) fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns a bot which sends requests to this server.
+    pub(crate) fn bot(&self) -> Bot {
+        Bot::new("TOKEN").set_api_url(self.url.clone())
+    }
+
+    /// Returns the next request received by this server.
+    pub(crate) async fn request(&mut self) -> RecordedRequest {
+        self.requests.recv().await.expect("server has stopped")
+    }
+}
+
+type OnData = dyn Fn(&[u8]) + Send + Sync;
+
+async fn serve(
+    mut conn: TcpStream,
+    response: String,
+    on_data: Arc<OnData>,
+    requests: mpsc::UnboundedSender<RecordedRequest>,
+) {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 8 * 1024];
+
+    let request = loop {
+        if let Some(request) = RecordedRequest::parse(&buf) {
+            break request;
+        }
+
+        match conn.read(&mut chunk).await {
+            // The client has aborted the request
+            Ok(0) | Err(_) => return,
+            Ok(n) => {
+                buf.extend_from_slice(&chunk[..n]);
+                on_data(&buf);
+            }
+        }
+    };
+
+    let _ = requests.send(request);
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: \
+         close\r\n\r\n{}",
+        response.len(),
+        response
+    );
+    let _ = conn.write_all(response.as_bytes()).await;
+    let _ = conn.shutdown().await;
+}
+
+/// Request received by [`MockServer`].
+#[derive(Debug)]
+pub(crate) struct RecordedRequest {
+    /// Path of the request, e.g. `/botTOKEN/SendMessage`.
+    pub(crate) path: String,
+    headers: Vec<(String, String)>,
+    /// Body of the request (with the chunked encoding removed).
+    pub(crate) body: Vec<u8>,
+}
+
+/// Part of a `multipart/form-data` body.
+#[derive(Debug)]
+pub(crate) struct FormPart {
+    pub(crate) name: String,
+    pub(crate) content_type: Option<String>,
+    pub(crate) content: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// Returns the value of a header.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    /// Returns the parts of a `multipart/form-data` body.
+    pub(crate) fn parts(&self) -> Vec<FormPart> {
+        let content_type = self.header("content-type").unwrap();
+        let (_, boundary) = content_type.split_once("boundary=").unwrap();
+        let delimiter = format!("--{}", boundary);
+
+        let mut parts = split(&self.body, delimiter.as_bytes());
+        // The preamble and the epilogue
+        parts.remove(0);
+        parts.pop();
+
+        parts
+            .into_iter()
+            .map(|part| {
+                let part = part.strip_prefix(b"\r\n").unwrap();
+                let part = part.strip_suffix(b"\r\n").unwrap();
+                let (head, content) = split_once(part, b"\r\n\r\n").unwrap();
+                let headers = parse_headers(std::str::from_utf8(head).unwrap());
+
+                let disposition = header(&headers, "content-disposition").unwrap();
+                let (_, name) = disposition.split_once("name=\"").unwrap();
+                let (name, _) = name.split_once('"').unwrap();
+
+                FormPart {
+                    name: name.to_owned(),
+                    content_type: header(&headers, "content-type").map(<_>::to_owned),
+                    content: content.to_owned(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the part of a `multipart/form-data` body with the given name.
+    pub(crate) fn part(&self, name: &str) -> FormPart {
+        self.parts()
+            .into_iter()
+            .find(|part| part.name == name)
+            .unwrap_or_else(|| panic!("no `{}` part", name))
+    }
+
+    /// Parses a request, returns `None` if `buf` doesn't contain the whole
+    /// request.
+    fn parse(buf: &[u8]) -> Option<Self> {
+        let (head, rest) = split_once(buf, b"\r\n\r\n")?;
+        let head = std::str::from_utf8(head).unwrap();
+        let (request_line, head) = head.split_once("\r\n").unwrap_or((head, ""));
+        let path = request_line.split(' ').nth(1).unwrap().to_owned();
+        let headers = parse_headers(head);
+
+        let body = if let Some(len) = header(&headers, "content-length") {
+            rest.get(..len.parse().unwrap())?.to_owned()
+        } else if header(&headers, "transfer-encoding") == Some("chunked") {
+            let mut body = Vec::new();
+            let mut rest = rest;
+
+            loop {
+                let (len, tail) = split_once(rest, b"\r\n")?;
+                let len = usize::from_str_radix(std::str::from_utf8(len).unwrap(), 16).unwrap();
+                if len == 0 {
+                    break body;
+                }
+
+                body.extend_from_slice(tail.get(..len)?);
+                rest = tail.get(len + 2..)?;
+            }
+        } else {
+            Vec::new()
+        };
+
+        Some(Self {
+            path,
+            headers,
+            body,
+        })
+    }
+}
+
+fn parse_headers(head: &str) -> Vec<(String, String)> {
+    head.split("\r\n")
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_owned()))
+        .collect()
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| &**value)
+}
+
+fn split_once<'a>(haystack: &'a [u8], needle: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let pos = haystack
+        .windows(needle.len())
+        .position(|window| window == needle)?;
+
+    Some((&haystack[..pos], &haystack[pos + needle.len()..]))
+}
+
+fn split<'a>(mut haystack: &'a [u8], needle: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    while let Some((part, rest)) = split_once(haystack, needle) {
+        parts.push(part);
+        haystack = rest;
+    }
+    parts.push(haystack);
+
+    parts
+}
@@ -11,6 +11,9 @@ use crate::{
 /// A ready-to-send Telegram request whose payload is sent using
 /// [multipart/form-data].
 ///
+/// If none of the files in the payload need to be uploaded (i.e. they are all
+/// file ids or URLs), the payload is sent as JSON instead.
+///
 /// [multipart/form-data]: https://core.telegram.org/bots/api#making-requests
 #[must_use = "Requests are lazy and do nothing unless sent"]
 #[derive(Clone)]
@@ -1,18 +1,52 @@
 use crate::{
     payloads,
     requests::Payload,
+    serde_multipart::MultipartField,
     types::{InputFile, InputFileLike, InputMedia},
 };
 
 /// Payloads that need to be sent as `multipart/form-data` because they contain
 /// files inside.
 pub trait MultipartPayload: Payload {
+    /// Calls `into` with every text part of `multipart/form-data` this payload
+    /// is encoded as, i.e. with the name and the text of every field except
+    /// for `None`s.
+    ///
+    /// Files are encoded as `attach://<id>` (or as their url or file id, if
+    /// they don't need to be uploaded), the files themselves are taken by
+    /// [`copy_files`] and [`move_files`].
+    ///
+    /// [`copy_files`]: MultipartPayload::copy_files
+    /// [`move_files`]: MultipartPayload::move_files
+    fn text_fields(&self, into: &mut dyn FnMut(&'static str, String));
+
     fn copy_files(&self, into: &mut dyn FnMut(InputFile));
 
     fn move_files(&mut self, into: &mut dyn FnMut(InputFile));
+
+    /// Calls `f` with every file of this payload and the name of the field it
+    /// is in (for files inside of [`InputMedia`] the kind of the media is used
+    /// instead, e.g. `"photo"` or `"thumb"`).
+    ///
+    /// This is used to validate files before uploading them. The default
+    /// implementation passes empty field names.
+    fn for_each_file(&self, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        self.copy_files(&mut |file| f("", &file))
+    }
 }
 
 impl MultipartPayload for payloads::SendMediaGroup {
+    fn text_fields(&self, into: &mut dyn FnMut(&'static str, String)) {
+        self.chat_id.encode("chat_id", into);
+        self.media.encode("media", into);
+        self.disable_notification
+            .encode("disable_notification", into);
+        self.protect_content.encode("protect_content", into);
+        self.reply_to_message_id.encode("reply_to_message_id", into);
+        self.allow_sending_without_reply
+            .encode("allow_sending_without_reply", into);
+    }
+
     fn copy_files(&self, into: &mut dyn FnMut(InputFile)) {
         self.media
             .iter()
@@ -26,9 +60,23 @@ impl MultipartPayload for payloads::SendMediaGroup {
             .flat_map(InputMedia::files_mut)
             .for_each(|f| f.move_into(into))
     }
+
+    fn for_each_file(&self, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        self.media
+            .iter()
+            .flat_map(InputMedia::named_files)
+            .for_each(|(field, file)| f(field, file))
+    }
 }
 
 impl MultipartPayload for payloads::EditMessageMedia {
+    fn text_fields(&self, into: &mut dyn FnMut(&'static str, String)) {
+        self.chat_id.encode("chat_id", into);
+        self.message_id.encode("message_id", into);
+        self.media.encode("media", into);
+        self.reply_markup.encode("reply_markup", into);
+    }
+
     fn copy_files(&self, into: &mut dyn FnMut(InputFile)) {
         self.media.files().for_each(|f| f.copy_into(into))
     }
@@ -36,9 +84,21 @@ impl MultipartPayload for payloads::EditMessageMedia {
     fn move_files(&mut self, into: &mut dyn FnMut(InputFile)) {
         self.media.files_mut().for_each(|f| f.move_into(into))
     }
+
+    fn for_each_file(&self, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        self.media
+            .named_files()
+            .for_each(|(field, file)| f(field, file))
+    }
 }
 
 impl MultipartPayload for payloads::EditMessageMediaInline {
+    fn text_fields(&self, into: &mut dyn FnMut(&'static str, String)) {
+        self.inline_message_id.encode("inline_message_id", into);
+        self.media.encode("media", into);
+        self.reply_markup.encode("reply_markup", into);
+    }
+
     fn copy_files(&self, into: &mut dyn FnMut(InputFile)) {
         self.media.files().for_each(|f| f.copy_into(into))
     }
@@ -46,4 +106,10 @@ impl MultipartPayload for payloads::EditMessageMediaInline {
     fn move_files(&mut self, into: &mut dyn FnMut(InputFile)) {
         self.media.files_mut().for_each(|f| f.move_into(into))
     }
+
+    fn for_each_file(&self, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        self.media
+            .named_files()
+            .for_each(|(field, file)| f(field, file))
+    }
 }
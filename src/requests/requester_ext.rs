@@ -1,8 +1,18 @@
-use crate::{adaptors::DefaultParseMode, requests::Requester, types::ParseMode};
+use crate::{
+    adaptors::{defaults::DefaultValues, DefaultParseMode, Defaults},
+    requests::Requester,
+    types::ParseMode,
+};
 
 #[cfg(feature = "cache_me")]
 use crate::adaptors::CacheMe;
 
+#[cfg(feature = "cache_file_ids")]
+use crate::adaptors::CacheFileIds;
+
+#[cfg(feature = "auto_migrate")]
+use crate::adaptors::AutoMigrate;
+
 #[cfg(feature = "auto_send")]
 #[allow(deprecated)]
 use crate::adaptors::AutoSend;
@@ -27,6 +37,24 @@ pub trait RequesterExt: Requester {
         CacheMe::new(self)
     }
 
+    /// Add `file_id` caching ability, see [`CacheFileIds`] for more.
+    #[cfg(feature = "cache_file_ids")]
+    fn cache_file_ids(self) -> CacheFileIds<Self>
+    where
+        Self: Sized,
+    {
+        CacheFileIds::new(self)
+    }
+
+    /// Retry requests to migrated groups, see [`AutoMigrate`] for more.
+    #[cfg(feature = "auto_migrate")]
+    fn auto_migrate(self) -> AutoMigrate<Self>
+    where
+        Self: Sized,
+    {
+        AutoMigrate::new(self)
+    }
+
     /// Send requests automatically, see [`AutoSend`] for more.
     #[cfg(feature = "auto_send")]
     #[deprecated(
@@ -68,7 +96,6 @@ pub trait RequesterExt: Requester {
     where
         Self: Sized + Clone + Send + Sync + 'static,
         Self::Err: crate::errors::AsResponseParameters,
-        Self::GetChat: Send,
     {
         Throttle::new_spawn(self, limits)
     }
@@ -107,6 +134,15 @@ pub trait RequesterExt: Requester {
     {
         DefaultParseMode::new(self, parse_mode)
     }
+
+    /// Specifies default values of optional parameters (e.g.
+    /// `disable_notification`), see [`Defaults`] for more.
+    fn defaults(self, values: DefaultValues) -> Defaults<Self>
+    where
+        Self: Sized,
+    {
+        Defaults::new(self, values)
+    }
 }
 
 impl<T> RequesterExt for T
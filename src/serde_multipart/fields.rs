@@ -0,0 +1,134 @@
+use url::Url;
+
+use crate::types::{
+    AllowedUpdate, InlineKeyboardMarkup, InputFile, InputMedia, InputSticker, MaskPosition,
+    MessageEntity, MessageId, ParseMode, Recipient, ReplyMarkup, StickerType, UserId,
+};
+
+/// A field of a [`MultipartPayload`] which can be encoded as text parts of
+/// `multipart/form-data`.
+///
+/// The encoding must match the `Serialize` implementation of the payload (it
+/// is checked by the tests with [`unserializers`]).
+///
+/// [`MultipartPayload`]: crate::requests::MultipartPayload
+/// [`unserializers`]: super::unserializers
+pub(crate) trait MultipartField {
+    /// Calls `into` with every text part this field is encoded as, usually
+    /// it's a single part called `name`.
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String));
+}
+
+impl<T: MultipartField> MultipartField for Option<T> {
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        if let Some(value) = self {
+            value.encode(name, into)
+        }
+    }
+}
+
+/// Fields which are encoded as their `Display` representation.
+macro_rules! display_fields {
+    ($($T:ty),*) => {$(
+        impl MultipartField for $T {
+            fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+                into(name, self.to_string())
+            }
+        }
+    )*};
+}
+
+display_fields!(String, bool, u8, u32, i32, Url);
+
+/// Fields which are encoded as JSON.
+macro_rules! json_fields {
+    ($($T:ty),*) => {$(
+        impl MultipartField for $T {
+            fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+                // Serialization of these types can't fail: they don't contain maps with
+                // non-string keys or types with failing `Serialize` implementations
+                into(name, serde_json::to_string(self).expect("serialization can't fail"))
+            }
+        }
+    )*};
+}
+
+json_fields!(
+    Vec<MessageEntity>,
+    Vec<AllowedUpdate>,
+    Vec<InputMedia>,
+    InputMedia,
+    MaskPosition,
+    ReplyMarkup,
+    InlineKeyboardMarkup
+);
+
+impl MultipartField for Recipient {
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        let value = match self {
+            Recipient::Id(id) => id.to_string(),
+            Recipient::ChannelUsername(username) => username.clone(),
+        };
+
+        into(name, value)
+    }
+}
+
+impl MultipartField for UserId {
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        into(name, self.0.to_string())
+    }
+}
+
+impl MultipartField for MessageId {
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        self.0.encode(name, into)
+    }
+}
+
+impl MultipartField for ParseMode {
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        #[allow(deprecated)]
+        let value = match self {
+            ParseMode::MarkdownV2 => "MarkdownV2",
+            ParseMode::Html => "HTML",
+            ParseMode::Markdown => "Markdown",
+        };
+
+        into(name, value.to_owned())
+    }
+}
+
+impl MultipartField for InputFile {
+    fn encode(&self, name: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        into(name, self.attach_or_value())
+    }
+}
+
+/// Sticker is encoded in a field named after its format (it's
+/// `#[serde(flatten)]`ed in payloads).
+impl MultipartField for InputSticker {
+    fn encode(&self, _: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        let (name, file) = match self {
+            InputSticker::Png(file) => ("png_sticker", file),
+            InputSticker::Tgs(file) => ("tgs_sticker", file),
+            InputSticker::Webm(file) => ("webm_sticker", file),
+        };
+
+        file.encode(name, into)
+    }
+}
+
+/// Sticker type is encoded in the `sticker_type` field (it's
+/// `#[serde(flatten)]`ed in payloads).
+impl MultipartField for StickerType {
+    fn encode(&self, _: &'static str, into: &mut dyn FnMut(&'static str, String)) {
+        let value = match self {
+            StickerType::Regular => "regular",
+            StickerType::Mask => "mask",
+            StickerType::CustomEmoji => "custom_emoji",
+        };
+
+        into("sticker_type", value.to_owned())
+    }
+}
@@ -5,95 +5,416 @@
 //!
 //! ## How it works
 //!
-//! You better not know...
+//! Payloads are encoded in two steps:
 //!
-//! This whole module is an awful hack and we'll probably stop using it in next
-//! versions (in favor of something less automatic, but more simple).
+//! 1. Fields of the payload are encoded into text parts by
+//!    [`MultipartPayload::text_fields`], which is generated by `impl_payload!`
+//!    (and so by codegen) from the list of the fields. Every field is encoded
+//!    explicitly with [`MultipartField`]: strings and numbers are sent as-is,
+//!    complex types (entities, markups, media) are sent as JSON and input
+//!    files are sent as `attach://<id>` (or as their url or file id, if they
+//!    don't need to be uploaded).
+//! 2. Files are taken from the payload with [`MultipartPayload`] and are added
+//!    as parts named by their ids.
+//!
+//! Files are moved out of the payload by [`to_form`] and cheaply copied by
+//! [`to_form_ref`], so neither of them clones file contents.
+//!
+//! The encoding of all multipart payloads is checked against their `Serialize`
+//! implementations in tests, with [`unserializers`].
 
 mod error;
-mod serializers;
+mod fields;
+#[cfg(test)]
+mod unserializers;
+mod validation;
 
 use std::future::Future;
 
 use reqwest::multipart::Form;
-use serde::Serialize;
 
-use crate::requests::MultipartPayload;
+use crate::{requests::MultipartPayload, types::InputFile};
 use error::Error;
-use serializers::MultipartSerializer;
+
+pub(crate) use fields::MultipartField;
 
 /// Serializes given value into [`Form`] **taking all input files out**.
 ///
+/// If `validate` is `true`, files are checked against Telegram's requirements
+/// (see [`Bot::validate_files`]).
+///
 /// [`Form`]:  reqwest::multipart::Form
-pub(crate) fn to_form<T>(val: &mut T) -> Result<impl Future<Output = Form>, Error>
+/// [`Bot::validate_files`]: crate::Bot::validate_files
+pub(crate) fn to_form<T>(val: &mut T, validate: bool) -> impl Future<Output = Result<Form, Error>>
 where
-    T: Serialize + MultipartPayload,
+    T: MultipartPayload,
 {
-    let mut form = val.serialize(MultipartSerializer::new())?;
-
-    let mut vec = Vec::with_capacity(1);
-    val.move_files(&mut |f| vec.push(f));
-    let iter = vec.into_iter();
-
-    let fut = async move {
-        for file in iter {
-            if file.needs_attach() {
-                let id = file.id().to_owned();
-                if let Some(part) = file.into_part() {
-                    form = form.part(id, part.await);
-                }
-            }
-        }
+    // Text fields must be encoded before the files are moved out, so that
+    // `attach://` references are written to the fields
+    let form = text_form(&*val);
+    let fields = validate.then(|| file_fields(&*val));
 
-        form
-    };
+    let mut files = Vec::with_capacity(1);
+    val.move_files(&mut |f| files.push(f));
 
-    Ok(fut)
+    attach_files::<T>(form, files, fields)
 }
 
 /// Serializes given value into [`Form`].
 ///
+/// If `validate` is `true`, files are checked against Telegram's requirements
+/// (see [`Bot::validate_files`]).
+///
 /// [`Form`]:  reqwest::multipart::Form
-pub(crate) fn to_form_ref<T: ?Sized>(val: &T) -> Result<impl Future<Output = Form>, Error>
+/// [`Bot::validate_files`]: crate::Bot::validate_files
+pub(crate) fn to_form_ref<T>(val: &T, validate: bool) -> impl Future<Output = Result<Form, Error>>
 where
-    T: Serialize + MultipartPayload,
+    T: ?Sized + MultipartPayload,
 {
-    let mut form = val.serialize(MultipartSerializer::new())?;
-    let mut vec = Vec::with_capacity(1);
-    val.copy_files(&mut |f| vec.push(f));
-
-    let iter = vec.into_iter();
-
-    let fut = async move {
-        for file in iter {
-            if file.needs_attach() {
-                let id = file.id().to_owned();
-                if let Some(part) = file.into_part() {
-                    form = form.part(id, part.await);
-                }
-            }
+    let form = text_form(val);
+    let fields = validate.then(|| file_fields(val));
+
+    let mut files = Vec::with_capacity(1);
+    val.copy_files(&mut |f| files.push(f));
+
+    attach_files::<T>(form, files, fields)
+}
+
+/// Creates [`Form`] with text fields of `val`.
+fn text_form<T: ?Sized + MultipartPayload>(val: &T) -> Form {
+    text_fields(val)
+        .into_iter()
+        .fold(Form::new(), |form, (name, text)| form.text(name, text))
+}
+
+/// Returns text fields of `val` as `(name, text)` pairs.
+fn text_fields<T: ?Sized + MultipartPayload>(val: &T) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+    val.text_fields(&mut |name, text| fields.push((name, text)));
+
+    fields
+}
+
+/// Returns ids of all files that need to be attached alongside with the names
+/// of the fields they are in.
+fn file_fields<T: ?Sized + MultipartPayload>(val: &T) -> Vec<(String, &'static str)> {
+    let mut fields = Vec::with_capacity(1);
+    val.for_each_file(&mut |field, file| {
+        if file.needs_attach() {
+            fields.push((file.id().to_owned(), field));
         }
+    });
+
+    fields
+}
+
+/// Adds files that need to be attached to the `form`.
+async fn attach_files<T: ?Sized + MultipartPayload>(
+    mut form: Form,
+    files: Vec<InputFile>,
+    fields: Option<Vec<(String, &'static str)>>,
+) -> Result<Form, Error> {
+    for file in files {
+        if file.needs_attach() {
+            form = attach::<T>(form, file, fields.as_deref()).await?;
+        }
+    }
+
+    Ok(form)
+}
+
+/// Adds `file` to the `form`, validating it first if `fields` are provided.
+async fn attach<T: ?Sized + MultipartPayload>(
+    form: Form,
+    file: InputFile,
+    fields: Option<&[(String, &'static str)]>,
+) -> Result<Form, Error> {
+    // Fail before anything is sent, instead of sending a request with a broken body
+    if file.is_uploaded_stream() {
+        return Err(InputFile::uploaded_stream_error().into());
+    }
+
+    let id = file.id().to_owned();
+
+    let mime = match fields {
+        Some(fields) => {
+            let field = fields
+                .iter()
+                .find(|(file_id, _)| *file_id == id)
+                .map_or("", |&(_, field)| field);
+
+            validation::validate(T::NAME, field, &file).await?
+        }
+        None => None,
+    };
 
-        form
+    let part = match file.into_part() {
+        Some(part) => part.await,
+        None => return Ok(form),
     };
 
-    Ok(fut)
+    let part = match mime {
+        // `mime_str` only fails if the mime type can't be parsed, but we only use valid ones
+        Some(mime) => part.mime_str(mime).expect("valid mime type"),
+        None => part,
+    };
+
+    Ok(form.part(id, part))
 }
 
 #[cfg(test)]
 mod tests {
-    use tokio::fs::File;
+    use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+    use bytes::Bytes;
+    use reqwest::multipart::Form;
+    use serde::Serialize;
+    use serde_json::{json, Value};
+    use tokio::{fs::File, sync::Notify, time::timeout};
+    use url::Url;
 
-    use super::to_form_ref;
+    use super::{text_fields, to_form, to_form_ref, unserializers::FieldsUnserializer, Error};
     use crate::{
+        errors::FileValidationError,
+        mock::MockServer,
         payloads::{self, setters::*},
+        requests::{MultipartPayload, Requester},
         types::{
-            ChatId, InputFile, InputMedia, InputMediaAnimation, InputMediaAudio,
-            InputMediaDocument, InputMediaPhoto, InputMediaVideo, InputSticker, MessageEntity,
-            MessageEntityKind, ParseMode, UserId,
+            AllowedUpdate, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile,
+            InputMedia, InputMediaAnimation, InputMediaAudio, InputMediaDocument, InputMediaPhoto,
+            InputMediaVideo, InputSticker, MaskPoint, MaskPosition, MessageEntity,
+            MessageEntityKind, MessageId, ParseMode, Recipient, ReplyMarkup, StickerType, UserId,
         },
     };
 
+    #[test]
+    fn text_fields_send_photo() {
+        let photo = InputFile::memory(&b"Hello world!"[..]);
+        let attach = format!("attach://{}", photo.id());
+
+        let payload = payloads::SendPhoto::new(ChatId(0), photo)
+            .caption("caption")
+            .parse_mode(ParseMode::MarkdownV2)
+            .caption_entities([MessageEntity::new(MessageEntityKind::Url, 0, 7)])
+            .reply_to_message_id(MessageId(17));
+
+        let mut fields: BTreeMap<_, _> = text_fields(&payload).into_iter().collect();
+
+        let entities: Value =
+            serde_json::from_str(&fields.remove("caption_entities").unwrap()).unwrap();
+        assert_eq!(
+            entities,
+            json!([{ "type": "url", "offset": 0, "length": 7 }])
+        );
+
+        let expected: BTreeMap<_, _> = [
+            ("chat_id", "0"),
+            ("photo", &*attach),
+            ("caption", "caption"),
+            ("parse_mode", "MarkdownV2"),
+            ("reply_to_message_id", "17"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k, v.to_owned()))
+        .collect();
+
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn text_fields_flatten() {
+        let sticker = InputFile::file_id("id");
+        let payload = payloads::CreateNewStickerSet::new(
+            UserId(1),
+            "name",
+            "title",
+            InputSticker::Webm(sticker),
+            "✈️",
+        )
+        .sticker_type(StickerType::CustomEmoji);
+
+        let fields: BTreeMap<_, _> = text_fields(&payload).into_iter().collect();
+        assert_eq!(fields["webm_sticker"], "id");
+        assert_eq!(fields["user_id"], "1");
+        assert_eq!(fields["sticker_type"], "custom_emoji");
+        assert!(!fields.contains_key("sticker"));
+    }
+
+    /// Checks that the explicit encoding of every multipart payload matches
+    /// its `Serialize` implementation.
+    #[test]
+    fn round_trip() {
+        fn check<P: MultipartPayload + Serialize>(payload: &P) {
+            let mut encoded = text_fields(payload)
+                .into_iter()
+                .map(|(name, text)| (name.to_owned(), text))
+                .collect::<Vec<_>>();
+            let mut unserialized = payload.serialize(FieldsUnserializer).unwrap();
+
+            encoded.sort();
+            unserialized.sort();
+            assert_eq!(encoded, unserialized, "{}", P::NAME);
+        }
+
+        let markup = InlineKeyboardMarkup::new([[InlineKeyboardButton::callback("a", "b")]]);
+        let url: Url = "https://example.com".parse().unwrap();
+
+        check(&payloads::SetWebhook::new(url.clone()));
+        check(
+            &payloads::SetWebhook::new(url.clone())
+                .certificate(InputFile::memory(&b"cert"[..]))
+                .ip_address("127.0.0.1")
+                .max_connections(17)
+                .allowed_updates([AllowedUpdate::Message, AllowedUpdate::ChatJoinRequest])
+                .drop_pending_updates(true)
+                .secret_token("secret"),
+        );
+
+        check(&payloads::SendPhoto::new(
+            ChatId(0),
+            InputFile::file("./media/logo.png"),
+        ));
+        check(
+            &payloads::SendPhoto::new(
+                Recipient::ChannelUsername("@channel".to_owned()),
+                InputFile::file_id("id"),
+            )
+            .caption("caption")
+            .parse_mode(ParseMode::Html)
+            .caption_entities(entities())
+            .disable_notification(true)
+            .protect_content(false)
+            .reply_to_message_id(MessageId(17))
+            .allow_sending_without_reply(true)
+            .reply_markup(markup.clone()),
+        );
+        check(
+            &payloads::SendAudio::new(ChatId(-1), InputFile::url(url.clone()))
+                .caption("some text")
+                .duration(10)
+                .performer("performer")
+                .title("title")
+                .thumb(InputFile::memory(&b"thumb"[..]))
+                .reply_markup(ReplyMarkup::kb_remove()),
+        );
+        check(
+            &payloads::SendDocument::new(ChatId(-1), InputFile::memory(&b"document"[..]))
+                .disable_content_type_detection(true)
+                .parse_mode(
+                    #[allow(deprecated)]
+                    ParseMode::Markdown,
+                ),
+        );
+        check(
+            &payloads::SendVideo::new(ChatId(-1), InputFile::file("./media/example.gif"))
+                .width(10)
+                .height(20)
+                .supports_streaming(true),
+        );
+        check(
+            &payloads::SendAnimation::new(ChatId(-1), InputFile::file("./media/example.gif"))
+                .caption("some text")
+                .caption_entities(entities())
+                .thumb(InputFile::url(url.clone()))
+                .width(10)
+                .allow_sending_without_reply(true),
+        );
+        check(&payloads::SendVoice::new(ChatId(-1), InputFile::memory(&b"voice"[..])).duration(1));
+        check(
+            &payloads::SendVideoNote::new(ChatId(-1), InputFile::memory(&b"note"[..]))
+                .length(240)
+                .thumb(InputFile::file_id("thumb")),
+        );
+        check(
+            &payloads::SendMediaGroup::new(
+                ChatId(-1),
+                [
+                    InputMedia::Photo(InputMediaPhoto::new(InputFile::file("./media/logo.png"))),
+                    InputMedia::Document(
+                        InputMediaDocument::new(InputFile::file_id("17"))
+                            .caption_entities(entities())
+                            .thumb(InputFile::memory(&b"thumb"[..])),
+                    ),
+                ],
+            )
+            .reply_to_message_id(MessageId(1)),
+        );
+        check(&payloads::EditMessageMedia::new(
+            ChatId(1),
+            MessageId(2),
+            InputMedia::Video(InputMediaVideo::new(InputFile::memory(&b"video"[..]))),
+        ));
+        check(
+            &payloads::EditMessageMediaInline::new(
+                "inline",
+                InputMedia::Audio(InputMediaAudio::new(InputFile::file_id("audio"))),
+            )
+            .reply_markup(markup),
+        );
+        check(
+            &payloads::SendSticker::new(ChatId(1), InputFile::memory(&b"sticker"[..]))
+                .reply_to_message_id(3),
+        );
+        check(&payloads::SetChatPhoto::new(
+            ChatId(1),
+            InputFile::memory(&b"photo"[..]),
+        ));
+        check(&payloads::UploadStickerFile::new(
+            UserId(1),
+            InputFile::memory(&b"png"[..]),
+        ));
+        check(
+            &payloads::SetStickerSetThumb::new("name", UserId(1))
+                .thumb(InputFile::memory(&b"thumb"[..])),
+        );
+        check(&payloads::CreateNewStickerSet::new(
+            UserId(1),
+            "name",
+            "title",
+            InputSticker::Png(InputFile::memory(&b"png"[..])),
+            "✈️",
+        ));
+        check(
+            &payloads::CreateNewStickerSet::new(
+                UserId(1),
+                "name",
+                "title",
+                InputSticker::Tgs(InputFile::file_id("tgs")),
+                "✈️",
+            )
+            .sticker_type(StickerType::Mask)
+            .mask_position(MaskPosition::new(MaskPoint::Eyes, 1.0, 0.5, 2.0)),
+        );
+        check(&payloads::AddStickerToSet::new(
+            UserId(1),
+            "name",
+            InputSticker::Webm(InputFile::memory(&b"webm"[..])),
+            "✈️",
+        ));
+    }
+
+    #[tokio::test]
+    async fn files_are_moved() {
+        let mut payload =
+            payloads::SendDocument::new(ChatId(0), InputFile::memory(&b"Hello world!"[..]))
+                .thumb(InputFile::file("./media/logo.png"));
+
+        to_form(&mut payload, false).await.unwrap();
+
+        let mut files = 0;
+        payload.copy_files(&mut |file| {
+            assert!(!file.needs_attach());
+            files += 1;
+        });
+        assert_eq!(files, 2);
+
+        let payload =
+            payloads::SendDocument::new(ChatId(0), InputFile::memory(&b"Hello world!"[..]));
+
+        to_form_ref(&payload, false).await.unwrap();
+        assert!(payload.document.needs_attach());
+    }
+
     // https://github.com/teloxide/teloxide/issues/473
     #[tokio::test]
     async fn issue_473() {
@@ -105,59 +426,105 @@ mod tests {
                     length: 0,
                 },
             ]),
+            false,
         )
-        .unwrap()
-        .await;
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
     async fn test_send_media_group() {
         const CAPTION: &str = "caption";
 
-        to_form_ref(&payloads::SendMediaGroup::new(
-            ChatId(0),
-            [
-                InputMedia::Photo(
-                    InputMediaPhoto::new(InputFile::file("./media/logo.png"))
-                        .caption(CAPTION)
-                        .parse_mode(ParseMode::MarkdownV2)
-                        .caption_entities(entities()),
-                ),
-                InputMedia::Video(
-                    InputMediaVideo::new(InputFile::file_id("17")).supports_streaming(true),
-                ),
-                InputMedia::Animation(
-                    InputMediaAnimation::new(InputFile::read(
-                        File::open("./media/example.gif").await.unwrap(),
-                    ))
-                    .thumb(InputFile::read(
-                        File::open("./media/logo.png").await.unwrap(),
-                    ))
-                    .duration(17),
-                ),
-                InputMedia::Audio(
-                    InputMediaAudio::new(InputFile::url("https://example.com".parse().unwrap()))
+        to_form_ref(
+            &payloads::SendMediaGroup::new(
+                ChatId(0),
+                [
+                    InputMedia::Photo(
+                        InputMediaPhoto::new(InputFile::file("./media/logo.png"))
+                            .caption(CAPTION)
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .caption_entities(entities()),
+                    ),
+                    InputMedia::Video(
+                        InputMediaVideo::new(InputFile::file_id("17")).supports_streaming(true),
+                    ),
+                    InputMedia::Animation(
+                        InputMediaAnimation::new(InputFile::read(
+                            File::open("./media/example.gif").await.unwrap(),
+                        ))
+                        .thumb(InputFile::read(
+                            File::open("./media/logo.png").await.unwrap(),
+                        ))
+                        .duration(17),
+                    ),
+                    InputMedia::Audio(
+                        InputMediaAudio::new(InputFile::url(
+                            "https://example.com".parse().unwrap(),
+                        ))
                         .performer("a"),
-                ),
-                InputMedia::Document(InputMediaDocument::new(InputFile::memory(
-                    &b"Hello world!"[..],
-                ))),
-            ],
-        ))
-        .unwrap()
-        .await;
+                    ),
+                    InputMedia::Document(InputMediaDocument::new(InputFile::memory(
+                        &b"Hello world!"[..],
+                    ))),
+                ],
+            ),
+            false,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_media_group_thumb() {
+        let mut server = MockServer::start(json!([])).await;
+        let bot = server.bot().validate_files(true);
+
+        let document = InputFile::memory(&b"Hello world!"[..]);
+        let thumb = InputFile::memory(&[0xFF, 0xD8, 0xFF, 0xE0][..]);
+        let (document_id, thumb_id) = (document.id().to_owned(), thumb.id().to_owned());
+
+        bot.send_media_group(
+            ChatId(0),
+            [InputMedia::Document(
+                InputMediaDocument::new(document).thumb(thumb),
+            )],
+        )
+        .await
+        .unwrap();
+
+        let request = server.request().await;
+        let media: Value = serde_json::from_slice(&request.part("media").content).unwrap();
+        assert_eq!(
+            media,
+            json!([{
+                "type": "document",
+                "media": format!("attach://{}", document_id),
+                "thumb": format!("attach://{}", thumb_id),
+            }])
+        );
+
+        assert_eq!(request.part(&document_id).content, b"Hello world!");
+
+        // The thumbnail was validated as a thumbnail, so it has a mime type
+        let thumb = request.part(&thumb_id);
+        assert_eq!(thumb.content, [0xFF, 0xD8, 0xFF, 0xE0]);
+        assert_eq!(thumb.content_type.as_deref(), Some("image/jpeg"));
     }
 
     #[tokio::test]
     async fn test_add_sticker_to_set() {
-        to_form_ref(&payloads::AddStickerToSet::new(
-            UserId(0),
-            "name",
-            InputSticker::Png(InputFile::file("./media/logo.png")),
-            "✈️⚙️",
-        ))
-        .unwrap()
-        .await;
+        to_form_ref(
+            &payloads::AddStickerToSet::new(
+                UserId(0),
+                "name",
+                InputSticker::Png(InputFile::file("./media/logo.png")),
+                "✈️⚙️",
+            ),
+            false,
+        )
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
@@ -169,9 +536,194 @@ mod tests {
                     File::open("./media/logo.png").await.unwrap(),
                 ))
                 .allow_sending_without_reply(true),
+            false,
         )
-        .unwrap()
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_document_stream() {
+        let received = Arc::new(Notify::new());
+        let mut server = MockServer::start_with(json!(null), {
+            let received = Arc::clone(&received);
+            move |data| {
+                if data.windows(11).any(|w| w == b"first chunk") {
+                    received.notify_one();
+                }
+            }
+        })
         .await;
+
+        let stream = futures::stream::unfold(0, move |state| {
+            let received = Arc::clone(&received);
+            async move {
+                match state {
+                    0 => Some((Ok(Bytes::from_static(b"first chunk")), 1)),
+                    // If the stream was buffered, the server would never receive the first chunk
+                    1 => {
+                        received.notified().await;
+                        Some((Ok(Bytes::from_static(b", second chunk")), 2))
+                    }
+                    _ => None,
+                }
+            }
+        });
+        let file = InputFile::stream(stream);
+        let id = file.id().to_owned();
+
+        let form = to_form_ref(&payloads::SendDocument::new(ChatId(0), file), false)
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(10), send(&server, form))
+            .await
+            .expect("the stream was buffered")
+            .unwrap();
+
+        let request = server.request().await;
+        assert_eq!(request.header("transfer-encoding"), Some("chunked"));
+        assert_eq!(request.part(&id).content, b"first chunk, second chunk");
+    }
+
+    #[tokio::test]
+    async fn test_send_document_stream_with_length() {
+        let mut server = MockServer::start(json!(null)).await;
+
+        let stream = futures::stream::iter([Ok(Bytes::from_static(b"Hello world!"))]);
+        let file = InputFile::stream_with_length(stream, 12).file_name("hello.txt");
+        let id = file.id().to_owned();
+
+        let form = to_form_ref(&payloads::SendDocument::new(ChatId(0), file), false)
+            .await
+            .unwrap();
+        send(&server, form).await.unwrap();
+
+        // The length of the whole form is known only if the length of the stream is
+        // known
+        let request = server.request().await;
+        let len = request.body.len().to_string();
+        assert_eq!(request.header("content-length"), Some(&*len));
+        assert_eq!(request.header("transfer-encoding"), None);
+        assert_eq!(request.part(&id).content, b"Hello world!");
+    }
+
+    #[tokio::test]
+    async fn test_send_document_stream_twice() {
+        let mut server = MockServer::start(json!(null)).await;
+
+        let stream = futures::stream::iter([Ok(Bytes::from_static(b"Hello world!"))]);
+        let payload = payloads::SendDocument::new(ChatId(0), InputFile::stream(stream));
+
+        let form = to_form_ref(&payload, false).await.unwrap();
+        send(&server, form).await.unwrap();
+        server.request().await;
+
+        // `to_form_ref` copies the file, but the stream was already taken by the first
+        // copy, so the form can't be built
+        match to_form_ref(&payload, false).await {
+            Err(Error::Io(err)) => assert!(
+                err.to_string()
+                    .contains("the stream of this `InputFile` was already uploaded"),
+                "{}",
+                err
+            ),
+            res => panic!("unexpected result: {:?}", res.map(drop)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_files() {
+        let photo = payloads::SendPhoto::new(ChatId(0), InputFile::file("./media/logo.png"));
+        to_form_ref(&photo, true).await.unwrap();
+
+        let photo = payloads::SendPhoto::new(ChatId(0), InputFile::memory(vec![0; 11 << 20]));
+        let err = to_form_ref(&photo, true).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FileValidationError::TooBig {
+                method: "SendPhoto",
+                field: "photo",
+                ..
+            })
+        ));
+
+        let voice = payloads::SendVoice::new(ChatId(0), InputFile::file("./media/logo.png"));
+        let err = to_form_ref(&voice, true).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FileValidationError::WrongType {
+                method: "SendVoice",
+                field: "voice",
+                found: Some("PNG"),
+                ..
+            })
+        ));
+
+        // Not validated
+        to_form_ref(&voice, false).await.unwrap();
+
+        let group = payloads::SendMediaGroup::new(
+            ChatId(0),
+            [InputMedia::Video(
+                InputMediaVideo::new(InputFile::file_id("17"))
+                    .thumb(InputFile::file("./media/example.gif")),
+            )],
+        );
+        let err = to_form_ref(&group, true).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FileValidationError::WrongType {
+                method: "SendMediaGroup",
+                field: "thumb",
+                found: Some("GIF"),
+                ..
+            })
+        ));
+
+        // TGS and WEBM thumbnails of sticker sets are limited to 32 KB, PNG ones to 128
+        // KB
+        let mut tgs = vec![0; 40 << 10];
+        tgs[..3].copy_from_slice(&[0x1F, 0x8B, 0x08]);
+        let thumb =
+            payloads::SetStickerSetThumb::new("name", UserId(0)).thumb(InputFile::memory(tgs));
+        let err = to_form_ref(&thumb, true).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FileValidationError::TooBig {
+                field: "thumb",
+                limit: 32768,
+                ..
+            })
+        ));
+
+        let mut png = include_bytes!("../../media/logo.png").to_vec();
+        png.resize(100 << 10, 0);
+        let thumb =
+            payloads::SetStickerSetThumb::new("name", UserId(0)).thumb(InputFile::memory(png));
+        to_form_ref(&thumb, true).await.unwrap();
+
+        let sticker = payloads::AddStickerToSet::new(
+            UserId(0),
+            "name",
+            InputSticker::Tgs(InputFile::file("./media/logo.png")),
+            "✈️⚙️",
+        );
+        let err = to_form_ref(&sticker, true).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(FileValidationError::WrongType {
+                field: "tgs_sticker",
+                ..
+            })
+        ));
+    }
+
+    async fn send(server: &MockServer, form: Form) -> reqwest::Result<reqwest::Response> {
+        reqwest::Client::new()
+            .post(server.url().clone())
+            .multipart(form)
+            .send()
+            .await
     }
 
     fn entities() -> impl Iterator<Item = MessageEntity> {
@@ -0,0 +1,144 @@
+use serde::{
+    ser::{self, Impossible, SerializeMap, SerializeStruct},
+    Serialize, Serializer,
+};
+
+use crate::serde_multipart::unserializers::{StringUnserializer, UnserializerError};
+
+/// Unserializes a struct (or a map, for `#[serde(flatten)]`) into the text
+/// parts of `multipart/form-data` it should be encoded as.
+///
+/// - Strings, numbers, booleans and unit variants are encoded as text (see
+///   [`StringUnserializer`])
+/// - `None`s are skipped
+/// - Everything else (structs, sequences, etc) is encoded as JSON
+pub(crate) struct FieldsUnserializer;
+
+/// State of [`FieldsUnserializer`] while unserializing a struct or a map.
+pub(crate) struct Fields {
+    fields: Vec<(String, String)>,
+    key: Option<String>,
+}
+
+impl Fields {
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        name: String,
+        value: &T,
+    ) -> Result<(), UnserializerError> {
+        let text = match value.serialize(StringUnserializer) {
+            Ok(Some(text)) => text,
+            Ok(None) => return Ok(()),
+            Err(UnserializerError::UnsupportedType { .. }) => {
+                serde_json::to_string(value).map_err(ser::Error::custom)?
+            }
+            Err(err) => return Err(err),
+        };
+
+        self.fields.push((name, text));
+        Ok(())
+    }
+}
+
+impl Serializer for FieldsUnserializer {
+    type Ok = Vec<(String, String)>;
+    type Error = UnserializerError;
+
+    type SerializeSeq = Impossible<Self::Ok, UnserializerError>;
+    type SerializeTuple = Impossible<Self::Ok, UnserializerError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, UnserializerError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, UnserializerError>;
+    type SerializeMap = Fields;
+    type SerializeStruct = Fields;
+    type SerializeStructVariant = Impossible<Self::Ok, UnserializerError>;
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(Fields {
+            fields: Vec::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(None)
+    }
+
+    forward_to_unsuported_ty! {
+        supported: "struct, map";
+        simple {
+            serialize_bool  bool
+            serialize_i8    i8
+            serialize_i16   i16
+            serialize_i32   i32
+            serialize_i64   i64
+            serialize_u8    u8
+            serialize_u16   u16
+            serialize_u32   u32
+            serialize_u64   u64
+            serialize_f32   f32
+            serialize_f64   f64
+            serialize_bytes &[u8]
+            serialize_char  char
+            serialize_str   &str
+        }
+        unit {
+            serialize_none "None"
+            serialize_unit "unit"
+        }
+        compound {
+            serialize_some<T: ?Sized + Serialize>(_: &T) -> Self::Ok => "Some(_)"
+            serialize_unit_struct(_: &'static str) -> Self::Ok => "unit struct"
+            serialize_unit_variant(_: &'static str, _: u32, _: &'static str) -> Self::Ok => "unit variant"
+            serialize_newtype_struct<T: ?Sized + Serialize>(_: &'static str, _: &T) -> Self::Ok => "newtype struct"
+            serialize_newtype_variant<T: ?Sized + Serialize>(_: &'static str, _: u32, _: &'static str, _: &T) -> Self::Ok => "newtype variant"
+            serialize_seq(_: Option<usize>) -> Self::SerializeSeq => "sequence"
+            serialize_tuple(_: usize) -> Self::SerializeTuple => "tuple"
+            serialize_tuple_struct(_: &'static str, _: usize) -> Self::SerializeTupleStruct => "tuple struct"
+            serialize_tuple_variant(_: &'static str, _: u32, _: &'static str, _: usize) -> Self::SerializeTupleVariant => "tuple variant"
+            serialize_struct_variant(_: &'static str, _: u32, _: &'static str, _: usize) -> Self::SerializeStructVariant => "struct variant"
+        }
+    }
+}
+
+impl SerializeStruct for Fields {
+    type Ok = Vec<(String, String)>;
+    type Error = UnserializerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.push(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.fields)
+    }
+}
+
+impl SerializeMap for Fields {
+    type Ok = Vec<(String, String)>;
+    type Error = UnserializerError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.key = key.serialize(StringUnserializer)?;
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .expect("`serialize_value` called before `serialize_key`");
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.fields)
+    }
+}
@@ -0,0 +1,119 @@
+use serde::{ser::Impossible, Serialize, Serializer};
+
+use crate::serde_multipart::unserializers::UnserializerError;
+
+/// Unserializes a "simple" value (a string, a number, a boolean or a unit
+/// variant) into its text representation, `None`s are unserialized into
+/// `None`.
+pub(crate) struct StringUnserializer;
+
+impl Serializer for StringUnserializer {
+    type Ok = Option<String>;
+    type Error = UnserializerError;
+
+    type SerializeSeq = Impossible<Option<String>, UnserializerError>;
+    type SerializeTuple = Impossible<Option<String>, UnserializerError>;
+    type SerializeTupleStruct = Impossible<Option<String>, UnserializerError>;
+    type SerializeTupleVariant = Impossible<Option<String>, UnserializerError>;
+    type SerializeMap = Impossible<Option<String>, UnserializerError>;
+    type SerializeStruct = Impossible<Option<String>, UnserializerError>;
+    type SerializeStructVariant = Impossible<Option<String>, UnserializerError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(v.to_owned()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    forward_to_unsuported_ty! {
+        supported: "string, number, bool, unit variant";
+        simple {
+            serialize_bytes &[u8]
+        }
+        unit {
+            serialize_unit "unit"
+        }
+        compound {
+            serialize_unit_struct(_: &'static str) -> Self::Ok => "unit struct"
+            serialize_newtype_variant<T: ?Sized + Serialize>(_: &'static str, _: u32, _: &'static str, _: &T) -> Self::Ok => "newtype variant"
+            serialize_seq(_: Option<usize>) -> Self::SerializeSeq => "sequence"
+            serialize_tuple(_: usize) -> Self::SerializeTuple => "tuple"
+            serialize_tuple_struct(_: &'static str, _: usize) -> Self::SerializeTupleStruct => "tuple struct"
+            serialize_tuple_variant(_: &'static str, _: u32, _: &'static str, _: usize) -> Self::SerializeTupleVariant => "tuple variant"
+            serialize_map(_: Option<usize>) -> Self::SerializeMap => "map"
+            serialize_struct(_: &'static str, _: usize) -> Self::SerializeStruct => "struct"
+            serialize_struct_variant(_: &'static str, _: u32, _: &'static str, _: usize) -> Self::SerializeStructVariant => "struct variant"
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Validation of files against the requirements documented by Telegram (see
+//! [`Bot::validate_files`]).
+//!
+//! [`Bot::validate_files`]: crate::Bot::validate_files
+
+use std::io;
+
+use tokio::io::AsyncReadExt;
+
+use crate::{errors::FileValidationError, serde_multipart::error::Error, types::InputFile};
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+
+/// Maximum size of a file that can be uploaded via `multipart/form-data`.
+const UPLOAD_LIMIT: u64 = 50 * MB;
+
+/// Number of the first bytes of a file that are used to detect its type.
+const HEADER_LEN: usize = 64;
+
+/// Checks that `file` from `field` of `method` satisfies Telegram's
+/// requirements.
+///
+/// Returns MIME type of the file, if it could be detected.
+pub(crate) async fn validate(
+    method: &'static str,
+    field: &'static str,
+    file: &InputFile,
+) -> Result<Option<&'static str>, Error> {
+    let (size, header) = match inspect(file).await? {
+        Some(inspected) => inspected,
+        // Nothing is known about the file without consuming it (e.g. it's an `AsyncRead`)
+        None => return Ok(None),
+    };
+
+    let rule = Rule::of(method, field);
+
+    if size > rule.limit {
+        let err = FileValidationError::TooBig {
+            method,
+            field,
+            size,
+            limit: rule.limit,
+        };
+        return Err(err.into());
+    }
+
+    // We can't check the type of streams, since we don't have their first bytes
+    let header = match header {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    let ty = FileType::detect(&header);
+
+    // Some fields have lower limits for some of the allowed types
+    let type_limit = rule
+        .type_limits
+        .iter()
+        .find(|&&(limit_ty, _)| Some(limit_ty) == ty)
+        .map(|&(_, limit)| limit);
+
+    if let Some(limit) = type_limit.filter(|&limit| size > limit) {
+        let err = FileValidationError::TooBig {
+            method,
+            field,
+            size,
+            limit,
+        };
+        return Err(err.into());
+    }
+
+    if !rule.types.is_empty() && !ty.map_or(false, |ty| rule.types.contains(&ty)) {
+        let err = FileValidationError::WrongType {
+            method,
+            field,
+            expected: rule.expected,
+            found: ty.map(FileType::name),
+        };
+        return Err(err.into());
+    }
+
+    Ok(ty.map(FileType::mime))
+}
+
+/// Returns the size and the first bytes (if available) of a file, or `None` if
+/// they can't be known without consuming the file.
+async fn inspect(file: &InputFile) -> io::Result<Option<(u64, Option<Vec<u8>>)>> {
+    if let Some(path) = file.path() {
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        (&mut file)
+            .take(HEADER_LEN as u64)
+            .read_to_end(&mut header)
+            .await?;
+
+        return Ok(Some((size, Some(header))));
+    }
+
+    if let Some(data) = file.bytes() {
+        let header = data[..data.len().min(HEADER_LEN)].to_vec();
+        return Ok(Some((data.len() as u64, Some(header))));
+    }
+
+    Ok(file.stream_length().map(|length| (length, None)))
+}
+
+/// Requirements for files in a field.
+struct Rule {
+    /// Maximum size of the file in bytes.
+    limit: u64,
+    /// Lower maximum sizes of files of some types (if they are detected).
+    type_limits: &'static [(FileType, u64)],
+    /// Allowed file types, empty if any type is allowed.
+    types: &'static [FileType],
+    /// Human readable description of `types`.
+    expected: &'static str,
+}
+
+impl Rule {
+    fn of(method: &str, field: &str) -> Self {
+        use FileType::*;
+
+        let type_limits: &[_] = match (method, field) {
+            ("SetStickerSetThumb", "thumb") => &[(Gzip, 32 * KB), (Webm, 32 * KB)],
+            _ => &[],
+        };
+
+        let (limit, types, expected): (_, &[_], _) = match (method, field) {
+            ("SetStickerSetThumb", "thumb") => (128 * KB, &[Png, Gzip, Webm], "PNG, TGS or WEBM"),
+            (_, "thumb") => (200 * KB, &[Jpeg], "JPEG"),
+            ("SetChatPhoto", "photo") => (UPLOAD_LIMIT, &[], ""),
+            (_, "photo") => (10 * MB, &[], ""),
+            (_, "voice") => (UPLOAD_LIMIT, &[OggOpus], "OGG/Opus"),
+            (_, "video_note") => (UPLOAD_LIMIT, &[Mp4], "MP4"),
+            (_, "png_sticker") => (512 * KB, &[Png], "PNG"),
+            (_, "tgs_sticker") => (64 * KB, &[Gzip], "TGS"),
+            (_, "webm_sticker") => (256 * KB, &[Webm], "WEBM"),
+            _ => (UPLOAD_LIMIT, &[], ""),
+        };
+
+        Self {
+            limit,
+            type_limits,
+            types,
+            expected,
+        }
+    }
+}
+
+/// Type of a file, detected by its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Pdf,
+    Zip,
+    /// Gzip archive, TGS stickers are gzipped lottie animations.
+    Gzip,
+    /// OGG container with OPUS audio.
+    OggOpus,
+    /// OGG container with something other than OPUS audio.
+    Ogg,
+    Mp3,
+    Mp4,
+    Webm,
+}
+
+impl FileType {
+    fn detect(header: &[u8]) -> Option<Self> {
+        use FileType::*;
+
+        let ty = match header {
+            [0xFF, 0xD8, 0xFF, ..] => Jpeg,
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Png,
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Gif,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Webp,
+            [b'%', b'P', b'D', b'F', b'-', ..] => Pdf,
+            [b'P', b'K', 0x03, 0x04, ..] => Zip,
+            [0x1F, 0x8B, ..] => Gzip,
+            [b'O', b'g', b'g', b'S', ..] if is_opus(header) => OggOpus,
+            [b'O', b'g', b'g', b'S', ..] => Ogg,
+            [b'I', b'D', b'3', ..] | [0xFF, 0xFB | 0xF3 | 0xF2, ..] => Mp3,
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Mp4,
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => Webm,
+            _ => return None,
+        };
+
+        Some(ty)
+    }
+
+    fn mime(self) -> &'static str {
+        use FileType::*;
+
+        match self {
+            Jpeg => "image/jpeg",
+            Png => "image/png",
+            Gif => "image/gif",
+            Webp => "image/webp",
+            Pdf => "application/pdf",
+            Zip => "application/zip",
+            Gzip => "application/gzip",
+            OggOpus | Ogg => "audio/ogg",
+            Mp3 => "audio/mpeg",
+            Mp4 => "video/mp4",
+            Webm => "video/webm",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        use FileType::*;
+
+        match self {
+            Jpeg => "JPEG",
+            Png => "PNG",
+            Gif => "GIF",
+            Webp => "WEBP",
+            Pdf => "PDF",
+            Zip => "ZIP",
+            Gzip => "GZIP",
+            OggOpus => "OGG/Opus",
+            Ogg => "OGG",
+            Mp3 => "MP3",
+            Mp4 => "MP4",
+            Webm => "WEBM",
+        }
+    }
+}
+
+/// Returns `true` if the first packet of the OGG page in `header` is an OPUS
+/// identification header.
+fn is_opus(header: &[u8]) -> bool {
+    // The page header is 27 bytes long and is followed by a segment table, the
+    // last byte of the page header is the length of the segment table.
+    let start = match header.get(26) {
+        Some(&segments) => 27 + usize::from(segments),
+        None => return false,
+    };
+
+    header.get(start..start + 8) == Some(&b"OpusHead"[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileType;
+
+    #[test]
+    fn detect() {
+        let ogg_opus = {
+            let mut header = b"OggS".to_vec();
+            header.resize(26, 0);
+            header.extend([1, 19]);
+            header.extend(b"OpusHead");
+            header
+        };
+
+        let detect = FileType::detect;
+
+        assert_eq!(
+            detect(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10]),
+            Some(FileType::Jpeg)
+        );
+        assert_eq!(
+            detect(include_bytes!("../../media/logo.png")),
+            Some(FileType::Png)
+        );
+        assert_eq!(
+            detect(include_bytes!("../../media/example.gif")),
+            Some(FileType::Gif)
+        );
+        assert_eq!(detect(b"RIFF\0\0\0\0WEBPVP8 "), Some(FileType::Webp));
+        assert_eq!(detect(b"%PDF-1.4"), Some(FileType::Pdf));
+        assert_eq!(detect(&[0x1F, 0x8B, 0x08]), Some(FileType::Gzip));
+        assert_eq!(detect(&ogg_opus), Some(FileType::OggOpus));
+        assert_eq!(detect(&ogg_opus[..30]), Some(FileType::Ogg));
+        assert_eq!(detect(b"ID3\x03\0"), Some(FileType::Mp3));
+        assert_eq!(detect(b"\0\0\0\x20ftypisom"), Some(FileType::Mp4));
+        assert_eq!(
+            detect(&[0x1A, 0x45, 0xDF, 0xA3, 0x9F]),
+            Some(FileType::Webm)
+        );
+        assert_eq!(detect(b"Hello world!"), None);
+        assert_eq!(detect(b""), None);
+    }
+}
@@ -4,6 +4,7 @@ pub use allowed_update::*;
 pub use animation::*;
 pub use audio::*;
 pub use bot_command::*;
+pub use bot_command_ref::*;
 pub use bot_command_scope::*;
 pub use callback_game::*;
 pub use callback_query::*;
@@ -70,6 +71,7 @@ pub use message::*;
 pub use message_auto_delete_timer_changed::*;
 pub use message_entity::*;
 pub use message_id::*;
+pub use message_link::*;
 pub use order_info::*;
 pub use parse_mode::*;
 pub use passport_data::*;
@@ -115,6 +117,7 @@ mod allowed_update;
 mod animation;
 mod audio;
 mod bot_command;
+mod bot_command_ref;
 mod bot_command_scope;
 mod callback_game;
 mod callback_query;
@@ -157,6 +160,7 @@ mod message;
 mod message_auto_delete_timer_changed;
 mod message_entity;
 mod message_id;
+mod message_link;
 mod order_info;
 mod parse_mode;
 mod photo_size;
@@ -239,6 +243,9 @@ pub use chat_id::*;
 pub use recipient::*;
 pub use user_id::*;
 
+pub mod deep_link;
+pub mod formatting;
+
 pub(crate) mod serde_opt_date_from_unix_timestamp {
     use chrono::{DateTime, NaiveDateTime, Utc};
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
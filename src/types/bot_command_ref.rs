@@ -0,0 +1,127 @@
+use crate::types::{Me, MessageEntityKind, MessageEntityRef};
+
+/// A bot command in a message, e.g. `/start@my_bot arguments`.
+///
+/// You can get a `BotCommandRef` by calling [`Message::bot_command`] or
+/// [`BotCommandRef::from_entity`].
+///
+/// [`Message::bot_command`]: crate::types::Message::bot_command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BotCommandRef<'a> {
+    command: &'a str,
+    bot_username: Option<&'a str>,
+    args: &'a str,
+}
+
+impl<'a> BotCommandRef<'a> {
+    /// Creates a bot command from a [`MessageEntityKind::BotCommand`] entity,
+    /// the arguments are the rest of the text after the entity.
+    ///
+    /// Returns `None` if the entity is not a bot command.
+    #[must_use]
+    pub fn from_entity(entity: &MessageEntityRef<'a>) -> Option<Self> {
+        if *entity.kind() != MessageEntityKind::BotCommand {
+            return None;
+        }
+
+        let command = entity.text().strip_prefix('/').unwrap_or(entity.text());
+        let (command, bot_username) = match command.split_once('@') {
+            Some((command, bot_username)) => (command, Some(bot_username)),
+            None => (command, None),
+        };
+        let args = entity.message_text()[entity.end()..].trim();
+
+        Some(Self {
+            command,
+            bot_username,
+            args,
+        })
+    }
+
+    /// Returns the name of the command, without the leading `/` and the bot
+    /// username (e.g. `start` for `/start@my_bot`).
+    #[must_use]
+    pub fn command(&self) -> &'a str {
+        self.command
+    }
+
+    /// Returns the username of the bot which the command is addressed to, if
+    /// it's specified (e.g. `my_bot` for `/start@my_bot`).
+    #[must_use]
+    pub fn bot_username(&self) -> Option<&'a str> {
+        self.bot_username
+    }
+
+    /// Returns the text after the command, without leading and trailing
+    /// whitespace.
+    #[must_use]
+    pub fn args(&self) -> &'a str {
+        self.args
+    }
+
+    /// Returns `true` if the command is addressed to the bot, i.e. the bot
+    /// username is either not specified or equal to the username of the bot.
+    ///
+    /// Note that in groups commands without bot username are sent to all bots.
+    #[must_use]
+    pub fn is_addressed_to(&self, me: &Me) -> bool {
+        self.bot_username.map_or(true, |username| {
+            username.eq_ignore_ascii_case(me.username())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{
+        BotCommandRef, Me, MessageEntity, MessageEntityKind, MessageEntityRef, User, UserId,
+    };
+
+    fn me() -> Me {
+        Me {
+            user: User {
+                id: UserId(42),
+                is_bot: true,
+                first_name: "First".to_owned(),
+                last_name: None,
+                username: Some("SomethingBot".to_owned()),
+                language_code: None,
+                is_premium: false,
+                added_to_attachment_menu: false,
+            },
+            can_join_groups: false,
+            can_read_all_group_messages: false,
+            supports_inline_queries: false,
+        }
+    }
+
+    #[test]
+    fn from_entity() {
+        let text = "/start@somethingbot  a b ";
+        let entities = [MessageEntity::new(MessageEntityKind::BotCommand, 0, 19)];
+        let entity = &MessageEntityRef::parse(text, &entities)[0];
+        let command = BotCommandRef::from_entity(entity).unwrap();
+
+        assert_eq!(command.command(), "start");
+        assert_eq!(command.bot_username(), Some("somethingbot"));
+        assert_eq!(command.args(), "a b");
+        assert!(command.is_addressed_to(&me()));
+
+        let text = "/help@other_bot";
+        let entities = [MessageEntity::new(MessageEntityKind::BotCommand, 0, 15)];
+        let entity = &MessageEntityRef::parse(text, &entities)[0];
+        let command = BotCommandRef::from_entity(entity).unwrap();
+
+        assert_eq!(command.command(), "help");
+        assert_eq!(command.args(), "");
+        assert!(!command.is_addressed_to(&me()));
+    }
+
+    #[test]
+    fn not_a_command() {
+        let entities = [MessageEntity::bold(0, 6)];
+        let entity = &MessageEntityRef::parse("/start", &entities)[0];
+
+        assert_eq!(BotCommandRef::from_entity(entity), None);
+    }
+}
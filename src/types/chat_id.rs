@@ -75,7 +75,6 @@ impl From<UserId> for ChatId {
 
 impl BareChatId {
     /// Converts bare chat id back to normal bot API [`ChatId`].
-    #[allow(unused)]
     pub(crate) fn to_bot_api(self) -> ChatId {
         use BareChatId::*;
 
@@ -95,6 +94,9 @@ const MAX_MARKED_CHAT_ID: i64 = MIN_USER_ID - 1;
 const MIN_USER_ID: i64 = 0;
 const MAX_USER_ID: i64 = (1 << 40) - 1;
 
+/// The largest bare id of a channel, which can be converted to a bot API id.
+pub(crate) const MAX_BARE_CHANNEL_ID: u64 = (MAX_MARKED_CHANNEL_ID - MIN_MARKED_CHANNEL_ID) as u64;
+
 #[cfg(test)]
 mod tests {
     use serde::{Deserialize, Serialize};
@@ -0,0 +1,479 @@
+//! Utilities for [deep links] to bots and invite links to chats.
+//!
+//! Deep links (e.g. `https://t.me/my_bot?start=<param>`) pass a start
+//! parameter to a bot, which receives it as `/start <param>`. Start parameters
+//! are at most [`START_PARAM_MAX_LEN`] characters long and can only contain
+//! characters of the base64url alphabet (`A-Z`, `a-z`, `0-9`, `_` and `-`), so
+//! arbitrary data has to be [`encode`]d into them and [`decode`]d back.
+//!
+//! Links are built by [`start_url`], [`start_group_url`] and
+//! [`start_attach_url`] (or by the same methods of [`Me`] and [`User`]), while
+//! [`start_param`] extracts the parameter from a received `/start` command.
+//!
+//! [`InviteLink`] parses invite links to chats (`t.me/+<hash>` and
+//! `t.me/joinchat/<hash>`).
+//!
+//! ## Examples
+//!
+//! ```
+//! use teloxide_core::types::deep_link;
+//!
+//! let param = deep_link::encode(&42u64.to_le_bytes()).unwrap();
+//! let url = deep_link::start_url("my_bot", &param).unwrap();
+//! assert_eq!(url.as_str(), "https://t.me/my_bot?start=KgAAAAAAAAA");
+//!
+//! // `/start KgAAAAAAAAA` is received by the bot
+//! let bytes = deep_link::decode("KgAAAAAAAAA").unwrap();
+//! assert_eq!(bytes, 42u64.to_le_bytes());
+//! ```
+//!
+//! [deep links]: https://core.telegram.org/bots/features#deep-linking
+//! [`Me`]: crate::types::Me
+//! [`User`]: crate::types::User
+
+use url::Url;
+
+use crate::types::{
+    formatting,
+    message_link::{is_username, TME_HOSTS},
+    BotCommandRef, ChatInviteLink,
+};
+
+/// Maximum length of a start parameter, in characters.
+pub const START_PARAM_MAX_LEN: usize = 64;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Error returned when a start parameter (or a deep link) is invalid.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum StartParamError {
+    /// The start parameter is longer than [`START_PARAM_MAX_LEN`].
+    #[error(
+        "The start parameter is {len} characters long, but at most {} characters are allowed",
+        START_PARAM_MAX_LEN
+    )]
+    TooLong { len: usize },
+
+    /// The start parameter contains a character which is not in the base64url
+    /// alphabet.
+    #[error("Character {c:?} at byte offset {offset} is not allowed in start parameters")]
+    InvalidCharacter { c: char, offset: usize },
+
+    /// The start parameter has a length which no encoded data has (i.e. `4n +
+    /// 1` characters).
+    #[error("The start parameter is not valid base64url-encoded data")]
+    InvalidLength,
+
+    /// The last character of the start parameter has non-zero bits which
+    /// aren't part of the decoded bytes, i.e. it wasn't produced by [`encode`].
+    #[error("The start parameter has non-zero trailing bits")]
+    NonZeroTrailingBits,
+
+    /// The username of the bot is not a valid username (usernames are
+    /// `/[a-zA-Z][a-zA-Z0-9_]{4,31}/`).
+    #[error("{username:?} is not a valid bot username")]
+    InvalidBotUsername { username: String },
+}
+
+/// Checks that `param` can be used as a start parameter, i.e. that it's at most
+/// [`START_PARAM_MAX_LEN`] characters long and contains only `A-Z`, `a-z`,
+/// `0-9`, `_` and `-`.
+pub fn validate(param: &str) -> Result<(), StartParamError> {
+    if let Some((offset, c)) = param.char_indices().find(|&(_, c)| value(c).is_none()) {
+        return Err(StartParamError::InvalidCharacter { c, offset });
+    }
+
+    if param.len() > START_PARAM_MAX_LEN {
+        return Err(StartParamError::TooLong { len: param.len() });
+    }
+
+    Ok(())
+}
+
+/// Encodes `bytes` into a start parameter using unpadded base64url encoding.
+///
+/// At most 48 bytes fit into a start parameter, [`StartParamError::TooLong`] is
+/// returned for longer data.
+pub fn encode(bytes: &[u8]) -> Result<String, StartParamError> {
+    let len = (bytes.len() * 4 + 2) / 3;
+    if len > START_PARAM_MAX_LEN {
+        return Err(StartParamError::TooLong { len });
+    }
+
+    let mut param = String::with_capacity(len);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (u32::from(b) << (16 - 8 * i)));
+
+        for i in 0..=chunk.len() {
+            param.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3F) as usize] as char);
+        }
+    }
+
+    Ok(param)
+}
+
+/// Decodes a start parameter produced by [`encode`].
+///
+/// Every byte string has only one encoding, so parameters which differ only in
+/// the unused bits of the last character (e.g. `AB` and `AC`) are rejected.
+pub fn decode(param: &str) -> Result<Vec<u8>, StartParamError> {
+    validate(param)?;
+
+    if param.len() % 4 == 1 {
+        return Err(StartParamError::InvalidLength);
+    }
+
+    let mut bytes = Vec::with_capacity(param.len() * 3 / 4);
+    let mut buf = 0u32;
+    let mut bits = 0;
+    for c in param.chars() {
+        // UNWRAP: characters are checked by `validate`
+        buf = (buf << 6) | u32::from(value(c).unwrap());
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buf >> bits) as u8);
+        }
+    }
+
+    if buf & ((1 << bits) - 1) != 0 {
+        return Err(StartParamError::NonZeroTrailingBits);
+    }
+
+    Ok(bytes)
+}
+
+fn value(c: char) -> Option<u8> {
+    let value = match c {
+        'A'..='Z' => c as u8 - b'A',
+        'a'..='z' => c as u8 - b'a' + 26,
+        '0'..='9' => c as u8 - b'0' + 52,
+        '-' => 62,
+        '_' => 63,
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Returns a link which opens a private chat with the bot and sends it
+/// `/start <param>` when the user presses the "Start" button.
+pub fn start_url(bot_username: &str, param: &str) -> Result<Url, StartParamError> {
+    bot_url(bot_username, "start", Some(param))
+}
+
+/// Returns a link which prompts the user to add the bot to a group, the bot
+/// receives `/start <param>` (or `/start@bot_username <param>`) in the group.
+pub fn start_group_url(bot_username: &str, param: &str) -> Result<Url, StartParamError> {
+    bot_url(bot_username, "startgroup", Some(param))
+}
+
+/// Returns a link which adds the bot to the attachment menu of the user (if
+/// the bot supports it) and opens the bot's web app with `start_param` in
+/// its init data.
+pub fn start_attach_url(bot_username: &str, param: Option<&str>) -> Result<Url, StartParamError> {
+    bot_url(bot_username, "startattach", param)
+}
+
+fn bot_url(bot_username: &str, key: &str, param: Option<&str>) -> Result<Url, StartParamError> {
+    if !is_username(bot_username) {
+        return Err(StartParamError::InvalidBotUsername {
+            username: bot_username.to_owned(),
+        });
+    }
+
+    let query = match param {
+        Some(param) => {
+            validate(param)?;
+            format!("{}={}", key, param)
+        }
+        None => key.to_owned(),
+    };
+
+    // UNWRAP:
+    //
+    // The `url` produced by formatting is correct since both the username and
+    // the parameter are validated.
+    Ok(Url::parse(&format!("https://t.me/{}?{}", bot_username, query)).unwrap())
+}
+
+/// Returns the start parameter of a `/start <param>` command, if it's a
+/// `/start` command with a valid parameter.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{deep_link, Message};
+///
+/// fn handle(message: &Message) -> Option<Vec<u8>> {
+///     let command = message.bot_command()?;
+///     let param = deep_link::start_param(&command)?;
+///
+///     deep_link::decode(param).ok()
+/// }
+/// ```
+#[must_use]
+pub fn start_param<'a>(command: &BotCommandRef<'a>) -> Option<&'a str> {
+    let param = command.args();
+
+    (command.command() == "start" && !param.is_empty() && validate(param).is_ok()).then(|| param)
+}
+
+/// An invite link to a chat, e.g. `https://t.me/+AbCdEf0123456789`.
+///
+/// Invite links are returned by methods such as [`CreateChatInviteLink`] in
+/// [`ChatInviteLink::invite_link`] and are accepted by methods such as
+/// [`RevokeChatInviteLink`] in the `https://t.me/+<hash>` form returned by
+/// [`InviteLink::url`].
+///
+/// [`CreateChatInviteLink`]: crate::payloads::CreateChatInviteLink
+/// [`RevokeChatInviteLink`]: crate::payloads::RevokeChatInviteLink
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InviteLink {
+    hash: String,
+}
+
+impl InviteLink {
+    /// Parses an invite link, the scheme may be omitted.
+    ///
+    /// Links in the `t.me/+<hash>`, `t.me/joinchat/<hash>` and
+    /// `tg://join?invite=<hash>` forms are supported.
+    ///
+    /// Returns `None` if `link` is not an invite link.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use teloxide_core::types::deep_link::InviteLink;
+    ///
+    /// let link = InviteLink::parse("t.me/joinchat/AbCdEf0123456789").unwrap();
+    /// assert_eq!(link.hash(), "AbCdEf0123456789");
+    /// assert_eq!(link.url().as_str(), "https://t.me/+AbCdEf0123456789");
+    /// ```
+    #[must_use]
+    pub fn parse(link: &str) -> Option<Self> {
+        let url = formatting::parse_url(link)?;
+
+        let hash = match (url.scheme(), url.host_str()?) {
+            ("http" | "https", host) if TME_HOSTS.contains(&host) => {
+                let segments: Vec<_> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+                match segments[..] {
+                    [hash] => hash.strip_prefix('+')?.to_owned(),
+                    ["joinchat", hash] => hash.to_owned(),
+                    _ => return None,
+                }
+            }
+            ("tg", "join") => url
+                .query_pairs()
+                .find(|(key, _)| key == "invite")?
+                .1
+                .into_owned(),
+            _ => return None,
+        };
+
+        let valid = !hash.is_empty()
+            && hash
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        valid.then(|| Self { hash })
+    }
+
+    /// Returns the hash of the link, which identifies the chat and the link.
+    #[must_use]
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Returns the link in the `https://t.me/+<hash>` form, which is used by
+    /// the Bot API.
+    #[must_use]
+    pub fn url(&self) -> Url {
+        // UNWRAP: the hash is validated in `parse`
+        Url::parse(&format!("https://t.me/+{}", self.hash)).unwrap()
+    }
+
+    /// Returns `true` if `link` is the same invite link.
+    ///
+    /// Links created by other administrators have the end of the hash replaced
+    /// with “…”, in which case only the rest of the hash is compared.
+    #[must_use]
+    pub fn matches(&self, link: &ChatInviteLink) -> bool {
+        let other = match Self::parse(link.invite_link.trim_end_matches('…')) {
+            Some(other) => other,
+            None => return false,
+        };
+
+        if link.invite_link.ends_with('…') {
+            self.hash.starts_with(&other.hash)
+        } else {
+            self.hash == other.hash
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode, encode, start_attach_url, start_param, start_url, validate, InviteLink,
+        StartParamError,
+    };
+    use crate::types::{
+        BotCommandRef, ChatInviteLink, MessageEntity, MessageEntityKind, MessageEntityRef, User,
+        UserId,
+    };
+
+    #[test]
+    fn encoding() {
+        for len in 0..=48 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 255) as u8).collect();
+            let param = encode(&bytes).unwrap();
+
+            assert!(validate(&param).is_ok(), "{}", param);
+            assert_eq!(decode(&param).unwrap(), bytes);
+        }
+
+        assert_eq!(encode(b"\xFB\xFF").unwrap(), "-_8");
+        assert_eq!(encode(&[0; 49]), Err(StartParamError::TooLong { len: 66 }));
+    }
+
+    #[test]
+    fn invalid_params() {
+        assert_eq!(
+            decode("abc="),
+            Err(StartParamError::InvalidCharacter { c: '=', offset: 3 })
+        );
+        assert_eq!(decode("abcde"), Err(StartParamError::InvalidLength));
+        assert_eq!(decode("AA"), Ok(vec![0]));
+        assert_eq!(decode("AB"), Err(StartParamError::NonZeroTrailingBits));
+        assert_eq!(decode("AC"), Err(StartParamError::NonZeroTrailingBits));
+        assert_eq!(decode("AAB"), Err(StartParamError::NonZeroTrailingBits));
+        assert_eq!(
+            validate(&"a".repeat(65)),
+            Err(StartParamError::TooLong { len: 65 })
+        );
+        assert_eq!(
+            StartParamError::TooLong { len: 65 }.to_string(),
+            "The start parameter is 65 characters long, but at most 64 characters are allowed"
+        );
+    }
+
+    #[test]
+    fn bot_usernames() {
+        assert!(start_url("my_bot", "abc").is_ok());
+
+        for username in [
+            "",
+            "bot",
+            "my_bot?start=x",
+            "my/bot",
+            "1_bot",
+            &"a".repeat(33),
+        ] {
+            assert_eq!(
+                start_url(username, "abc"),
+                Err(StartParamError::InvalidBotUsername {
+                    username: username.to_owned()
+                }),
+            );
+        }
+    }
+
+    #[test]
+    fn start_params() {
+        fn param(text: &str) -> Option<String> {
+            let len = text.find(' ').unwrap_or(text.len());
+            let entities = [MessageEntity::new(MessageEntityKind::BotCommand, 0, len)];
+            let entity = &MessageEntityRef::parse(text, &entities)[0];
+
+            start_param(&BotCommandRef::from_entity(entity).unwrap()).map(str::to_owned)
+        }
+
+        assert_eq!(param("/start KgAAAAAAAAA").as_deref(), Some("KgAAAAAAAAA"));
+        assert_eq!(param("/start@my_bot abc").as_deref(), Some("abc"));
+        assert_eq!(param("/start"), None);
+        assert_eq!(param("/start a b"), None);
+        assert_eq!(param("/help abc"), None);
+    }
+
+    #[test]
+    fn attach_url() {
+        assert_eq!(
+            start_attach_url("my_bot", None).unwrap().as_str(),
+            "https://t.me/my_bot?startattach"
+        );
+        assert_eq!(
+            start_attach_url("my_bot", Some("a b")),
+            Err(StartParamError::InvalidCharacter { c: ' ', offset: 1 })
+        );
+    }
+
+    #[test]
+    fn invite_links() {
+        for link in [
+            "https://t.me/+AbC-_0",
+            "t.me/+AbC-_0",
+            "https://telegram.me/joinchat/AbC-_0/",
+            "https://www.t.me/+AbC-_0",
+            "https://www.telegram.me/joinchat/AbC-_0",
+            "tg://join?invite=AbC-_0",
+        ] {
+            assert_eq!(
+                InviteLink::parse(link).unwrap().hash(),
+                "AbC-_0",
+                "{}",
+                link
+            );
+        }
+
+        for link in [
+            "https://t.me/AbC",
+            "https://t.me/+",
+            "https://t.me/joinchat/",
+            "https://example.com/+AbC",
+            "https://t.me/+AbC/1",
+        ] {
+            assert_eq!(InviteLink::parse(link), None, "{}", link);
+        }
+    }
+
+    #[test]
+    fn invite_link_matches() {
+        fn chat_invite_link(invite_link: &str) -> ChatInviteLink {
+            ChatInviteLink {
+                invite_link: invite_link.to_owned(),
+                creator: User {
+                    id: UserId(1),
+                    is_bot: false,
+                    first_name: "First".to_owned(),
+                    last_name: None,
+                    username: None,
+                    language_code: None,
+                    is_premium: false,
+                    added_to_attachment_menu: false,
+                },
+                creates_join_request: false,
+                is_primary: false,
+                is_revoked: false,
+                name: None,
+                expire_date: None,
+                member_limit: None,
+                pending_join_request_count: None,
+            }
+        }
+
+        let link = InviteLink::parse("t.me/joinchat/AbCdEf0123456789").unwrap();
+
+        assert!(link.matches(&chat_invite_link("https://t.me/+AbCdEf0123456789")));
+        assert!(link.matches(&chat_invite_link("https://t.me/+AbCdEf…")));
+        assert!(!link.matches(&chat_invite_link("https://t.me/+AbCdEf")));
+        assert!(!link.matches(&chat_invite_link("https://t.me/+AbCdEg…")));
+        assert!(!link.matches(&chat_invite_link("https://t.me/+AbCdEf0123456789a")));
+        assert!(!link.matches(&chat_invite_link("https://example.com/+AbCdEf0123456789")));
+    }
+}
@@ -0,0 +1,30 @@
+//! Utilities for formatted text, i.e. text with [`MessageEntity`]s.
+//!
+//! Instead of using a [`ParseMode`] and escaping the text, messages can be
+//! sent as plain text alongside with entities (e.g. with
+//! [`SendMessageSetters::entities`]). [`Text`] allows to build such messages,
+//! while [`parse()`] converts text formatted with a [`ParseMode`] into plain
+//! text and entities locally and [`render()`] does the opposite. [`escape()`]
+//! allows to use arbitrary text in formatted text.
+//!
+//! Long texts can be [`split()`] into chunks which fit into a message (or a
+//! caption), preserving entities, while the [`utf16`] module converts offsets
+//! between UTF-16 code units used by Telegram and bytes.
+//!
+//! [`MessageEntity`]: crate::types::MessageEntity
+//! [`ParseMode`]: crate::types::ParseMode
+//! [`SendMessageSetters::entities`]: crate::payloads::SendMessageSetters::entities
+
+mod escape;
+mod parse;
+mod render;
+mod split;
+mod text;
+pub mod utf16;
+
+pub use escape::{escape, EscapeContext};
+pub(crate) use parse::parse_url;
+pub use parse::{parse, ParseError};
+pub use render::render;
+pub use split::{split, CAPTION_MAX_LEN, MESSAGE_MAX_LEN};
+pub use text::Text;
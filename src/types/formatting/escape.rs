@@ -0,0 +1,253 @@
+use crate::types::ParseMode;
+
+/// Context in which escaped text is used, since every [`ParseMode`] has
+/// different escaping rules for different parts of the markup.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EscapeContext {
+    /// Text outside of code, pre and link URLs (including text of links).
+    Text,
+
+    /// Text inside of code and pre entities.
+    Code,
+
+    /// URL of an inline link, i.e. the `(...)` part of a Markdown link or the
+    /// `href` attribute of an HTML link.
+    ///
+    /// This includes links which mention users (`tg://user?id=...`).
+    LinkUrl,
+}
+
+/// Escapes `s`, so that it's treated as plain text (or a plain URL) when used
+/// in the `context` of text formatted with the `mode`.
+///
+/// Escaping rules are documented on [`ParseMode`]. Notably, legacy
+/// [`ParseMode::Markdown`] doesn't allow escaping inside of entities, so:
+/// - In [`EscapeContext::Code`] the text is returned as is, it mustn't contain
+///   '`' characters (close the entity and reopen it after an escaped '`'
+///   instead).
+/// - In [`EscapeContext::LinkUrl`] ')' characters are percent-encoded.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{
+///     formatting::{escape, EscapeContext},
+///     ParseMode,
+/// };
+///
+/// let text = escape(ParseMode::MarkdownV2, EscapeContext::Text, "1 + 1 = 2.");
+/// assert_eq!(text, "1 \\+ 1 \\= 2\\.");
+///
+/// let code = escape(ParseMode::MarkdownV2, EscapeContext::Code, "a_b `c`");
+/// assert_eq!(code, "a_b \\`c\\`");
+///
+/// let text = escape(ParseMode::Html, EscapeContext::Text, "<b>bold</b> & co");
+/// assert_eq!(text, "&lt;b&gt;bold&lt;/b&gt; &amp; co");
+/// ```
+#[allow(deprecated)]
+#[must_use]
+pub fn escape(mode: ParseMode, context: EscapeContext, s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    match mode {
+        ParseMode::MarkdownV2 => {
+            let reserved: &[char] = match context {
+                EscapeContext::Text => &[
+                    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}',
+                    '.', '!', '\\',
+                ],
+                EscapeContext::Code => &['`', '\\'],
+                EscapeContext::LinkUrl => &[')', '\\'],
+            };
+
+            for c in s.chars() {
+                if reserved.contains(&c) {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        }
+        ParseMode::Html => {
+            for c in s.chars() {
+                match c {
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    '&' => out.push_str("&amp;"),
+                    // URLs are used in quoted attributes
+                    '"' if context == EscapeContext::LinkUrl => out.push_str("&quot;"),
+                    c => out.push(c),
+                }
+            }
+        }
+        ParseMode::Markdown => match context {
+            EscapeContext::Text => {
+                for c in s.chars() {
+                    if matches!(c, '_' | '*' | '`' | '[') {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+            }
+            EscapeContext::Code => out.push_str(s),
+            EscapeContext::LinkUrl => out.push_str(&s.replace(')', "%29")),
+        },
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(deprecated)]
+
+    use url::Url;
+
+    use super::{escape, EscapeContext};
+    use crate::types::{formatting::parse, MessageEntity, MessageEntityKind, ParseMode};
+
+    const MODES: [ParseMode; 3] = [ParseMode::MarkdownV2, ParseMode::Html, ParseMode::Markdown];
+
+    /// All printable ASCII characters, a newline and some non-ASCII ones.
+    fn all_characters() -> String {
+        (' '..='~').chain("\n🦀ы".chars()).collect()
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(
+            escape(
+                ParseMode::MarkdownV2,
+                EscapeContext::Text,
+                "_*[]()~`>#+-=|{}.!\\ a"
+            ),
+            "\\_\\*\\[\\]\\(\\)\\~\\`\\>\\#\\+\\-\\=\\|\\{\\}\\.\\!\\\\ a"
+        );
+        assert_eq!(
+            escape(
+                ParseMode::Html,
+                EscapeContext::Text,
+                "<a href=\"x\">&amp;</a>"
+            ),
+            "&lt;a href=\"x\"&gt;&amp;amp;&lt;/a&gt;"
+        );
+        assert_eq!(
+            escape(ParseMode::Markdown, EscapeContext::Text, "_*`[]()\\"),
+            "\\_\\*\\`\\[]()\\"
+        );
+
+        let text = all_characters();
+        for mode in MODES {
+            let escaped = escape(mode, EscapeContext::Text, &text);
+            assert_eq!(
+                parse(mode, &escaped).unwrap(),
+                (text.clone(), vec![]),
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            escape(ParseMode::MarkdownV2, EscapeContext::Code, "_*`\\.!"),
+            "_*\\`\\\\.!"
+        );
+        assert_eq!(
+            escape(ParseMode::Html, EscapeContext::Code, "<&>\""),
+            "&lt;&amp;&gt;\""
+        );
+        assert_eq!(
+            escape(ParseMode::Markdown, EscapeContext::Code, "_*[\\"),
+            "_*[\\"
+        );
+
+        let text = all_characters();
+        let code_len = text.encode_utf16().count();
+
+        let escaped = escape(ParseMode::MarkdownV2, EscapeContext::Code, &text);
+        assert_eq!(
+            parse(ParseMode::MarkdownV2, &format!("`{}`", escaped)).unwrap(),
+            (text.clone(), vec![MessageEntity::code(0, code_len)])
+        );
+
+        let escaped = escape(ParseMode::Html, EscapeContext::Code, &text);
+        assert_eq!(
+            parse(ParseMode::Html, &format!("<code>{}</code>", escaped)).unwrap(),
+            (text.clone(), vec![MessageEntity::code(0, code_len)])
+        );
+
+        // '`' can't be escaped inside of code in the legacy Markdown
+        let text = text.replace('`', "");
+        let escaped = escape(ParseMode::Markdown, EscapeContext::Code, &text);
+        assert_eq!(
+            parse(ParseMode::Markdown, &format!("`{}`", escaped)).unwrap(),
+            (text.clone(), vec![MessageEntity::code(0, code_len - 1)])
+        );
+    }
+
+    #[test]
+    fn link_url() {
+        let url = "https://example.com/(a)?b=\\\"&c=<d>#e";
+
+        assert_eq!(
+            escape(ParseMode::MarkdownV2, EscapeContext::LinkUrl, url),
+            "https://example.com/(a\\)?b=\\\\\"&c=<d>#e"
+        );
+        assert_eq!(
+            escape(ParseMode::Html, EscapeContext::LinkUrl, url),
+            "https://example.com/(a)?b=\\&quot;&amp;c=&lt;d&gt;#e"
+        );
+        assert_eq!(
+            escape(ParseMode::Markdown, EscapeContext::LinkUrl, url),
+            "https://example.com/(a%29?b=\\\"&c=<d>#e"
+        );
+
+        let url = format!("https://example.com/?q={}", all_characters());
+        for mode in MODES {
+            let escaped = escape(mode, EscapeContext::LinkUrl, &url);
+            let formatted = match mode {
+                ParseMode::Html => format!("<a href=\"{}\">link</a>", escaped),
+                _ => format!("[link]({})", escaped),
+            };
+
+            let (_, entities) = parse(mode, &formatted).unwrap();
+            let expected = match mode {
+                ParseMode::Markdown => url.replace(')', "%29"),
+                _ => url.clone(),
+            };
+            assert_eq!(
+                entities,
+                [MessageEntity::text_link(
+                    Url::parse(&expected).unwrap(),
+                    0,
+                    4
+                )],
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn mention_url() {
+        let url = crate::types::UserId(1).url();
+
+        for mode in MODES {
+            let escaped = escape(mode, EscapeContext::LinkUrl, url.as_str());
+            let formatted = match mode {
+                ParseMode::Html => format!("<a href=\"{}\">user</a>", escaped),
+                _ => format!("[user]({})", escaped),
+            };
+
+            let (_, entities) = parse(mode, &formatted).unwrap();
+            assert_eq!(
+                entities[0].kind,
+                MessageEntityKind::TextLink { url: url.clone() },
+                "{:?}",
+                mode
+            );
+        }
+    }
+}
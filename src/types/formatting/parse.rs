@@ -0,0 +1,973 @@
+use std::{cmp, mem};
+
+use url::Url;
+
+use super::text::kind_name;
+use crate::types::{MessageEntity, MessageEntityKind, ParseMode};
+
+/// Parses text formatted with the `mode` into plain text and
+/// [`MessageEntity`]s, the same way Telegram does it.
+///
+/// This allows to validate formatted text (e.g. templates) without sending it
+/// to Telegram, or to send it as text with entities.
+///
+/// Links with invalid URLs are omitted, just like Telegram does. Note that
+/// Telegram may also remove entities which break the nesting rules documented
+/// on [`ParseMode`], this function doesn't do that.
+///
+/// ## Errors
+///
+/// If the text is not formatted correctly, returns a [`ParseError`] with the
+/// same message and position as the error Telegram would return (`Can't parse
+/// entities: ...`).
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{formatting::parse, MessageEntity, ParseMode};
+///
+/// let (text, entities) = parse(ParseMode::MarkdownV2, "*bold* _italic_\\!").unwrap();
+/// assert_eq!(text, "bold italic!");
+/// assert_eq!(
+///     entities,
+///     [MessageEntity::bold(0, 4), MessageEntity::italic(5, 6)]
+/// );
+///
+/// let err = parse(ParseMode::MarkdownV2, "Hello!").unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Character '!' is reserved and must be escaped with the preceding '\\'"
+/// );
+/// assert_eq!(err.offset(), 5);
+/// ```
+#[allow(deprecated)]
+pub fn parse(mode: ParseMode, text: &str) -> Result<(String, Vec<MessageEntity>), ParseError> {
+    match mode {
+        ParseMode::MarkdownV2 => parse_markdown_v2(text),
+        ParseMode::Html => parse_html(text),
+        ParseMode::Markdown => parse_markdown(text),
+    }
+}
+
+/// Error returned by [`parse`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("{message}")]
+pub struct ParseError {
+    message: String,
+    offset: usize,
+}
+
+impl ParseError {
+    fn new(message: String, offset: usize) -> Self {
+        Self { message, offset }
+    }
+
+    /// Returns the description of the error, as returned by Telegram.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the offset (in bytes) in the formatted text at which the error
+    /// was found.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Parsed plain text and entities.
+#[derive(Default)]
+struct Output {
+    text: String,
+    len_utf16: usize,
+    entities: Vec<MessageEntity>,
+}
+
+impl Output {
+    fn push(&mut self, c: char) {
+        // Telegram ignores carriage returns, this allows to separate italic and
+        // underline markers in MarkdownV2 (`_\r__`)
+        if c == '\r' {
+            return;
+        }
+
+        self.text.push(c);
+        self.len_utf16 += c.len_utf16();
+    }
+
+    /// Adds an entity which starts at `offset` (in UTF-16 code units) and ends
+    /// at the end of the text, unless it's empty.
+    fn push_entity(&mut self, kind: MessageEntityKind, offset: usize) {
+        let length = self.len_utf16 - offset;
+        if length != 0 {
+            self.entities.push(MessageEntity::new(kind, offset, length));
+        }
+    }
+
+    fn finish(mut self) -> (String, Vec<MessageEntity>) {
+        // Entities are added when they end, so inner entities go before outer ones,
+        // reverse them so that outer entities go first, even if they have the same
+        // range as inner ones
+        self.entities.reverse();
+        self.entities
+            .sort_by_key(|e| (e.offset, cmp::Reverse(e.length)));
+
+        (self.text, self.entities)
+    }
+}
+
+/// Returns the byte at `i` or `0` if `i` is out of bounds, the parsers rely on
+/// this instead of checking bounds everywhere.
+fn byte_at(text: &str, i: usize) -> u8 {
+    text.as_bytes().get(i).copied().unwrap_or(0)
+}
+
+fn char_at(text: &str, i: usize) -> char {
+    text[i..].chars().next().expect("`i` is in bounds")
+}
+
+fn is_space(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\n' | b'\x0B' | b'\x0C')
+}
+
+/// Returns the URL of a link, or `None` if the URL is invalid. Like Telegram,
+/// treats URLs without a scheme (e.g. `example.com`) as HTTP URLs.
+pub(crate) fn parse_url(url: &str) -> Option<Url> {
+    match Url::parse(url) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => Url::parse(&format!("http://{}", url)).ok(),
+        res => res.ok(),
+    }
+}
+
+fn reserved_character(c: char, offset: usize) -> ParseError {
+    let message = format!(
+        "Character '{}' is reserved and must be escaped with the preceding '\\'",
+        c
+    );
+
+    ParseError::new(message, offset)
+}
+
+/// Parses the language of a pre entity which starts at `i` (after "```") and
+/// skips one newline after it, returns the language and the new position.
+fn pre_language(text: &str, mut i: usize) -> (Option<String>, usize) {
+    let mut language = None;
+
+    let mut language_end = i;
+    while language_end < text.len()
+        && !is_space(byte_at(text, language_end))
+        && byte_at(text, language_end) != b'`'
+    {
+        language_end += 1;
+    }
+
+    if i != language_end && language_end < text.len() && byte_at(text, language_end) != b'`' {
+        language = Some(text[i..language_end].to_owned());
+        i = language_end;
+    }
+
+    // Skip one newline in the beginning of the text
+    let (c, next) = (byte_at(text, i), byte_at(text, i + 1));
+    if c == b'\n' || c == b'\r' {
+        if (next == b'\n' || next == b'\r') && c != next {
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+
+    (language, i)
+}
+
+/// An entity which start was found, but the end wasn't yet.
+struct Nested {
+    kind: MessageEntityKind,
+
+    /// Offset of the entity in the parsed text, in UTF-16 code units.
+    offset: usize,
+
+    /// Offset of the entity in the parsed text, in bytes.
+    byte_offset: usize,
+
+    /// Offset of the start of the entity in the formatted text, in bytes.
+    begin_pos: usize,
+}
+
+fn parse_markdown_v2(text: &str) -> Result<(String, Vec<MessageEntity>), ParseError> {
+    const RESERVED: &[u8] = b"_*[]()~`>#+-=|{}.!";
+
+    let mut out = Output::default();
+    let mut nested: Vec<Nested> = Vec::new();
+
+    let mut i = 0;
+    while i < text.len() {
+        let c = byte_at(text, i);
+        let next = byte_at(text, i + 1);
+
+        if c == b'\\' && (1..=126).contains(&next) {
+            out.push(next as char);
+            i += 2;
+            continue;
+        }
+
+        let in_code = matches!(
+            nested.last().map(|n| &n.kind),
+            Some(MessageEntityKind::Code | MessageEntityKind::Pre { .. })
+        );
+        let reserved: &[u8] = if in_code { b"`" } else { RESERVED };
+
+        if !reserved.contains(&c) {
+            let ch = char_at(text, i);
+            out.push(ch);
+            i += ch.len_utf8();
+            continue;
+        }
+
+        let is_end_of_entity = nested.last().map_or(false, |n| match n.kind {
+            MessageEntityKind::Bold => c == b'*',
+            MessageEntityKind::Italic => c == b'_' && next != b'_',
+            MessageEntityKind::Code => c == b'`',
+            MessageEntityKind::Pre { .. } => text[i..].starts_with("```"),
+            MessageEntityKind::TextLink { .. } => c == b']',
+            MessageEntityKind::Underline => c == b'_' && next == b'_',
+            MessageEntityKind::Strikethrough => c == b'~',
+            MessageEntityKind::Spoiler => c == b'|' && next == b'|',
+            _ => false,
+        });
+
+        if !is_end_of_entity {
+            let begin_pos = i;
+
+            let kind = match c {
+                b'_' if next == b'_' => {
+                    i += 1;
+                    MessageEntityKind::Underline
+                }
+                b'_' => MessageEntityKind::Italic,
+                b'*' => MessageEntityKind::Bold,
+                b'~' => MessageEntityKind::Strikethrough,
+                b'|' if next == b'|' => {
+                    i += 1;
+                    MessageEntityKind::Spoiler
+                }
+                // The url is not known yet, it's replaced when the entity ends
+                b'[' => MessageEntityKind::TextLink {
+                    url: Url::parse("tg://").unwrap(),
+                },
+                b'`' if text[i..].starts_with("```") => {
+                    let (language, new_i) = pre_language(text, i + 3);
+                    i = new_i - 1;
+                    MessageEntityKind::Pre { language }
+                }
+                b'`' => MessageEntityKind::Code,
+                _ => return Err(reserved_character(c as char, i)),
+            };
+
+            nested.push(Nested {
+                kind,
+                offset: out.len_utf16,
+                byte_offset: out.text.len(),
+                begin_pos,
+            });
+
+            i += 1;
+            continue;
+        }
+
+        let entity = nested.pop().expect("the entity ends, so it was started");
+        let kind = match entity.kind {
+            MessageEntityKind::Pre { language } => {
+                i += 2;
+                Some(MessageEntityKind::Pre { language })
+            }
+            kind @ (MessageEntityKind::Underline | MessageEntityKind::Spoiler) => {
+                i += 1;
+                Some(kind)
+            }
+            MessageEntityKind::TextLink { .. } => {
+                let url = if byte_at(text, i + 1) != b'(' {
+                    // Use the text as the url
+                    out.text[entity.byte_offset..].to_owned()
+                } else {
+                    i += 2;
+                    let url_begin_pos = i;
+
+                    let mut url = String::new();
+                    while i < text.len() && byte_at(text, i) != b')' {
+                        let next = byte_at(text, i + 1);
+                        if byte_at(text, i) == b'\\' && (1..=126).contains(&next) {
+                            url.push(next as char);
+                            i += 2;
+                            continue;
+                        }
+
+                        let ch = char_at(text, i);
+                        url.push(ch);
+                        i += ch.len_utf8();
+                    }
+
+                    if byte_at(text, i) != b')' {
+                        let message =
+                            format!("Can't find end of a URL at byte offset {}", url_begin_pos);
+                        return Err(ParseError::new(message, url_begin_pos));
+                    }
+
+                    url
+                };
+
+                parse_url(&url).map(|url| MessageEntityKind::TextLink { url })
+            }
+            kind => Some(kind),
+        };
+
+        if let Some(kind) = kind {
+            out.push_entity(kind, entity.offset);
+        }
+
+        i += 1;
+    }
+
+    if let Some(entity) = nested.pop() {
+        let message = format!(
+            "Can't find end of {} entity at byte offset {}",
+            kind_name(&entity.kind),
+            entity.begin_pos
+        );
+        return Err(ParseError::new(message, entity.begin_pos));
+    }
+
+    Ok(out.finish())
+}
+
+fn parse_markdown(text: &str) -> Result<(String, Vec<MessageEntity>), ParseError> {
+    let mut out = Output::default();
+
+    let mut i = 0;
+    while i < text.len() {
+        let c = byte_at(text, i);
+        let next = byte_at(text, i + 1);
+
+        if c == b'\\' && matches!(next, b'_' | b'*' | b'`' | b'[') {
+            out.push(next as char);
+            i += 2;
+            continue;
+        }
+
+        if !matches!(c, b'_' | b'*' | b'`' | b'[') {
+            let ch = char_at(text, i);
+            out.push(ch);
+            i += ch.len_utf8();
+            continue;
+        }
+
+        // We are at the beginning of an entity
+        let begin_pos = i;
+        let end_character = if c == b'[' { b']' } else { c };
+        let mut is_pre = false;
+        let mut language = None;
+        i += 1;
+
+        if c == b'`' && text[i..].starts_with("``") {
+            is_pre = true;
+            (language, i) = pre_language(text, i + 2);
+        }
+
+        let offset = out.len_utf16;
+        let byte_offset = out.text.len();
+        while i < text.len()
+            && (byte_at(text, i) != end_character || (is_pre && !text[i..].starts_with("```")))
+        {
+            let ch = char_at(text, i);
+            out.push(ch);
+            i += ch.len_utf8();
+        }
+
+        if i == text.len() {
+            let message = format!(
+                "Can't find end of the entity starting at byte offset {}",
+                begin_pos
+            );
+            return Err(ParseError::new(message, begin_pos));
+        }
+
+        if offset != out.len_utf16 {
+            let kind = match c {
+                b'_' => Some(MessageEntityKind::Italic),
+                b'*' => Some(MessageEntityKind::Bold),
+                b'[' => {
+                    let url = if byte_at(text, i + 1) != b'(' {
+                        out.text[byte_offset..].to_owned()
+                    } else {
+                        i += 2;
+                        let url_begin_pos = i;
+                        while i < text.len() && byte_at(text, i) != b')' {
+                            i += 1;
+                        }
+
+                        if i == text.len() {
+                            let message =
+                                format!("Can't find end of a URL at byte offset {}", url_begin_pos);
+                            return Err(ParseError::new(message, url_begin_pos));
+                        }
+
+                        text[url_begin_pos..i].to_owned()
+                    };
+
+                    parse_url(&url).map(|url| MessageEntityKind::TextLink { url })
+                }
+                _ if is_pre => Some(MessageEntityKind::Pre { language }),
+                _ => Some(MessageEntityKind::Code),
+            };
+
+            if let Some(kind) = kind {
+                out.push_entity(kind, offset);
+            }
+        }
+
+        if is_pre {
+            i += 2;
+        }
+
+        i += 1;
+    }
+
+    Ok(out.finish())
+}
+
+/// Decodes an HTML entity (e.g. `&lt;` or `&#60;`) at `i`, returns the decoded
+/// character and the position after the entity.
+fn decode_html_entity(text: &str, i: usize) -> Option<(char, usize)> {
+    let rest = &text[i + 1..];
+    let end = rest.find(';')?;
+    let name = &rest[..end];
+
+    let c = match name {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        _ => {
+            let code = match name.strip_prefix('#')? {
+                hex if hex.starts_with(|c: char| c == 'x' || c == 'X') => {
+                    u32::from_str_radix(&hex[1..], 16).ok()?
+                }
+                dec => dec.parse().ok()?,
+            };
+
+            char::from_u32(code).filter(|&c| c != '\0')?
+        }
+    };
+
+    Some((c, i + 1 + end + 1))
+}
+
+fn parse_html(text: &str) -> Result<(String, Vec<MessageEntity>), ParseError> {
+    struct Tag {
+        name: String,
+        argument: String,
+        offset: usize,
+        byte_offset: usize,
+        begin_pos: usize,
+    }
+
+    const TAGS: &[&str] = &[
+        "a",
+        "b",
+        "strong",
+        "i",
+        "em",
+        "s",
+        "strike",
+        "del",
+        "u",
+        "ins",
+        "tg-spoiler",
+        "span",
+        "pre",
+        "code",
+    ];
+
+    let mut out = Output::default();
+    let mut nested: Vec<Tag> = Vec::new();
+
+    // Language of the last code entity, if it's the last entity
+    let mut code_language: Option<String> = None;
+
+    let mut i = 0;
+    while i < text.len() {
+        let c = byte_at(text, i);
+
+        if c == b'&' {
+            if let Some((ch, new_i)) = decode_html_entity(text, i) {
+                out.push(ch);
+                i = new_i;
+                continue;
+            }
+        }
+
+        if c != b'<' {
+            let ch = char_at(text, i);
+            out.push(ch);
+            i += ch.len_utf8();
+            continue;
+        }
+
+        let begin_pos = i;
+        i += 1;
+
+        if byte_at(text, i) != b'/' {
+            // Start of a tag
+            let mut name_end = i;
+            while name_end < text.len()
+                && !is_space(byte_at(text, name_end))
+                && byte_at(text, name_end) != b'>'
+            {
+                name_end += 1;
+            }
+
+            let name = text[i..name_end].to_lowercase();
+            if !TAGS.contains(&name.as_str()) {
+                let message = format!(
+                    "Unsupported start tag \"{}\" at byte offset {}",
+                    name, begin_pos
+                );
+                return Err(ParseError::new(message, begin_pos));
+            }
+
+            let unclosed = || {
+                let message = format!("Unclosed start tag at byte offset {}", begin_pos);
+                ParseError::new(message, begin_pos)
+            };
+
+            let mut argument = String::new();
+            i = name_end;
+            while byte_at(text, i) != b'>' {
+                while is_space(byte_at(text, i)) {
+                    i += 1;
+                }
+
+                match byte_at(text, i) {
+                    b'>' => break,
+                    0 => return Err(unclosed()),
+                    _ => {}
+                }
+
+                let attribute_begin_pos = i;
+                while i < text.len() && !is_space(byte_at(text, i)) && byte_at(text, i) != b'=' {
+                    i += 1;
+                }
+
+                let attribute = &text[attribute_begin_pos..i];
+                if attribute.is_empty() {
+                    let message = format!(
+                        "Empty attribute name in the tag \"{}\" at byte offset {}",
+                        name, begin_pos
+                    );
+                    return Err(ParseError::new(message, begin_pos));
+                }
+
+                while is_space(byte_at(text, i)) {
+                    i += 1;
+                }
+
+                if byte_at(text, i) != b'=' {
+                    let message = format!(
+                        "Expected equal sign in declaration of an attribute of the tag \"{}\" at \
+                         byte offset {}",
+                        name, begin_pos
+                    );
+                    return Err(ParseError::new(message, begin_pos));
+                }
+
+                i += 1;
+                while is_space(byte_at(text, i)) {
+                    i += 1;
+                }
+
+                if i >= text.len() {
+                    return Err(unclosed());
+                }
+
+                let mut value = String::new();
+                let quote = byte_at(text, i);
+                if quote != b'\'' && quote != b'"' {
+                    // A name token (a sequence of letters, digits, periods, or hyphens), which is
+                    // not case sensitive
+                    let token_begin_pos = i;
+                    while byte_at(text, i).is_ascii_alphanumeric()
+                        || matches!(byte_at(text, i), b'.' | b'-')
+                    {
+                        i += 1;
+                    }
+
+                    value = text[token_begin_pos..i].to_lowercase();
+
+                    if !is_space(byte_at(text, i)) && byte_at(text, i) != b'>' {
+                        let message = format!(
+                            "Unexpected end of name token at byte offset {}",
+                            token_begin_pos
+                        );
+                        return Err(ParseError::new(message, token_begin_pos));
+                    }
+                } else {
+                    // A string literal delimited by quotes
+                    i += 1;
+                    while i < text.len() && byte_at(text, i) != quote {
+                        if byte_at(text, i) == b'&' {
+                            if let Some((ch, new_i)) = decode_html_entity(text, i) {
+                                value.push(ch);
+                                i = new_i;
+                                continue;
+                            }
+                        }
+
+                        let ch = char_at(text, i);
+                        value.push(ch);
+                        i += ch.len_utf8();
+                    }
+
+                    if byte_at(text, i) == quote {
+                        i += 1;
+                    }
+                }
+
+                if i >= text.len() {
+                    return Err(unclosed());
+                }
+
+                match (name.as_str(), attribute) {
+                    ("a", "href") => argument = value,
+                    ("code", "class") if value.starts_with("language-") => {
+                        argument = value["language-".len()..].to_owned()
+                    }
+                    ("span", "class") if value.starts_with("tg-") => {
+                        argument = value["tg-".len()..].to_owned()
+                    }
+                    _ => {}
+                }
+            }
+
+            if name == "span" && argument != "spoiler" {
+                let message = format!(
+                    "Tag \"span\" must have class \"tg-spoiler\" at byte offset {}",
+                    begin_pos
+                );
+                return Err(ParseError::new(message, begin_pos));
+            }
+
+            nested.push(Tag {
+                name,
+                argument,
+                offset: out.len_utf16,
+                byte_offset: out.text.len(),
+                begin_pos,
+            });
+        } else {
+            // End of a tag
+            let mut tag = match nested.pop() {
+                Some(tag) => tag,
+                None => {
+                    let message = format!("Unexpected end tag at byte offset {}", begin_pos);
+                    return Err(ParseError::new(message, begin_pos));
+                }
+            };
+
+            i += 1;
+            let name_begin = i;
+            while i < text.len() && !is_space(byte_at(text, i)) && byte_at(text, i) != b'>' {
+                i += 1;
+            }
+            let end_name = &text[name_begin..i];
+
+            while is_space(byte_at(text, i)) {
+                i += 1;
+            }
+
+            if byte_at(text, i) != b'>' {
+                let message = format!("Unclosed end tag at byte offset {}", begin_pos);
+                return Err(ParseError::new(message, begin_pos));
+            }
+
+            if !end_name.is_empty() && end_name != tag.name {
+                let message = format!(
+                    "Unmatched end tag at byte offset {}, expected \"</{}>\", found \"</{}>\"",
+                    begin_pos, tag.name, end_name
+                );
+                return Err(ParseError::new(message, begin_pos));
+            }
+
+            let length = out.len_utf16 - tag.offset;
+            let entities_len = out.entities.len();
+            let last = out
+                .entities
+                .last_mut()
+                .filter(|e| e.offset == tag.offset && e.length == length);
+
+            let kind = match tag.name.as_str() {
+                "b" | "strong" => Some(MessageEntityKind::Bold),
+                "i" | "em" => Some(MessageEntityKind::Italic),
+                "s" | "strike" | "del" => Some(MessageEntityKind::Strikethrough),
+                "u" | "ins" => Some(MessageEntityKind::Underline),
+                "tg-spoiler" | "span" => Some(MessageEntityKind::Spoiler),
+                "a" => {
+                    let url = match tag.argument.as_str() {
+                        "" => &out.text[tag.byte_offset..],
+                        url => url,
+                    };
+
+                    parse_url(url).map(|url| MessageEntityKind::TextLink { url })
+                }
+                // `<pre><code class="language-...">` is a pre entity with the language
+                "pre" => match (last, code_language.take()) {
+                    (
+                        Some(MessageEntity {
+                            kind: kind @ MessageEntityKind::Code,
+                            ..
+                        }),
+                        Some(language),
+                    ) => {
+                        *kind = MessageEntityKind::Pre {
+                            language: Some(language),
+                        };
+                        None
+                    }
+                    _ => Some(MessageEntityKind::Pre { language: None }),
+                },
+                "code" => match last {
+                    Some(MessageEntity {
+                        kind: MessageEntityKind::Pre { language },
+                        ..
+                    }) if !tag.argument.is_empty() => {
+                        *language = Some(mem::take(&mut tag.argument));
+                        None
+                    }
+                    _ => Some(MessageEntityKind::Code),
+                },
+                _ => unreachable!("only supported tags are started"),
+            };
+
+            if let Some(kind) = kind {
+                out.push_entity(kind, tag.offset);
+            }
+
+            if out.entities.len() != entities_len {
+                code_language = match tag.name.as_str() {
+                    "code" if !tag.argument.is_empty() => Some(tag.argument),
+                    _ => None,
+                };
+            }
+        }
+
+        i += 1;
+    }
+
+    if let Some(tag) = nested.pop() {
+        let message = format!("Can't find end tag corresponding to start tag {}", tag.name);
+        return Err(ParseError::new(message, tag.begin_pos));
+    }
+
+    Ok(out.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(deprecated)]
+
+    use super::parse;
+    use crate::types::{MessageEntity, ParseMode, UserId};
+
+    fn err(mode: ParseMode, text: &str) -> (String, usize) {
+        let err = parse(mode, text).unwrap_err();
+        (err.to_string(), err.offset())
+    }
+
+    #[test]
+    fn markdown_v2() {
+        let (text, entities) = parse(
+            ParseMode::MarkdownV2,
+            "*bold _italic bold ~strike||spoiler||~ __underline___* [link](https://example.com/\\)) \
+             [mention](tg://user/?id=1) `co\\`de` ```rust\nfn main() {}```",
+        )
+        .unwrap();
+
+        assert_eq!(
+            text,
+            "bold italic bold strikespoiler underline link mention co`de fn main() {}"
+        );
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::bold(0, 40),
+                MessageEntity::italic(5, 35),
+                MessageEntity::strikethrough(17, 13),
+                MessageEntity::spoiler(23, 7),
+                MessageEntity::underline(31, 9),
+                MessageEntity::text_link("https://example.com/)".parse().unwrap(), 41, 4),
+                MessageEntity::text_mention_id(UserId(1), 46, 7),
+                MessageEntity::code(54, 5),
+                MessageEntity::pre(Some("rust".to_owned()), 60, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_v2_utf16() {
+        let (text, entities) = parse(ParseMode::MarkdownV2, "🦀 *быба*").unwrap();
+        assert_eq!(text, "🦀 быба");
+        assert_eq!(entities, [MessageEntity::bold(3, 4)]);
+    }
+
+    #[test]
+    fn markdown_v2_errors() {
+        let mode = ParseMode::MarkdownV2;
+
+        assert_eq!(
+            err(mode, "a.b"),
+            (
+                "Character '.' is reserved and must be escaped with the preceding '\\'".to_owned(),
+                1
+            )
+        );
+        assert_eq!(
+            err(mode, "x *bold _italic*_"),
+            (
+                "Can't find end of Italic entity at byte offset 16".to_owned(),
+                16
+            )
+        );
+        assert_eq!(
+            err(mode, "[link](https://example.com"),
+            ("Can't find end of a URL at byte offset 7".to_owned(), 7)
+        );
+        // See the note about `___` in `ParseMode` docs
+        assert_eq!(
+            err(mode, "___italic underline___"),
+            (
+                "Can't find end of Italic entity at byte offset 21".to_owned(),
+                21
+            )
+        );
+        assert_eq!(
+            parse(mode, "___italic underline_\r__").unwrap(),
+            (
+                "italic underline".to_owned(),
+                vec![
+                    MessageEntity::underline(0, 16),
+                    MessageEntity::italic(0, 16)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn markdown_v2_invalid_url() {
+        let (text, entities) = parse(ParseMode::MarkdownV2, "[link](http://exa mple.com)").unwrap();
+        assert_eq!(text, "link");
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    fn html() {
+        let (text, entities) = parse(
+            ParseMode::Html,
+            "<b>bold <i>italic &amp; bold</i></b> <a href=\"https://example.com/?a=1&amp;b=2\">link</a> \
+             <span class=\"tg-spoiler\">spoiler</span> &lt;&#129408;&#x1F980;&gt; \
+             <pre><code class=\"language-rust\">fn main() {}</code></pre> <code>x</code>",
+        )
+        .unwrap();
+
+        assert_eq!(
+            text,
+            "bold italic & bold link spoiler <🦀🦀> fn main() {} x"
+        );
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::bold(0, 18),
+                MessageEntity::italic(5, 13),
+                MessageEntity::text_link("https://example.com/?a=1&b=2".parse().unwrap(), 19, 4),
+                MessageEntity::spoiler(24, 7),
+                MessageEntity::pre(Some("rust".to_owned()), 39, 12),
+                MessageEntity::code(52, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_errors() {
+        let mode = ParseMode::Html;
+
+        assert_eq!(
+            err(mode, "a <x>b</x>"),
+            ("Unsupported start tag \"x\" at byte offset 2".to_owned(), 2)
+        );
+        assert_eq!(
+            err(mode, "<b><i>a</b></i>"),
+            (
+                "Unmatched end tag at byte offset 7, expected \"</i>\", found \"</b>\"".to_owned(),
+                7
+            )
+        );
+        assert_eq!(
+            err(mode, "a</b>"),
+            ("Unexpected end tag at byte offset 1".to_owned(), 1)
+        );
+        assert_eq!(
+            err(mode, "<b>a"),
+            (
+                "Can't find end tag corresponding to start tag b".to_owned(),
+                0
+            )
+        );
+        assert_eq!(
+            err(mode, "<span>a</span>"),
+            (
+                "Tag \"span\" must have class \"tg-spoiler\" at byte offset 0".to_owned(),
+                0
+            )
+        );
+        assert_eq!(
+            err(mode, "<a href>a</a>"),
+            (
+                "Expected equal sign in declaration of an attribute of the tag \"a\" at byte \
+                 offset 0"
+                    .to_owned(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let (text, entities) = parse(
+            ParseMode::Markdown,
+            "*bold* _snake_\\__case_ [link](https://example.com) ```\ncode```",
+        )
+        .unwrap();
+
+        assert_eq!(text, "bold snake_case link code");
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::bold(0, 4),
+                MessageEntity::italic(5, 5),
+                MessageEntity::italic(11, 4),
+                MessageEntity::text_link("https://example.com".parse().unwrap(), 16, 4),
+                MessageEntity::pre(None, 21, 4),
+            ]
+        );
+
+        assert_eq!(
+            err(ParseMode::Markdown, "a *b"),
+            (
+                "Can't find end of the entity starting at byte offset 2".to_owned(),
+                2
+            )
+        );
+        assert_eq!(
+            err(ParseMode::Markdown, "[link](https://example.com"),
+            ("Can't find end of a URL at byte offset 7".to_owned(), 7)
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use std::cmp;
+
+use super::{escape, text::can_contain, EscapeContext};
+use crate::types::{MessageEntity, MessageEntityKind, MessageEntityRef, ParseMode};
+
+/// Renders `text` with `entities` (e.g. received in a [`Message`]) as text
+/// formatted with the `mode`.
+///
+/// When sent with the `mode`, the result is parsed by Telegram into the same
+/// text and entities, which allows to quote, store or resend messages.
+///
+/// Some entities can't be represented with markup, so they are rendered as
+/// plain text:
+/// - Entities which are detected by Telegram automatically (mentions, hashtags,
+///   URLs, etc), they are detected again after sending.
+/// - Custom emojis.
+/// - Entities which break the nesting rules documented on
+///   [`Text`](super::Text), e.g. bold text inside of code.
+///
+/// Entities which partially overlap are split, so that they are nested
+/// properly. Text mentions are rendered as links in the form of
+/// `tg://user/?id=...`.
+///
+/// Legacy [`ParseMode::Markdown`] is supported on a best-effort basis: it
+/// doesn't support nested entities (only the outer entities are rendered),
+/// underline, strikethrough and spoiler entities, while entities containing
+/// characters which can't be escaped are split.
+///
+/// ## Panics
+///
+/// If offsets of the `entities` are out of bounds of the `text` or point to the
+/// middle of a character.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{formatting::render, MessageEntity, ParseMode};
+///
+/// let entities = [MessageEntity::bold(0, 11), MessageEntity::italic(6, 5)];
+/// let html = render(ParseMode::Html, "Hello world!", &entities);
+/// assert_eq!(html, "<b>Hello <i>world</i></b>!");
+///
+/// let markdown = render(ParseMode::MarkdownV2, "Hello world!", &entities);
+/// assert_eq!(markdown, "*Hello _world_*\\!");
+/// ```
+///
+/// [`Message`]: crate::types::Message
+#[allow(deprecated)]
+#[must_use]
+pub fn render(mode: ParseMode, text: &str, entities: &[MessageEntity]) -> String {
+    let mut entities: Vec<_> = MessageEntityRef::parse(text, entities)
+        .into_iter()
+        .filter(|e| !e.range().is_empty() && is_supported(mode, e.kind()))
+        .collect();
+    entities.sort_by_key(|e| (e.start(), cmp::Reverse(e.end())));
+
+    match mode {
+        ParseMode::MarkdownV2 | ParseMode::Html => render_nested(mode, text, &entities),
+        ParseMode::Markdown => render_markdown(text, &entities),
+    }
+}
+
+#[allow(deprecated)]
+fn is_supported(mode: ParseMode, kind: &MessageEntityKind) -> bool {
+    use MessageEntityKind::*;
+
+    match kind {
+        Bold | Italic | Code | Pre { .. } | TextLink { .. } | TextMention { .. } => true,
+        Underline | Strikethrough | Spoiler => mode != ParseMode::Markdown,
+        _ => false,
+    }
+}
+
+/// Renders entities for modes which support nesting.
+fn render_nested(mode: ParseMode, text: &str, entities: &[MessageEntityRef<'_>]) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    let mut boundaries: Vec<_> = entities
+        .iter()
+        .flat_map(|e| [e.start(), e.end()])
+        .chain([text.len()])
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut stack: Vec<&MessageEntityRef<'_>> = Vec::new();
+    let mut entities = entities.iter().peekable();
+    let mut pos = 0;
+
+    for boundary in boundaries {
+        let in_code = stack.iter().any(|e| {
+            matches!(
+                e.kind(),
+                MessageEntityKind::Code | MessageEntityKind::Pre { .. }
+            )
+        });
+        push_text(mode, &text[pos..boundary], in_code, &mut out);
+        pos = boundary;
+
+        // Close entities which end here, reopening entities nested in them
+        if let Some(idx) = stack.iter().position(|e| e.end() == boundary) {
+            let closed: Vec<_> = stack.drain(idx..).collect();
+
+            for e in closed.iter().rev() {
+                close(mode, e.kind(), &mut out);
+            }
+
+            for e in closed.into_iter().filter(|e| e.end() != boundary) {
+                open(mode, e.kind(), &mut out);
+                stack.push(e);
+            }
+        }
+
+        while let Some(e) = entities.next_if(|e| e.start() == boundary) {
+            if stack
+                .iter()
+                .all(|outer| can_contain(outer.kind(), e.kind()))
+            {
+                open(mode, e.kind(), &mut out);
+                stack.push(e);
+            }
+        }
+    }
+
+    out
+}
+
+/// Renders entities for the legacy Markdown mode, which doesn't support nesting
+/// and escaping inside of entities.
+#[allow(deprecated)]
+fn render_markdown(text: &str, entities: &[MessageEntityRef<'_>]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+
+    for e in entities {
+        // Nested (or overlapping) entity
+        if e.start() < pos {
+            continue;
+        }
+
+        push_text(ParseMode::Markdown, &text[pos..e.start()], false, &mut out);
+        pos = e.end();
+
+        let end_character = match e.kind() {
+            MessageEntityKind::Bold => '*',
+            MessageEntityKind::Italic => '_',
+            MessageEntityKind::Code | MessageEntityKind::Pre { .. } => '`',
+            _ => ']',
+        };
+
+        // Characters equal to the end of the entity can't be escaped inside of it, so
+        // the entity is closed, the character is escaped and the entity is reopened
+        for (i, part) in e.text().split(end_character).enumerate() {
+            if i != 0 {
+                out.push('\\');
+                out.push(end_character);
+            }
+
+            if !part.is_empty() {
+                open(ParseMode::Markdown, e.kind(), &mut out);
+                out.push_str(part);
+                close(ParseMode::Markdown, e.kind(), &mut out);
+            }
+        }
+    }
+
+    push_text(ParseMode::Markdown, &text[pos..], false, &mut out);
+
+    out
+}
+
+#[allow(deprecated)]
+fn push_text(mode: ParseMode, text: &str, in_code: bool, out: &mut String) {
+    let context = if in_code {
+        EscapeContext::Code
+    } else {
+        EscapeContext::Text
+    };
+
+    out.push_str(&escape(mode, context, text));
+}
+
+/// Separates an italic or underline marker from the previous one with `\r`
+/// (which is ignored by Telegram), since `___` is ambiguous in MarkdownV2.
+fn separate_underscores(out: &mut String) {
+    let is_marker = out.strip_suffix('_').map_or(false, |rest| {
+        // Unescaped underscore
+        rest.chars().rev().take_while(|&c| c == '\\').count() % 2 == 0
+    });
+
+    if is_marker {
+        out.push('\r');
+    }
+}
+
+fn link_url(kind: &MessageEntityKind) -> String {
+    match kind {
+        MessageEntityKind::TextLink { url } => url.as_str().to_owned(),
+        MessageEntityKind::TextMention { user } => user.id.url().into(),
+        _ => unreachable!("only links have urls"),
+    }
+}
+
+#[allow(deprecated)]
+fn open(mode: ParseMode, kind: &MessageEntityKind, out: &mut String) {
+    use MessageEntityKind::*;
+
+    match mode {
+        ParseMode::MarkdownV2 => {
+            let marker = match kind {
+                Bold => "*",
+                Italic => "_",
+                Underline => "__",
+                Strikethrough => "~",
+                Spoiler => "||",
+                Code => "`",
+                Pre { .. } => "```",
+                _ => "[",
+            };
+
+            if marker.starts_with('_') {
+                separate_underscores(out);
+            }
+            out.push_str(marker);
+
+            if let Pre { language } = kind {
+                out.push_str(language.as_deref().unwrap_or_default());
+                out.push('\n');
+            }
+        }
+        ParseMode::Html => match kind {
+            Bold => out.push_str("<b>"),
+            Italic => out.push_str("<i>"),
+            Underline => out.push_str("<u>"),
+            Strikethrough => out.push_str("<s>"),
+            Spoiler => out.push_str("<tg-spoiler>"),
+            Code => out.push_str("<code>"),
+            Pre { language: None } => out.push_str("<pre>"),
+            Pre {
+                language: Some(language),
+            } => {
+                out.push_str("<pre><code class=\"language-");
+                // Attributes are escaped the same way as URLs
+                out.push_str(&escape(mode, EscapeContext::LinkUrl, language));
+                out.push_str("\">");
+            }
+            _ => {
+                out.push_str("<a href=\"");
+                out.push_str(&escape(mode, EscapeContext::LinkUrl, &link_url(kind)));
+                out.push_str("\">");
+            }
+        },
+        ParseMode::Markdown => match kind {
+            Bold => out.push('*'),
+            Italic => out.push('_'),
+            Code => out.push('`'),
+            Pre { language } => {
+                out.push_str("```");
+                out.push_str(language.as_deref().unwrap_or_default());
+                out.push('\n');
+            }
+            _ => out.push('['),
+        },
+    }
+}
+
+#[allow(deprecated)]
+fn close(mode: ParseMode, kind: &MessageEntityKind, out: &mut String) {
+    use MessageEntityKind::*;
+
+    match mode {
+        ParseMode::MarkdownV2 => match kind {
+            Bold => out.push('*'),
+            Italic => {
+                separate_underscores(out);
+                out.push('_');
+            }
+            Underline => {
+                separate_underscores(out);
+                out.push_str("__");
+            }
+            Strikethrough => out.push('~'),
+            Spoiler => out.push_str("||"),
+            Code => out.push('`'),
+            Pre { .. } => out.push_str("```"),
+            _ => {
+                out.push_str("](");
+                out.push_str(&escape(mode, EscapeContext::LinkUrl, &link_url(kind)));
+                out.push(')');
+            }
+        },
+        ParseMode::Html => match kind {
+            Bold => out.push_str("</b>"),
+            Italic => out.push_str("</i>"),
+            Underline => out.push_str("</u>"),
+            Strikethrough => out.push_str("</s>"),
+            Spoiler => out.push_str("</tg-spoiler>"),
+            Code => out.push_str("</code>"),
+            Pre { language: None } => out.push_str("</pre>"),
+            Pre { language: Some(_) } => out.push_str("</code></pre>"),
+            _ => out.push_str("</a>"),
+        },
+        ParseMode::Markdown => match kind {
+            Bold => out.push('*'),
+            Italic => out.push('_'),
+            Code => out.push('`'),
+            Pre { .. } => out.push_str("```"),
+            _ => {
+                out.push_str("](");
+                out.push_str(&escape(mode, EscapeContext::LinkUrl, &link_url(kind)));
+                out.push(')');
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(deprecated)]
+
+    use super::render;
+    use crate::types::{formatting::parse, MessageEntity, MessageEntityKind, ParseMode, UserId};
+
+    fn round_trip(mode: ParseMode, text: &str, entities: &[MessageEntity]) -> String {
+        let rendered = render(mode, text, entities);
+        let (parsed_text, parsed_entities) = parse(mode, &rendered).unwrap();
+
+        assert_eq!(parsed_text, text, "rendered: {:?}", rendered);
+        assert_eq!(parsed_entities, entities, "rendered: {:?}", rendered);
+
+        rendered
+    }
+
+    fn nested_entities() -> Vec<MessageEntity> {
+        vec![
+            MessageEntity::bold(0, 18),
+            MessageEntity::italic(0, 11),
+            MessageEntity::underline(0, 11),
+            MessageEntity::spoiler(12, 6),
+            MessageEntity::strikethrough(12, 5),
+            MessageEntity::text_link("https://example.com/a_(b)".parse().unwrap(), 19, 4),
+            MessageEntity::text_mention_id(UserId(1), 24, 2),
+            MessageEntity::code(27, 6),
+            MessageEntity::pre(Some("rust".to_owned()), 34, 6),
+        ]
+    }
+
+    const NESTED_TEXT: &str = "*italic_ul* 🦀<>&. link me `code\\ let x;";
+
+    #[test]
+    fn markdown_v2() {
+        let rendered = round_trip(ParseMode::MarkdownV2, NESTED_TEXT, &nested_entities());
+        assert_eq!(
+            rendered,
+            "*_\r__\\*italic\\_ul\\*__\r_ ||~🦀<\\>&~\\.||* [link](https://example.com/a_(b\\)) \
+             [me](tg://user/?id=1) `\\`code\\\\` ```rust\nlet x;```"
+        );
+    }
+
+    #[test]
+    fn html() {
+        let rendered = round_trip(ParseMode::Html, NESTED_TEXT, &nested_entities());
+        assert_eq!(
+            rendered,
+            "<b><i><u>*italic_ul*</u></i> <tg-spoiler><s>🦀&lt;&gt;&amp;</s>.</tg-spoiler></b> \
+             <a href=\"https://example.com/a_(b)\">link</a> <a href=\"tg://user/?id=1\">me</a> \
+             <code>`code\\</code> <pre><code class=\"language-rust\">let x;</code></pre>"
+        );
+    }
+
+    /// Checks the nesting rules without `can_contain`, since it's under test.
+    fn properly_nested(entities: &[MessageEntity]) -> bool {
+        use MessageEntityKind::*;
+
+        let contains = |outer: &MessageEntity, inner: &MessageEntity| {
+            let can_contain = match outer.kind {
+                Code | Pre { .. } => false,
+                Bold | Italic | Underline | Strikethrough | Spoiler => true,
+                _ => matches!(
+                    inner.kind,
+                    Bold | Italic | Underline | Strikethrough | Spoiler
+                ),
+            };
+
+            can_contain
+                && outer.offset <= inner.offset
+                && inner.offset + inner.length <= outer.offset + outer.length
+        };
+
+        entities.iter().enumerate().all(|(i, a)| {
+            entities[i + 1..].iter().all(|b| {
+                let disjoint = a.offset + a.length <= b.offset || b.offset + b.length <= a.offset;
+                disjoint || contains(a, b) || contains(b, a)
+            })
+        })
+    }
+
+    /// Parses random snippets of markup, checking that rendering the result in
+    /// every nesting-capable mode gives the same text with entities back.
+    #[test]
+    fn parse_render_parse() {
+        const MARKDOWN_V2: &[&str] = &[
+            "*",
+            "_",
+            "__",
+            "~",
+            "||",
+            "`",
+            "```",
+            "```rust\n",
+            "[",
+            "](https://example.com)",
+            "](tg://user?id=1)",
+            "\\*",
+            "\\\\",
+            "a",
+            "@",
+            " ",
+            "\n",
+            "🦀",
+            "<&>",
+        ];
+        const HTML: &[&str] = &[
+            "<b>",
+            "</b>",
+            "<i>",
+            "</i>",
+            "<u>",
+            "</u>",
+            "<s>",
+            "</s>",
+            "<tg-spoiler>",
+            "</tg-spoiler>",
+            "<code>",
+            "</code>",
+            "<pre>",
+            "</pre>",
+            "<pre><code class=\"language-rust\">",
+            "</code></pre>",
+            "<a href=\"https://example.com\">",
+            "<a href=\"tg://user?id=1\">",
+            "</a>",
+            "&lt;",
+            "&amp;",
+            "a",
+            "@",
+            " ",
+            "\n",
+            "🦀",
+            "*_`",
+        ];
+
+        // xorshift, to get reproducible pseudo-random snippets without extra
+        // dependencies
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+
+        let mut checked = 0;
+        for _ in 0..20_000 {
+            let (mode, tokens) = match next(2) {
+                0 => (ParseMode::MarkdownV2, MARKDOWN_V2),
+                _ => (ParseMode::Html, HTML),
+            };
+            let len = next(12);
+            let markup: String = (0..len).map(|_| tokens[next(tokens.len())]).collect();
+
+            let (text, entities) = match parse(mode, &markup) {
+                Ok(parsed) => parsed,
+                Err(_) => continue,
+            };
+
+            // `parse` doesn't remove invalidly nested entities (e.g. code inside of pre),
+            // while `render` does
+            if !properly_nested(&entities) {
+                continue;
+            }
+
+            for mode in [ParseMode::MarkdownV2, ParseMode::Html] {
+                let rendered = render(mode, &text, &entities);
+                let reparsed = parse(mode, &rendered).unwrap();
+                assert_eq!(
+                    reparsed,
+                    (text.clone(), entities.clone()),
+                    "markup: {:?}, rendered: {:?}",
+                    markup,
+                    rendered
+                );
+            }
+            checked += 1;
+        }
+
+        // Make sure that the snippets aren't mostly invalid
+        assert!(checked > 1_000, "only {} snippets were checked", checked);
+    }
+
+    #[test]
+    fn code_in_bold() {
+        let (text, entities) = parse(ParseMode::MarkdownV2, "*a`@`*").unwrap();
+        assert_eq!(
+            entities,
+            [MessageEntity::bold(0, 2), MessageEntity::code(1, 1)]
+        );
+
+        assert_eq!(
+            round_trip(ParseMode::Html, &text, &entities),
+            "<b>a<code>@</code></b>"
+        );
+        assert_eq!(
+            round_trip(ParseMode::MarkdownV2, &text, &entities),
+            "*a`@`*"
+        );
+    }
+
+    #[test]
+    fn overlapping() {
+        let entities = [MessageEntity::bold(0, 4), MessageEntity::italic(2, 4)];
+
+        assert_eq!(
+            render(ParseMode::Html, "abcdef", &entities),
+            "<b>ab<i>cd</i></b><i>ef</i>"
+        );
+        assert_eq!(
+            render(ParseMode::MarkdownV2, "abcdef", &entities),
+            "*ab_cd_*_ef_"
+        );
+    }
+
+    #[test]
+    fn invalid_nesting() {
+        let entities = [MessageEntity::code(0, 4), MessageEntity::bold(1, 2)];
+        round_trip(ParseMode::Html, "code", &entities[..1]);
+        assert_eq!(
+            render(ParseMode::Html, "code", &entities),
+            "<code>code</code>"
+        );
+    }
+
+    #[test]
+    fn automatic_entities() {
+        let entities = [MessageEntity {
+            kind: MessageEntityKind::Hashtag,
+            offset: 0,
+            length: 4,
+        }];
+
+        assert_eq!(render(ParseMode::MarkdownV2, "#tag", &entities), "\\#tag");
+    }
+
+    #[test]
+    fn markdown() {
+        let entities = [
+            MessageEntity::bold(0, 5),
+            MessageEntity::italic(1, 2),
+            MessageEntity::italic(6, 10),
+            MessageEntity::text_link("https://example.com/)".parse().unwrap(), 17, 4),
+            MessageEntity::pre(None, 22, 4),
+        ];
+
+        assert_eq!(
+            render(
+                ParseMode::Markdown,
+                "2*2=4 snake_case link code [x]",
+                &entities
+            ),
+            "*2*\\**2=4* _snake_\\__case_ [link](https://example.com/%29) ```\ncode``` \\[x]"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::{cmp, ops::Range};
+
+use crate::types::MessageEntity;
+
+/// Maximum length of a message text (after entities parsing), in UTF-16 code
+/// units.
+pub const MESSAGE_MAX_LEN: usize = 4096;
+
+/// Maximum length of a media caption (after entities parsing), in UTF-16 code
+/// units.
+pub const CAPTION_MAX_LEN: usize = 1024;
+
+/// Splits `text` with `entities` into chunks which are at most `max_len` UTF-16
+/// code units long (e.g. [`MESSAGE_MAX_LEN`] or [`CAPTION_MAX_LEN`]), so that
+/// they can be sent as separate messages.
+///
+/// Chunks are split at paragraph boundaries (empty lines) if possible, then at
+/// line boundaries, then at word boundaries. Whitespace at which the text is
+/// split is not included in the chunks. Text without whitespace is split at
+/// character boundaries, surrogate pairs are never split.
+///
+/// Entities which cross chunks boundaries are split too, so that each chunk
+/// has entities with valid offsets.
+///
+/// Returns an empty vector if the `text` is empty.
+///
+/// ## Panics
+///
+/// If `max_len` is less than `2` (the length of the longest character).
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{formatting::split, MessageEntity};
+///
+/// let text = "Hello world!\n\nThis is a long text.";
+/// let entities = [MessageEntity::bold(6, 22)];
+/// let chunks = split(text, &entities, 20);
+///
+/// assert_eq!(
+///     chunks,
+///     [
+///         ("Hello world!".to_owned(), vec![MessageEntity::bold(6, 6)]),
+///         (
+///             "This is a long text.".to_owned(),
+///             vec![MessageEntity::bold(0, 14)]
+///         )
+///     ]
+/// );
+/// ```
+#[must_use]
+pub fn split(
+    text: &str,
+    entities: &[MessageEntity],
+    max_len: usize,
+) -> Vec<(String, Vec<MessageEntity>)> {
+    assert!(
+        max_len >= 2,
+        "`max_len` must be at least 2 to fit any character"
+    );
+
+    // Offsets in UTF-16 code units and in bytes of every character and of the end
+    // of the text
+    let mut bounds = Vec::with_capacity(text.len() + 1);
+    let mut len_utf16 = 0;
+    for (byte_offset, c) in text.char_indices() {
+        bounds.push((len_utf16, byte_offset));
+        len_utf16 += c.len_utf16();
+    }
+    bounds.push((len_utf16, text.len()));
+
+    let separators = separators(text);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < len_utf16 {
+        let (end, next_start) = if len_utf16 - start <= max_len {
+            (len_utf16, len_utf16)
+        } else {
+            let limit = start + max_len;
+
+            separators
+                .iter()
+                .filter(|s| s.start > start && s.start <= limit)
+                .max_by_key(|s| (s.boundary, s.start))
+                .map(|s| (s.start, s.end))
+                .unwrap_or_else(|| {
+                    // Split at the last character which fits
+                    let idx = bounds.partition_point(|&(offset, _)| offset <= limit) - 1;
+                    (bounds[idx].0, bounds[idx].0)
+                })
+        };
+
+        chunks.push(chunk(text, entities, &bounds, start..end));
+        start = next_start;
+    }
+
+    chunks
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Boundary {
+    Word,
+    Line,
+    Paragraph,
+}
+
+/// A run of whitespace characters at which the text can be split.
+struct Separator {
+    /// Offset of the first whitespace character, in UTF-16 code units.
+    start: usize,
+
+    /// Offset after the last whitespace character, in UTF-16 code units.
+    end: usize,
+
+    boundary: Boundary,
+}
+
+fn separators(text: &str) -> Vec<Separator> {
+    let mut separators = Vec::new();
+
+    // Start of the current whitespace run and the number of newlines in it
+    let mut run: Option<(usize, usize)> = None;
+    let mut offset = 0;
+
+    let mut push = |start, end, newlines| {
+        let boundary = match newlines {
+            0 => Boundary::Word,
+            1 => Boundary::Line,
+            _ => Boundary::Paragraph,
+        };
+
+        separators.push(Separator {
+            start,
+            end,
+            boundary,
+        });
+    };
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            let (_, newlines) = run.get_or_insert((offset, 0));
+            if c == '\n' {
+                *newlines += 1;
+            }
+        } else if let Some((start, newlines)) = run.take() {
+            push(start, offset, newlines);
+        }
+
+        offset += c.len_utf16();
+    }
+
+    if let Some((start, newlines)) = run {
+        push(start, offset, newlines);
+    }
+
+    separators
+}
+
+/// Returns the part of the `text` in the `range` (in UTF-16 code units) with
+/// entities clipped to it.
+fn chunk(
+    text: &str,
+    entities: &[MessageEntity],
+    bounds: &[(usize, usize)],
+    range: Range<usize>,
+) -> (String, Vec<MessageEntity>) {
+    let byte_offset = |offset: usize| bounds[bounds.partition_point(|&(o, _)| o < offset)].1;
+    let text = text[byte_offset(range.start)..byte_offset(range.end)].to_owned();
+
+    let entities = entities
+        .iter()
+        .filter_map(|e| {
+            let start = cmp::max(e.offset, range.start);
+            let end = cmp::min(e.offset + e.length, range.end);
+
+            (start < end).then(|| MessageEntity {
+                kind: e.kind.clone(),
+                offset: start - range.start,
+                length: end - start,
+            })
+        })
+        .collect();
+
+    (text, entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split, MESSAGE_MAX_LEN};
+    use crate::types::MessageEntity;
+
+    #[test]
+    fn prefers_paragraphs() {
+        let text = "aaaa\n\nbbbb\ncccc dddd";
+        let entities = [MessageEntity::underline(4, 2)];
+
+        assert_eq!(
+            split(text, &entities, 15),
+            [
+                ("aaaa".to_owned(), vec![]),
+                ("bbbb\ncccc dddd".to_owned(), vec![]),
+            ]
+        );
+        assert_eq!(
+            split("aaaa bbbb\ncccc dddd", &[], 15),
+            [
+                ("aaaa bbbb".to_owned(), vec![]),
+                ("cccc dddd".to_owned(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn words() {
+        let entities = [MessageEntity::bold(0, 15)];
+
+        assert_eq!(
+            split("hello world foo", &entities, 11),
+            [
+                ("hello world".to_owned(), vec![MessageEntity::bold(0, 11)]),
+                ("foo".to_owned(), vec![MessageEntity::bold(0, 3)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        let entities = [MessageEntity::italic(0, 6)];
+
+        assert_eq!(
+            split("🦀🦀🦀", &entities, 3),
+            [
+                ("🦀".to_owned(), vec![MessageEntity::italic(0, 2)]),
+                ("🦀".to_owned(), vec![MessageEntity::italic(0, 2)]),
+                ("🦀".to_owned(), vec![MessageEntity::italic(0, 2)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn message_limit() {
+        let text = "слово ".repeat(2000);
+        let chunks = split(&text, &[], MESSAGE_MAX_LEN);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks
+            .iter()
+            .all(|(chunk, _)| chunk.encode_utf16().count() <= MESSAGE_MAX_LEN));
+
+        let joined: Vec<_> = chunks.iter().map(|(chunk, _)| chunk.as_str()).collect();
+        assert_eq!(joined.join(" "), text);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(split("", &[], MESSAGE_MAX_LEN).is_empty());
+    }
+}
@@ -0,0 +1,377 @@
+use url::Url;
+
+use super::utf16;
+use crate::types::{MessageEntity, MessageEntityKind, User, UserId};
+
+/// Builder of formatted text.
+///
+/// Builds plain text alongside with [`MessageEntity`]s (with offsets in UTF-16
+/// code units, as required by Telegram), so that no [`ParseMode`] or escaping
+/// is needed.
+///
+/// Entities can be nested, providing the following restrictions (enforced by
+/// Telegram) are met:
+/// - bold, italic, underline, strikethrough and spoiler entities can contain
+///   any entities.
+/// - Other entities (links, mentions, etc) can only contain bold, italic,
+///   underline, strikethrough and spoiler entities.
+/// - Code and pre entities can't contain any entities.
+///
+/// Entities with empty content are omitted, since Telegram ignores them.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{formatting::Text, UserId};
+///
+/// let url = "https://example.com".parse().unwrap();
+/// let (text, entities) = Text::new()
+///     .bold("Hello")
+///     .plain(", ")
+///     .link(url, Text::new().italic("world"))
+///     .plain("! Welcome, ")
+///     .mention(UserId(1), "user")
+///     .build();
+///
+/// assert_eq!(text, "Hello, world! Welcome, user");
+/// assert_eq!(entities.len(), 4);
+/// ```
+///
+/// [`ParseMode`]: crate::types::ParseMode
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Text {
+    text: String,
+    entities: Vec<MessageEntity>,
+
+    /// Length of `text` in UTF-16 code units.
+    len_utf16: usize,
+}
+
+impl Text {
+    /// Creates an empty text.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends plain text.
+    #[must_use]
+    pub fn plain(mut self, text: &str) -> Self {
+        self.text.push_str(text);
+        self.len_utf16 += utf16::len(text);
+        self
+    }
+
+    /// Appends another text, preserving its entities.
+    #[must_use]
+    pub fn append<T>(mut self, other: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        let Self {
+            text,
+            entities,
+            len_utf16,
+        } = other.into();
+
+        let offset = self.len_utf16;
+        self.entities
+            .extend(entities.into_iter().map(|entity| MessageEntity {
+                offset: entity.offset + offset,
+                ..entity
+            }));
+        self.text.push_str(&text);
+        self.len_utf16 += len_utf16;
+        self
+    }
+
+    /// Appends bold text.
+    #[must_use]
+    pub fn bold<T>(self, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::Bold, content.into())
+    }
+
+    /// Appends italic text.
+    #[must_use]
+    pub fn italic<T>(self, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::Italic, content.into())
+    }
+
+    /// Appends underlined text.
+    #[must_use]
+    pub fn underline<T>(self, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::Underline, content.into())
+    }
+
+    /// Appends strikethrough text.
+    #[must_use]
+    pub fn strikethrough<T>(self, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::Strikethrough, content.into())
+    }
+
+    /// Appends spoiler text.
+    #[must_use]
+    pub fn spoiler<T>(self, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::Spoiler, content.into())
+    }
+
+    /// Appends inline fixed-width code.
+    #[must_use]
+    pub fn code(self, code: &str) -> Self {
+        self.wrap(MessageEntityKind::Code, Text::from(code))
+    }
+
+    /// Appends pre-formatted fixed-width code block, optionally written in the
+    /// programming `language`.
+    #[must_use]
+    pub fn pre(self, language: Option<&str>, code: &str) -> Self {
+        let kind = MessageEntityKind::Pre {
+            language: language.map(<_>::to_owned),
+        };
+
+        self.wrap(kind, Text::from(code))
+    }
+
+    /// Appends a link to the `url`.
+    ///
+    /// ## Panics
+    ///
+    /// If `content` contains entities other than bold, italic, underline,
+    /// strikethrough and spoiler.
+    #[must_use]
+    pub fn link<T>(self, url: Url, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::TextLink { url }, content.into())
+    }
+
+    /// Appends a mention of the user with the `user_id` (a link in the form of
+    /// `tg://user/?id=...`).
+    ///
+    /// See also notes about such links in [`ParseMode`] docs.
+    ///
+    /// ## Panics
+    ///
+    /// If `content` contains entities other than bold, italic, underline,
+    /// strikethrough and spoiler.
+    ///
+    /// [`ParseMode`]: crate::types::ParseMode
+    #[must_use]
+    pub fn mention<T>(self, user_id: UserId, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        let kind = MessageEntityKind::TextLink { url: user_id.url() };
+        self.wrap(kind, content.into())
+    }
+
+    /// Appends a mention of the `user`, for users without usernames.
+    ///
+    /// ## Panics
+    ///
+    /// If `content` contains entities other than bold, italic, underline,
+    /// strikethrough and spoiler.
+    #[must_use]
+    pub fn text_mention<T>(self, user: User, content: T) -> Self
+    where
+        T: Into<Text>,
+    {
+        self.wrap(MessageEntityKind::TextMention { user }, content.into())
+    }
+
+    /// Appends a custom emoji, the `emoji` is shown by clients which can't
+    /// display custom emojis.
+    #[must_use]
+    pub fn custom_emoji(self, custom_emoji_id: String, emoji: &str) -> Self {
+        let kind = MessageEntityKind::CustomEmoji { custom_emoji_id };
+        self.wrap(kind, Text::from(emoji))
+    }
+
+    /// Returns the text built so far, without entities.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns entities built so far.
+    #[must_use]
+    pub fn entities(&self) -> &[MessageEntity] {
+        &self.entities
+    }
+
+    /// Returns the length of the text in UTF-16 code units, i.e. the length
+    /// which is limited by Telegram.
+    #[must_use]
+    pub fn len_utf16(&self) -> usize {
+        self.len_utf16
+    }
+
+    /// Returns `true` if the text has no characters.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the text and its entities, which can be sent e.g. with
+    /// [`SendMessageSetters::entities`].
+    ///
+    /// Entities are sorted by their offsets, outer entities go first.
+    ///
+    /// [`SendMessageSetters::entities`]: crate::payloads::SendMessageSetters::entities
+    #[must_use]
+    pub fn build(self) -> (String, Vec<MessageEntity>) {
+        (self.text, self.entities)
+    }
+
+    fn wrap(mut self, kind: MessageEntityKind, content: Text) -> Self {
+        if let Some(inner) = content
+            .entities
+            .iter()
+            .find(|inner| !can_contain(&kind, &inner.kind))
+        {
+            panic!(
+                "{} entity can't contain {} entity",
+                kind_name(&kind),
+                kind_name(&inner.kind)
+            );
+        }
+
+        if content.len_utf16 != 0 {
+            let entity = MessageEntity::new(kind, self.len_utf16, content.len_utf16);
+            self.entities.push(entity);
+        }
+
+        self.append(content)
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self {
+        Self::new().plain(text)
+    }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Self {
+        let len_utf16 = utf16::len(&text);
+
+        Self {
+            text,
+            entities: Vec::new(),
+            len_utf16,
+        }
+    }
+}
+
+impl From<Text> for (String, Vec<MessageEntity>) {
+    fn from(text: Text) -> Self {
+        text.build()
+    }
+}
+
+/// Returns `true` if an entity of kind `inner` can be nested in an entity of
+/// kind `outer`, according to the rules documented on [`Text`].
+pub(super) fn can_contain(outer: &MessageEntityKind, inner: &MessageEntityKind) -> bool {
+    use MessageEntityKind::*;
+
+    match outer {
+        Bold | Italic | Underline | Strikethrough | Spoiler => true,
+        Code | Pre { .. } => false,
+        _ => matches!(inner, Bold | Italic | Underline | Strikethrough | Spoiler),
+    }
+}
+
+/// Returns the name of the entity kind, as used by Telegram in error messages.
+pub(super) fn kind_name(kind: &MessageEntityKind) -> &'static str {
+    use MessageEntityKind::*;
+
+    match kind {
+        Mention => "Mention",
+        Hashtag => "Hashtag",
+        Cashtag => "Cashtag",
+        BotCommand => "BotCommand",
+        Url => "Url",
+        Email => "Email",
+        PhoneNumber => "PhoneNumber",
+        Bold => "Bold",
+        Italic => "Italic",
+        Underline => "Underline",
+        Strikethrough => "Strikethrough",
+        Spoiler => "Spoiler",
+        Code => "Code",
+        Pre { .. } => "Pre",
+        TextLink { .. } => "TextUrl",
+        TextMention { .. } => "MentionName",
+        CustomEmoji { .. } => "CustomEmoji",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Text;
+    use crate::types::{MessageEntity, UserId};
+
+    #[test]
+    fn utf16_offsets() {
+        let (text, entities) = Text::new()
+            .plain("🦀 ")
+            .bold(Text::new().plain("быба ").italic("🦀"))
+            .code("x")
+            .build();
+
+        assert_eq!(text, "🦀 быба 🦀x");
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::bold(3, 7),
+                MessageEntity::italic(8, 2),
+                MessageEntity::code(10, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn mention() {
+        let (_, entities) = Text::new().plain("hi ").mention(UserId(42), "user").build();
+        assert_eq!(entities, [MessageEntity::text_mention_id(UserId(42), 3, 4)]);
+    }
+
+    #[test]
+    fn empty_entities_are_omitted() {
+        let text = Text::new().bold("").italic(Text::new());
+        assert!(text.is_empty());
+        assert!(text.entities().is_empty());
+    }
+
+    #[test]
+    fn code_in_bold() {
+        let text = Text::new().bold(Text::new().plain("a").code("@"));
+        assert_eq!(
+            text.entities(),
+            [MessageEntity::bold(0, 2), MessageEntity::code(1, 1)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "TextUrl entity can't contain TextUrl entity")]
+    fn link_in_link() {
+        let url: url::Url = "https://example.com".parse().unwrap();
+        let _ = Text::new().link(url.clone(), Text::new().link(url, "x"));
+    }
+}
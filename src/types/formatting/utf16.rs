@@ -0,0 +1,222 @@
+//! Conversions between UTF-16 code units (which are used by Telegram in
+//! [`MessageEntity`] offsets and lengths) and bytes (which are used by Rust
+//! strings).
+//!
+//! All offsets are validated against the text, i.e. they must be in bounds of
+//! the text and point to a character boundary. Ranges must not be reversed.
+//!
+//! ## Examples
+//!
+//! ```
+//! use teloxide_core::types::{formatting::utf16, MessageEntity, MessageEntityKind};
+//!
+//! let text = "🦀 is a crab";
+//! let start = text.find("crab").unwrap();
+//!
+//! assert_eq!(utf16::len(text), 12);
+//! assert_eq!(utf16::to_utf16_offset(text, start), Ok(8));
+//! assert_eq!(utf16::to_byte_offset(text, 8), Ok(start));
+//! assert_eq!(utf16::slice(text, 0..2), Ok("🦀"));
+//!
+//! let entity = utf16::entity(text, MessageEntityKind::Bold, start..text.len());
+//! assert_eq!(entity, Ok(MessageEntity::bold(8, 4)));
+//! ```
+
+use std::ops::Range;
+
+use crate::types::{MessageEntity, MessageEntityKind};
+
+/// Error returned when an offset is invalid for the text.
+///
+/// Offsets are reported as they were passed to the function, i.e. in bytes or
+/// in UTF-16 code units, depending on the function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum OffsetError {
+    /// The offset is greater than the length of the text.
+    #[error(
+        "The offset {offset} is out of bounds of the text of length {len} bytes ({len_utf16} \
+         UTF-16 code units)"
+    )]
+    OutOfBounds {
+        offset: usize,
+        /// The length of the text in bytes.
+        len: usize,
+        /// The length of the text in UTF-16 code units.
+        len_utf16: usize,
+    },
+
+    /// The offset points to the middle of a character (or a surrogate pair).
+    #[error("The offset {offset} is not a character boundary")]
+    NotCharBoundary { offset: usize },
+
+    /// The start of the range is greater than its end.
+    #[error("The range {start}..{end} is reversed")]
+    ReversedRange { start: usize, end: usize },
+}
+
+/// Returns the length of the `text` in UTF-16 code units, i.e. the length used
+/// by Telegram.
+#[must_use]
+pub fn len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Converts an offset in bytes into an offset in UTF-16 code units.
+pub fn to_utf16_offset(text: &str, byte_offset: usize) -> Result<usize, OffsetError> {
+    if byte_offset > text.len() {
+        return Err(OffsetError::OutOfBounds {
+            offset: byte_offset,
+            len: text.len(),
+            len_utf16: len(text),
+        });
+    }
+
+    if !text.is_char_boundary(byte_offset) {
+        return Err(OffsetError::NotCharBoundary {
+            offset: byte_offset,
+        });
+    }
+
+    Ok(len(&text[..byte_offset]))
+}
+
+/// Converts an offset in UTF-16 code units into an offset in bytes.
+pub fn to_byte_offset(text: &str, utf16_offset: usize) -> Result<usize, OffsetError> {
+    let mut len_utf16 = 0;
+
+    for (byte_offset, c) in text.char_indices() {
+        if len_utf16 >= utf16_offset {
+            break;
+        }
+
+        len_utf16 += c.len_utf16();
+
+        if len_utf16 == utf16_offset {
+            return Ok(byte_offset + c.len_utf8());
+        }
+    }
+
+    if utf16_offset == 0 {
+        Ok(0)
+    } else if len_utf16 > utf16_offset {
+        Err(OffsetError::NotCharBoundary {
+            offset: utf16_offset,
+        })
+    } else {
+        Err(OffsetError::OutOfBounds {
+            offset: utf16_offset,
+            len: text.len(),
+            len_utf16,
+        })
+    }
+}
+
+/// Returns the part of the `text` in the `range` of UTF-16 code units (e.g. the
+/// text of a [`MessageEntity`]).
+pub fn slice(text: &str, range: Range<usize>) -> Result<&str, OffsetError> {
+    check_range(&range)?;
+
+    let start = to_byte_offset(text, range.start)?;
+    let end = to_byte_offset(text, range.end)?;
+
+    Ok(&text[start..end])
+}
+
+/// Creates an entity of the `kind` which covers the `range` of bytes of the
+/// `text`.
+pub fn entity(
+    text: &str,
+    kind: MessageEntityKind,
+    range: Range<usize>,
+) -> Result<MessageEntity, OffsetError> {
+    check_range(&range)?;
+
+    let start = to_utf16_offset(text, range.start)?;
+    let end = to_utf16_offset(text, range.end)?;
+
+    Ok(MessageEntity::new(kind, start, end - start))
+}
+
+fn check_range(range: &Range<usize>) -> Result<(), OffsetError> {
+    if range.start > range.end {
+        return Err(OffsetError::ReversedRange {
+            start: range.start,
+            end: range.end,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entity, len, slice, to_byte_offset, to_utf16_offset, OffsetError};
+    use crate::types::{MessageEntity, MessageEntityKind};
+
+    const TEXT: &str = "a🦀ы";
+
+    #[test]
+    fn conversions() {
+        assert_eq!(len(TEXT), 4);
+
+        for (byte_offset, utf16_offset) in [(0, 0), (1, 1), (5, 3), (7, 4)] {
+            assert_eq!(to_utf16_offset(TEXT, byte_offset), Ok(utf16_offset));
+            assert_eq!(to_byte_offset(TEXT, utf16_offset), Ok(byte_offset));
+        }
+
+        assert_eq!(to_byte_offset("", 0), Ok(0));
+        assert_eq!(to_utf16_offset("", 0), Ok(0));
+    }
+
+    #[test]
+    fn invalid_offsets() {
+        assert_eq!(
+            to_utf16_offset(TEXT, 2),
+            Err(OffsetError::NotCharBoundary { offset: 2 })
+        );
+        assert_eq!(
+            to_utf16_offset(TEXT, 8),
+            Err(OffsetError::OutOfBounds {
+                offset: 8,
+                len: 7,
+                len_utf16: 4
+            })
+        );
+        assert_eq!(
+            to_byte_offset(TEXT, 2),
+            Err(OffsetError::NotCharBoundary { offset: 2 })
+        );
+        assert_eq!(
+            to_byte_offset(TEXT, 5),
+            Err(OffsetError::OutOfBounds {
+                offset: 5,
+                len: 7,
+                len_utf16: 4
+            })
+        );
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn slices_and_entities() {
+        assert_eq!(slice(TEXT, 1..3), Ok("🦀"));
+        assert_eq!(slice(TEXT, 3..3), Ok(""));
+        assert_eq!(
+            slice(TEXT, 3..1),
+            Err(OffsetError::ReversedRange { start: 3, end: 1 })
+        );
+        assert_eq!(
+            slice(TEXT, 1..2),
+            Err(OffsetError::NotCharBoundary { offset: 2 })
+        );
+
+        assert_eq!(
+            entity(TEXT, MessageEntityKind::Code, 1..7),
+            Ok(MessageEntity::code(1, 3))
+        );
+        assert_eq!(
+            entity(TEXT, MessageEntityKind::Code, 7..1),
+            Err(OffsetError::ReversedRange { start: 7, end: 1 })
+        );
+    }
+}
@@ -7,7 +7,7 @@ use once_cell::sync::OnceCell;
 use rc_box::ArcBox;
 use reqwest::{multipart::Part, Body};
 use serde::Serialize;
-use takecell::TakeCell;
+use takecell::{TakeCell, TakeOwnCell};
 use tokio::{
     io::{AsyncRead, AsyncReadExt, ReadBuf},
     sync::watch,
@@ -41,6 +41,7 @@ pub struct InputFile {
 #[derive(Clone)]
 enum InnerFile {
     Read(Read),
+    Stream(ByteStream),
     File(PathBuf),
     Bytes(bytes::Bytes),
     Url(url::Url),
@@ -113,6 +114,45 @@ impl InputFile {
         Self::new(Read(Read::new(Arc::new(TakeCell::new(it)))))
     }
 
+    /// Creates an `InputFile` from a stream of bytes.
+    ///
+    /// Unlike [`InputFile::read`], this never reads the stream into memory,
+    /// the chunks are forwarded to the request body as they come. Because of
+    /// this the stream can be uploaded only once: if the `InputFile` is cloned
+    /// (e.g. by [`send_ref`]) and uploaded multiple times, only the first
+    /// upload receives the data and all the others fail with an i/o error.
+    ///
+    /// This also means that requests with streams can't be retried by bot
+    /// adaptors: [`Throttle`] (with retries enabled, which is the default)
+    /// sends requests with `send_ref` and [`AutoMigrate`] sends the request
+    /// again after a migration. Such retries fail before anything is sent,
+    /// with an [`io::Error`] (in [`RequestError::Io`]) saying that the stream
+    /// was already uploaded.
+    ///
+    /// [`send_ref`]: crate::requests::Request::send_ref
+    /// [`Throttle`]: crate::adaptors::Throttle
+    /// [`AutoMigrate`]: crate::adaptors::AutoMigrate
+    /// [`RequestError::Io`]: crate::RequestError::Io
+    pub fn stream<S>(stream: S) -> Self
+    where
+        S: futures::Stream<Item = io::Result<bytes::Bytes>> + Send + 'static,
+    {
+        Self::new(Stream(ByteStream::new(Box::pin(stream), None)))
+    }
+
+    /// Creates an `InputFile` from a stream of bytes of a known length.
+    ///
+    /// The length is used as the length of the `multipart/form-data` part, so
+    /// it must be equal to the total number of bytes yielded by the stream.
+    ///
+    /// See [`InputFile::stream`] for more information.
+    pub fn stream_with_length<S>(stream: S, length: u64) -> Self
+    where
+        S: futures::Stream<Item = io::Result<bytes::Bytes>> + Send + 'static,
+    {
+        Self::new(Stream(ByteStream::new(Box::pin(stream), Some(length))))
+    }
+
     /// Shorthand for `Self { file_name: None, inner, id: default() }`
     /// (private because `InnerFile` iы private implementation detail)
     fn new(inner: InnerFile) -> Self {
@@ -138,6 +178,48 @@ impl InputFile {
         !matches!(self.inner, Url(_) | FileId(_))
     }
 
+    /// Returns the path of this file, if it was created with
+    /// [`InputFile::file`].
+    pub(crate) fn path(&self) -> Option<&std::path::Path> {
+        match &self.inner {
+            File(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the data of this file, if it was created with
+    /// [`InputFile::memory`].
+    pub(crate) fn bytes(&self) -> Option<&[u8]> {
+        match &self.inner {
+            Bytes(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns the length of this file, if it was created with
+    /// [`InputFile::stream_with_length`].
+    pub(crate) fn stream_length(&self) -> Option<u64> {
+        match &self.inner {
+            Stream(stream) => stream.length,
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this file is a stream which was already taken by an
+    /// upload (of this file or of its clone), so it can't be uploaded again.
+    pub(crate) fn is_uploaded_stream(&self) -> bool {
+        matches!(&self.inner, Stream(stream) if stream.inner.is_taken())
+    }
+
+    /// The error returned when a stream is uploaded for the second time.
+    pub(crate) fn uploaded_stream_error() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "the stream of this `InputFile` was already uploaded, requests with streams can't be \
+             sent again (e.g. retried)",
+        )
+    }
+
     /// Takes this file out.
     ///
     /// **Note**: this replaces `self` with a dummy value, this function should
@@ -150,7 +232,7 @@ impl InputFile {
     /// `"attach://{id}"` if this file should be uploaded via
     /// `multipart/form-data`, or the value if it may be uploaded in any way (ie
     /// it's an URL or file id).
-    fn attach_or_value(&self) -> String {
+    pub(crate) fn attach_or_value(&self) -> String {
         match &self.inner {
             Url(url) => url.as_str().to_owned(),
             FileId(file_id) => file_id.clone(),
@@ -184,6 +266,10 @@ impl fmt::Debug for InnerFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Read(_) => f.debug_struct("Read").finish_non_exhaustive(),
+            Stream(ByteStream { length, .. }) => f
+                .debug_struct("Stream")
+                .field("length", length)
+                .finish_non_exhaustive(),
             File(path) => f.debug_struct("File").field("path", path).finish(),
             Bytes(bytes) if f.alternate() => f.debug_tuple("Memory").field(bytes).finish(),
             Bytes(_) => f.debug_struct("Memory").finish_non_exhaustive(),
@@ -236,6 +322,10 @@ impl InputFile {
                 let stream = Part::stream(data).file_name(filename);
                 Some(Either::Right(Either::Left(ready(stream))))
             }
+            Stream(stream) => {
+                let part = stream.into_part(filename);
+                Some(Either::Right(Either::Left(ready(part))))
+            }
             Read(read) => Some(Either::Right(Either::Right(read.into_part(filename)))),
         }
     }
@@ -371,6 +461,78 @@ impl Read {
     }
 }
 
+type BoxedByteStream = Pin<Box<dyn futures::Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// Adaptor for `Stream` of bytes that allows cloning and converting to
+/// `multipart/form-data` without buffering.
+///
+/// The stream is shared by all clones, the first clone to be uploaded takes it
+/// out of the `TakeOwnCell` (so it's taken at most once, even if clones are
+/// uploaded concurrently). `TakeOwnCell` is `Sync` because the stream is
+/// `Send`, which makes `InputFile` `Sync` without a lock.
+#[derive(Clone)]
+struct ByteStream {
+    inner: Arc<TakeOwnCell<BoxedByteStream>>,
+    length: Option<u64>,
+}
+
+impl ByteStream {
+    fn new(stream: BoxedByteStream, length: Option<u64>) -> Self {
+        Self {
+            inner: Arc::new(TakeOwnCell::new(stream)),
+            length,
+        }
+    }
+
+    fn into_part(self, filename: Cow<'static, str>) -> Part {
+        let part = match self.inner.take() {
+            // The first copy to be uploaded gets the stream
+            Some(stream) => {
+                let body = Body::wrap_stream(ExclusiveStream(TakeOwnCell::new(stream)));
+
+                match self.length {
+                    Some(length) => Part::stream_with_length(body, length),
+                    None => Part::stream(body),
+                }
+            }
+
+            // The stream was already taken by another copy, we can't upload it again without
+            // buffering. This is usually caught earlier (see `InputFile::is_uploaded_stream`),
+            // but copies may be uploaded concurrently
+            None => {
+                let err = InputFile::uploaded_stream_error();
+
+                // explicit type needed for `Bytes: From<?T>` in `wrap_stream`
+                Part::stream(Body::wrap_stream(stream::iter([Err::<Bytes, _>(err)])))
+            }
+        };
+
+        part.file_name(filename)
+    }
+}
+
+/// Wrapper over a boxed stream that implements `Sync` (which is required by
+/// `reqwest`).
+///
+/// `TakeOwnCell` is only used because it's `Sync` when the value is `Send`, the
+/// value is never taken out of it.
+struct ExclusiveStream(TakeOwnCell<BoxedByteStream>);
+
+impl futures::Stream for ExclusiveStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        let Self(inner) = Pin::get_mut(self);
+        match inner.get() {
+            Some(stream) => futures::Stream::poll_next(stream.as_mut(), cx),
+            None => task::Poll::Ready(None),
+        }
+    }
+}
+
 /// Wrapper over an `ArcBox` that implements `AsyncRead`.
 struct ExclusiveArcAsyncRead(ArcBox<TakeCell<dyn AsyncRead + Send + Unpin>>);
 
@@ -407,6 +569,10 @@ pub(crate) trait InputFileLike {
     fn copy_into(&self, into: &mut dyn FnMut(InputFile));
 
     fn move_into(&mut self, into: &mut dyn FnMut(InputFile));
+
+    /// Calls `f` with all files and the names of the fields they are in,
+    /// `field` is the name of the field `self` is in.
+    fn for_each(&self, field: &'static str, f: &mut dyn FnMut(&'static str, &InputFile));
 }
 
 impl InputFileLike for InputFile {
@@ -417,6 +583,10 @@ impl InputFileLike for InputFile {
     fn move_into(&mut self, into: &mut dyn FnMut(InputFile)) {
         into(self.take())
     }
+
+    fn for_each(&self, field: &'static str, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        f(field, self)
+    }
 }
 
 impl InputFileLike for Option<InputFile> {
@@ -431,6 +601,12 @@ impl InputFileLike for Option<InputFile> {
             this.move_into(into)
         }
     }
+
+    fn for_each(&self, field: &'static str, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        if let Some(this) = self {
+            this.for_each(field, f)
+        }
+    }
 }
 
 impl InputFileLike for InputSticker {
@@ -445,4 +621,15 @@ impl InputFileLike for InputSticker {
 
         input_file.move_into(into)
     }
+
+    fn for_each(&self, _field: &'static str, f: &mut dyn FnMut(&'static str, &InputFile)) {
+        // Use the same field names as in serialization
+        let (field, input_file) = match self {
+            Self::Png(input_file) => ("png_sticker", input_file),
+            Self::Tgs(input_file) => ("tgs_sticker", input_file),
+            Self::Webm(input_file) => ("webm_sticker", input_file),
+        };
+
+        f(field, input_file)
+    }
 }
@@ -511,6 +511,26 @@ impl InputMedia {
         iter::once(media).chain(thumb)
     }
 
+    /// Returns an iterator of all files in this input media alongside with
+    /// their kind (`"photo"`, `"video"`, etc, or `"thumb"` for thumbnails)
+    pub(crate) fn named_files(&self) -> impl Iterator<Item = (&'static str, &InputFile)> {
+        use InputMedia::*;
+
+        let (kind, media, thumb) = match self {
+            Photo(InputMediaPhoto { media, .. }) => ("photo", media, None),
+            Document(InputMediaDocument { media, thumb, .. }) => {
+                ("document", media, thumb.as_ref())
+            }
+            Audio(InputMediaAudio { media, thumb, .. }) => ("audio", media, thumb.as_ref()),
+            Animation(InputMediaAnimation { media, thumb, .. }) => {
+                ("animation", media, thumb.as_ref())
+            }
+            Video(InputMediaVideo { media, thumb, .. }) => ("video", media, thumb.as_ref()),
+        };
+
+        iter::once((kind, media)).chain(thumb.map(|thumb| ("thumb", thumb)))
+    }
+
     /// Returns an iterator of all files in this input media
     pub(crate) fn files_mut(&mut self) -> impl Iterator<Item = &mut InputFile> {
         use InputMedia::*;
@@ -2,7 +2,10 @@ use std::ops::Deref;
 
 use serde::{Deserialize, Serialize};
 
-use crate::types::User;
+use crate::types::{
+    deep_link::{self, StartParamError},
+    User,
+};
 
 /// Returned only in [`GetMe`].
 ///
@@ -45,6 +48,27 @@ impl Me {
     pub fn tme_url(&self) -> reqwest::Url {
         format!("https://t.me/{}", self.username()).parse().unwrap()
     }
+
+    /// Returns a deep link which opens a private chat with this bot and sends
+    /// it `/start <param>`, see [`deep_link::start_url`].
+    #[must_use = "this only builds the link, it doesn't send anything"]
+    pub fn start_url(&self, param: &str) -> Result<reqwest::Url, StartParamError> {
+        deep_link::start_url(self.username(), param)
+    }
+
+    /// Returns a deep link which adds this bot to a group and sends it
+    /// `/start <param>` there, see [`deep_link::start_group_url`].
+    #[must_use = "this only builds the link, it doesn't send anything"]
+    pub fn start_group_url(&self, param: &str) -> Result<reqwest::Url, StartParamError> {
+        deep_link::start_group_url(self.username(), param)
+    }
+
+    /// Returns a deep link which adds this bot to the attachment menu, see
+    /// [`deep_link::start_attach_url`].
+    #[must_use = "this only builds the link, it doesn't send anything"]
+    pub fn start_attach_url(&self, param: Option<&str>) -> Result<reqwest::Url, StartParamError> {
+        deep_link::start_attach_url(self.username(), param)
+    }
 }
 
 impl Deref for Me {
@@ -83,5 +107,17 @@ mod tests {
             me.tme_url(),
             "https://t.me/SomethingSomethingBot".parse().unwrap()
         );
+        assert_eq!(
+            me.start_url("abc").unwrap(),
+            "https://t.me/SomethingSomethingBot?start=abc"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(
+            me.start_group_url("abc").unwrap(),
+            "https://t.me/SomethingSomethingBot?startgroup=abc"
+                .parse()
+                .unwrap()
+        );
     }
 }
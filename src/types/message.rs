@@ -5,11 +5,12 @@ use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::types::{
-    Animation, Audio, BareChatId, Chat, ChatId, Contact, Dice, Document, Game,
-    InlineKeyboardMarkup, Invoice, Location, MessageAutoDeleteTimerChanged, MessageEntity,
-    MessageEntityRef, MessageId, PassportData, PhotoSize, Poll, ProximityAlertTriggered, Sticker,
-    SuccessfulPayment, True, User, Venue, Video, VideoChatEnded, VideoChatParticipantsInvited,
-    VideoChatScheduled, VideoChatStarted, VideoNote, Voice, WebAppData,
+    formatting, Animation, Audio, BareChatId, BotCommandRef, Chat, ChatId, Contact, Dice, Document,
+    Game, InlineKeyboardMarkup, Invoice, Location, MessageAutoDeleteTimerChanged, MessageEntity,
+    MessageEntityKind, MessageEntityRef, MessageId, PassportData, PhotoSize, Poll,
+    ProximityAlertTriggered, Sticker, SuccessfulPayment, True, User, UserId, Venue, Video,
+    VideoChatEnded, VideoChatParticipantsInvited, VideoChatScheduled, VideoChatStarted, VideoNote,
+    Voice, WebAppData,
 };
 
 /// This object represents a message.
@@ -1319,6 +1320,97 @@ impl Message {
             .zip(self.caption_entities())
             .map(|(t, e)| MessageEntityRef::parse(t, e))
     }
+
+    /// Returns the bot command which this message starts with, if any.
+    ///
+    /// Like Telegram, only considers a command in the beginning of the text
+    /// (or the caption, for media messages), arguments of the command are the
+    /// rest of the text.
+    ///
+    /// Note that in groups commands can be addressed to other bots, use
+    /// [`BotCommandRef::is_addressed_to`] to check that.
+    #[must_use]
+    pub fn bot_command(&self) -> Option<BotCommandRef<'_>> {
+        let entities = self
+            .parse_entities()
+            .or_else(|| self.parse_caption_entities())?;
+
+        entities
+            .iter()
+            .filter(|entity| entity.start() == 0)
+            .find_map(BotCommandRef::from_entity)
+    }
+
+    /// Returns all URLs in the text (or the caption) of this message, including
+    /// targets of inline links.
+    ///
+    /// URLs without a scheme (e.g. `example.com`) are treated as HTTP URLs.
+    /// URLs which Telegram has marked as such, but which can't be parsed by
+    /// [`Url::parse`] (e.g. with an invalid IPv6 address), are skipped.
+    pub fn urls(&self) -> impl Iterator<Item = Url> + '_ {
+        self.text_entities()
+            .filter_map(|entity| match entity.kind() {
+                MessageEntityKind::Url => formatting::parse_url(entity.text()),
+                MessageEntityKind::TextLink { url } => Some(url.clone()),
+                _ => None,
+            })
+    }
+
+    /// Returns usernames mentioned in the text (or the caption) of this
+    /// message, without the leading `@`.
+    pub fn mentions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.text_entities_of(MessageEntityKind::Mention)
+            .map(|entity| {
+                let text = entity.text();
+                text.strip_prefix('@').unwrap_or(text)
+            })
+    }
+
+    /// Returns ids of users without usernames mentioned in the text (or the
+    /// caption) of this message.
+    pub fn text_mentions(&self) -> impl Iterator<Item = UserId> + '_ {
+        self.text_entities()
+            .filter_map(|entity| match entity.kind() {
+                MessageEntityKind::TextMention { user } => Some(user.id),
+                _ => None,
+            })
+    }
+
+    /// Returns hashtags in the text (or the caption) of this message, without
+    /// the leading `#`.
+    pub fn hashtags(&self) -> impl Iterator<Item = &str> + '_ {
+        self.text_entities_of(MessageEntityKind::Hashtag)
+            .map(|entity| {
+                let text = entity.text();
+                text.strip_prefix('#').unwrap_or(text)
+            })
+    }
+
+    /// Returns cashtags (e.g. `$USD`) in the text (or the caption) of this
+    /// message, without the leading `$`.
+    pub fn cashtags(&self) -> impl Iterator<Item = &str> + '_ {
+        self.text_entities_of(MessageEntityKind::Cashtag)
+            .map(|entity| {
+                let text = entity.text();
+                text.strip_prefix('$').unwrap_or(text)
+            })
+    }
+
+    /// Returns entities of the text or the caption of this message.
+    fn text_entities(&self) -> impl Iterator<Item = MessageEntityRef<'_>> {
+        self.parse_entities()
+            .or_else(|| self.parse_caption_entities())
+            .into_iter()
+            .flatten()
+    }
+
+    fn text_entities_of(
+        &self,
+        kind: MessageEntityKind,
+    ) -> impl Iterator<Item = MessageEntityRef<'_>> {
+        self.text_entities()
+            .filter(move |entity| *entity.kind() == kind)
+    }
 }
 
 #[cfg(test)]
@@ -1767,4 +1859,143 @@ mod tests {
         assert!(!entities.is_empty());
         assert_eq!(entities[0].kind().clone(), MessageEntityKind::Url);
     }
+
+    #[test]
+    fn bot_command() {
+        let json = r#"{
+            "message_id": 3460,
+            "from": {
+              "id": 27433968,
+              "is_bot": false,
+              "first_name": "Crax | rats addict",
+              "username": "tacocrasco",
+              "language_code": "en"
+            },
+            "chat": {
+              "id": -1001160242915,
+              "title": "a",
+              "type": "supergroup"
+            },
+            "date": 1655671349,
+            "text": "/ban@some_bot 🦀 spam",
+            "entities": [
+              {
+                "offset": 0,
+                "length": 13,
+                "type": "bot_command"
+              }
+            ]
+        }"#;
+
+        let message: Message = serde_json::from_str(json).unwrap();
+        let command = message.bot_command().unwrap();
+
+        assert_eq!(command.command(), "ban");
+        assert_eq!(command.bot_username(), Some("some_bot"));
+        assert_eq!(command.args(), "🦀 spam");
+    }
+
+    #[test]
+    fn bot_command_in_caption() {
+        let json = r#"{
+            "message_id": 3460,
+            "from": {
+              "id": 27433968,
+              "is_bot": false,
+              "first_name": "Crax | rats addict",
+              "username": "tacocrasco",
+              "language_code": "en"
+            },
+            "chat": {
+              "id": 27433968,
+              "first_name": "Crax | rats addict",
+              "username": "tacocrasco",
+              "type": "private"
+            },
+            "date": 1655671349,
+            "photo": [
+              {
+                "file_id": "AgACAgQAAxkBAAINhGKvijUVSn2i3980bQIIc1fqWGNCAAJpvDEbEmaBUfuA43fR-BnlAQADAgADcwADJAQ",
+                "file_unique_id": "AQADabwxGxJmgVF4",
+                "file_size": 2077,
+                "width": 90,
+                "height": 90
+              }
+            ],
+            "caption": "hi /start",
+            "caption_entities": [
+              {
+                "offset": 3,
+                "length": 6,
+                "type": "bot_command"
+              }
+            ]
+        }"#;
+
+        let mut message: Message = serde_json::from_str(json).unwrap();
+        // Commands are only recognized in the beginning of the text
+        assert_eq!(message.bot_command(), None);
+
+        if let MessageKind::Common(MessageCommon {
+            media_kind: MediaKind::Photo(photo),
+            ..
+        }) = &mut message.kind
+        {
+            photo.caption = Some("/start hi".to_owned());
+            photo.caption_entities[0].offset = 0;
+        }
+
+        let command = message.bot_command().unwrap();
+        assert_eq!(command.command(), "start");
+        assert_eq!(command.bot_username(), None);
+        assert_eq!(command.args(), "hi");
+    }
+
+    #[test]
+    fn extract_entities() {
+        let json = r#"{
+            "message_id": 3460,
+            "from": {
+              "id": 27433968,
+              "is_bot": false,
+              "first_name": "Crax | rats addict",
+              "username": "tacocrasco",
+              "language_code": "en"
+            },
+            "chat": {
+              "id": -1001160242915,
+              "title": "a",
+              "type": "supergroup"
+            },
+            "date": 1655671349,
+            "text": "see example.com, docs, @teloxide, Name #rust $USD",
+            "entities": [
+              { "offset": 4, "length": 11, "type": "url" },
+              { "offset": 17, "length": 4, "type": "text_link", "url": "https://docs.rs/" },
+              { "offset": 23, "length": 9, "type": "mention" },
+              {
+                "offset": 34,
+                "length": 4,
+                "type": "text_mention",
+                "user": { "id": 1, "is_bot": false, "first_name": "Name" }
+              },
+              { "offset": 39, "length": 5, "type": "hashtag" },
+              { "offset": 45, "length": 4, "type": "cashtag" }
+            ]
+        }"#;
+
+        let message: Message = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            message.urls().collect::<Vec<_>>(),
+            [
+                "http://example.com".parse::<url::Url>().unwrap(),
+                "https://docs.rs/".parse().unwrap()
+            ]
+        );
+        assert_eq!(message.mentions().collect::<Vec<_>>(), ["teloxide"]);
+        assert_eq!(message.text_mentions().collect::<Vec<_>>(), [UserId(1)]);
+        assert_eq!(message.hashtags().collect::<Vec<_>>(), ["rust"]);
+        assert_eq!(message.cashtags().collect::<Vec<_>>(), ["USD"]);
+    }
 }
@@ -0,0 +1,233 @@
+use std::str::FromStr;
+
+use url::Url;
+
+use crate::types::{formatting, BareChatId, MessageId, Recipient, MAX_BARE_CHANNEL_ID};
+
+/// A parsed `t.me` link to a message, e.g. `https://t.me/c/1234/56`.
+///
+/// This is the inverse of [`Message::url_of`], [`Message::comment_url_of`] and
+/// [`Message::url_in_thread_of`], the parsed chat and message can be used to
+/// forward or copy the message.
+///
+/// [`Message::url_of`]: crate::types::Message::url_of
+/// [`Message::comment_url_of`]: crate::types::Message::comment_url_of
+/// [`Message::url_in_thread_of`]: crate::types::Message::url_in_thread_of
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MessageLink {
+    /// The chat the message belongs to.
+    ///
+    /// This is [`Recipient::ChannelUsername`] for links to public chats and
+    /// [`Recipient::Id`] for links to private supergroups and channels.
+    pub chat: Recipient,
+
+    /// Identifier of the message (or the channel post, if there is a
+    /// `comment_id`).
+    pub message_id: MessageId,
+
+    /// Identifier of the first message in the thread, if the link points to a
+    /// message in a thread (`?thread=...`).
+    pub thread_starter_msg_id: Option<MessageId>,
+
+    /// Identifier of the comment on the channel post, if the link points to a
+    /// comment (`?comment=...`).
+    pub comment_id: Option<MessageId>,
+}
+
+impl MessageLink {
+    /// Parses a link to a message, the scheme may be omitted (e.g.
+    /// `t.me/username/56?thread=7`).
+    ///
+    /// Returns `None` if `link` is not a link to a message.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use teloxide_core::types::{ChatId, MessageId, MessageLink, Recipient};
+    ///
+    /// let link = MessageLink::parse("t.me/c/1234/56").unwrap();
+    /// assert_eq!(link.chat, Recipient::Id(ChatId(-1000000001234)));
+    /// assert_eq!(link.message_id, MessageId(56));
+    ///
+    /// let link = MessageLink::parse("https://t.me/username/56?comment=9").unwrap();
+    /// assert_eq!(
+    ///     link.chat,
+    ///     Recipient::ChannelUsername("@username".to_owned())
+    /// );
+    /// assert_eq!(link.comment_id, Some(MessageId(9)));
+    /// ```
+    #[must_use]
+    pub fn parse(link: &str) -> Option<Self> {
+        Self::from_url(&formatting::parse_url(link)?)
+    }
+
+    /// Parses a link to a message.
+    ///
+    /// Returns `None` if `url` is not a link to a message.
+    #[must_use]
+    pub fn from_url(url: &Url) -> Option<Self> {
+        if !matches!(url.scheme(), "http" | "https") || !TME_HOSTS.contains(&url.host_str()?) {
+            return None;
+        }
+
+        let segments: Vec<_> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        let (chat, message_id) = match segments[..] {
+            ["c", chat_id, message_id] => {
+                let chat_id = parse_digits::<u64>(chat_id)
+                    .filter(|id| (1..=MAX_BARE_CHANNEL_ID).contains(id))?;
+                let chat_id = BareChatId::Channel(chat_id).to_bot_api();
+
+                (Recipient::Id(chat_id), message_id)
+            }
+            [username, message_id] if is_username(username) => (
+                Recipient::ChannelUsername(format!("@{}", username)),
+                message_id,
+            ),
+            _ => return None,
+        };
+        let message_id = parse_message_id(message_id)?;
+
+        let mut thread_starter_msg_id = None;
+        let mut comment_id = None;
+        for (key, value) in url.query_pairs() {
+            let id = || parse_message_id(&value);
+
+            match &*key {
+                "thread" => thread_starter_msg_id = Some(id()?),
+                "comment" => comment_id = Some(id()?),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            chat,
+            message_id,
+            thread_starter_msg_id,
+            comment_id,
+        })
+    }
+}
+
+fn parse_message_id(s: &str) -> Option<MessageId> {
+    // Ids of messages start from 1
+    i32::try_from(parse_digits::<u32>(s)?)
+        .ok()
+        .filter(|&id| id != 0)
+        .map(MessageId)
+}
+
+/// Parses a number which consists only of digits, unlike [`str::parse`] which
+/// also accepts a leading `+`.
+fn parse_digits<T: FromStr>(s: &str) -> Option<T> {
+    if !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+/// Hosts of `t.me` links.
+pub(crate) const TME_HOSTS: [&str; 5] = [
+    "t.me",
+    "telegram.me",
+    "telegram.dog",
+    "www.t.me",
+    "www.telegram.me",
+];
+
+/// Usernames of chats are `/[a-zA-Z][a-zA-Z0-9_]{4,31}/`.
+pub(crate) fn is_username(s: &str) -> bool {
+    (5..=32).contains(&s.len())
+        && s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{ChatId, Message, MessageId, MessageLink, Recipient};
+
+    #[test]
+    fn private() {
+        let chat_id = ChatId(-1001234567890);
+        let url = Message::url_of(chat_id, None, MessageId(56)).unwrap();
+
+        assert_eq!(
+            MessageLink::from_url(&url),
+            Some(MessageLink {
+                chat: Recipient::Id(chat_id),
+                message_id: MessageId(56),
+                thread_starter_msg_id: None,
+                comment_id: None,
+            })
+        );
+
+        let link = MessageLink::parse("t.me/c/997852516352/1").unwrap();
+        assert_eq!(link.chat, Recipient::Id(ChatId(-1997852516352)));
+    }
+
+    #[test]
+    fn thread_and_comment() {
+        let chat_id = ChatId(-1001234567890);
+
+        let url = Message::url_in_thread_of(chat_id, Some("username"), MessageId(7), MessageId(56))
+            .unwrap();
+        assert_eq!(
+            MessageLink::from_url(&url),
+            Some(MessageLink {
+                chat: Recipient::ChannelUsername("@username".to_owned()),
+                message_id: MessageId(56),
+                thread_starter_msg_id: Some(MessageId(7)),
+                comment_id: None,
+            })
+        );
+
+        let url = Message::comment_url_of(chat_id, None, MessageId(56), MessageId(9)).unwrap();
+        assert_eq!(
+            MessageLink::from_url(&url),
+            Some(MessageLink {
+                chat: Recipient::Id(chat_id),
+                message_id: MessageId(56),
+                thread_starter_msg_id: None,
+                comment_id: Some(MessageId(9)),
+            })
+        );
+    }
+
+    #[test]
+    fn without_scheme() {
+        let link = MessageLink::parse("t.me/username/56/").unwrap();
+
+        assert_eq!(
+            link.chat,
+            Recipient::ChannelUsername("@username".to_owned())
+        );
+        assert_eq!(link.message_id, MessageId(56));
+    }
+
+    #[test]
+    fn invalid() {
+        for link in [
+            "https://example.com/username/56",
+            "https://t.me/username",
+            "https://t.me/username/abc",
+            "https://t.me/c/0/56",
+            "https://t.me/c/9223372036854775807/1",
+            "https://t.me/c/99999999999999/1",
+            "https://t.me/c/1234/-5",
+            "https://t.me/c/1234/+5",
+            "https://t.me/c/1234/2147483648",
+            "https://t.me/username/0",
+            "https://t.me/c/1234/0",
+            "https://t.me/username/56?comment=-9",
+            "https://t.me/username/56?comment=0",
+            "https://t.me/username/56?thread=%2B7",
+            "https://t.me/c/+1234/56",
+            "https://t.me/c/1234",
+            "https://t.me/+AbCdEf/56",
+            "https://t.me/username/56?thread=x",
+            "tg://resolve?domain=username&post=56",
+        ] {
+            assert_eq!(MessageLink::parse(link), None, "{}", link);
+        }
+    }
+}
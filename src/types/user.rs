@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use crate::types::UserId;
+use crate::types::{
+    deep_link::{self, StartParamError},
+    UserId,
+};
 
 /// This object represents a Telegram user or bot.
 ///
@@ -80,6 +83,40 @@ impl User {
         self.tme_url().unwrap_or_else(|| self.url())
     }
 
+    /// Returns a deep link which opens a private chat with this bot and sends
+    /// it `/start <param>`, see [`deep_link::start_url`].
+    ///
+    /// Returns `None` if this user is not a bot or doesn't have a username.
+    #[must_use]
+    pub fn start_url(&self, param: &str) -> Option<Result<reqwest::Url, StartParamError>> {
+        Some(deep_link::start_url(self.bot_username()?, param))
+    }
+
+    /// Returns a deep link which adds this bot to a group and sends it
+    /// `/start <param>` there, see [`deep_link::start_group_url`].
+    ///
+    /// Returns `None` if this user is not a bot or doesn't have a username.
+    #[must_use]
+    pub fn start_group_url(&self, param: &str) -> Option<Result<reqwest::Url, StartParamError>> {
+        Some(deep_link::start_group_url(self.bot_username()?, param))
+    }
+
+    /// Returns a deep link which adds this bot to the attachment menu, see
+    /// [`deep_link::start_attach_url`].
+    ///
+    /// Returns `None` if this user is not a bot or doesn't have a username.
+    #[must_use]
+    pub fn start_attach_url(
+        &self,
+        param: Option<&str>,
+    ) -> Option<Result<reqwest::Url, StartParamError>> {
+        Some(deep_link::start_attach_url(self.bot_username()?, param))
+    }
+
+    fn bot_username(&self) -> Option<&str> {
+        self.username.as_deref().filter(|_| self.is_bot)
+    }
+
     /// Returns `true` if this is the special user used by telegram bot API to
     /// denote an anonymous user that sends messages on behalf of a group.
     #[must_use]
@@ -204,5 +241,38 @@ mod tests {
             user_b.preferably_tme_url(),
             "tg://user/?id=44".parse().unwrap()
         );
+
+        assert_eq!(user_a.start_url("abc"), None);
+    }
+
+    #[test]
+    fn deep_links() {
+        let bot = User {
+            id: UserId(45),
+            is_bot: true,
+            first_name: "Bot".to_owned(),
+            last_name: None,
+            username: Some("some_bot".to_owned()),
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        };
+
+        assert_eq!(
+            bot.start_url("abc"),
+            Some(Ok("https://t.me/some_bot?start=abc".parse().unwrap()))
+        );
+        assert_eq!(
+            bot.start_group_url("abc"),
+            Some(Ok("https://t.me/some_bot?startgroup=abc".parse().unwrap()))
+        );
+        assert_eq!(
+            bot.start_attach_url(None),
+            Some(Ok("https://t.me/some_bot?startattach".parse().unwrap()))
+        );
+        assert_eq!(
+            bot.start_url("a b"),
+            Some(Err(StartParamError::InvalidCharacter { c: ' ', offset: 1 }))
+        );
     }
 }